@@ -0,0 +1,85 @@
+use crate::fingerprint::finding_fingerprint;
+use crate::PackageDetails;
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::Path;
+
+pub const DEFAULT_ACKS_FILE: &str = ".walker-acks.json";
+pub const TOML_ACKS_FILE: &str = ".walker-acks.toml";
+
+// a finding someone has looked at and accepted. Without a fingerprint it covers every
+// finding of that rule in the package
+pub struct Ack {
+    pub package: String,
+    pub rule: String,
+    // from `fingerprint::finding_fingerprint`, as shown in JSON output
+    pub fingerprint: Option<String>,
+    // whether it matched anything in this run, acks that don't are stale
+    pub matched: bool,
+}
+
+// the acknowledgements file is JSON, like the config file
+//
+//   {
+//     "acknowledged": [
+//       { "package": "legacy-utils", "rule": "cjs-only", "reason": "frozen, to be removed" },
+//       { "package": "app", "rule": "broken-entrypoint", "fingerprint": "4f1c0e9a7b2d3c11" }
+//     ]
+//   }
+//
+// the reason is only for whoever reads the file next
+pub fn load_acks(path: &Path) -> io::Result<Vec<Ack>> {
+    let contents = fs::read_to_string(path)?;
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+    let v: Value = serde_json::from_str(&contents).map_err(|err| invalid(err.to_string()))?;
+    let entries = v["acknowledged"]
+        .as_array()
+        .ok_or_else(|| invalid("expected an 'acknowledged' array".to_string()))?;
+
+    entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let required = |key: &str| {
+                entry[key].as_str().map(String::from).ok_or_else(|| {
+                    invalid(format!("acknowledgement {} has no '{}'", index + 1, key))
+                })
+            };
+            Ok(Ack {
+                package: required("package")?,
+                rule: required("rule")?,
+                fingerprint: entry["fingerprint"].as_str().map(String::from),
+                matched: false,
+            })
+        })
+        .collect()
+}
+
+// moves acknowledged findings out of the reported ones, they're still listed but no longer
+// count against the run
+pub fn apply_acks(acks: &mut [Ack], packages: &mut [PackageDetails]) {
+    for package_details in packages {
+        let findings = mem::take(&mut package_details.findings);
+        for finding in findings {
+            let fingerprint = finding_fingerprint(&package_details.name, &finding);
+            let ack = acks.iter_mut().find(|ack| {
+                ack.package == package_details.name
+                    && ack.rule == finding.rule
+                    && ack
+                        .fingerprint
+                        .as_ref()
+                        .is_none_or(|acked| *acked == fingerprint)
+            });
+            match ack {
+                Some(ack) => {
+                    ack.matched = true;
+                    package_details.acknowledged_findings.push(finding);
+                }
+                None => package_details.findings.push(finding),
+            }
+        }
+    }
+}
@@ -0,0 +1,760 @@
+use crate::baseline;
+use crate::dependency_export::ExportFormat;
+use crate::graph_export::GraphFormat;
+use crate::output::{self, OutputFormat, Recipient, ReportLimits, WriteMode};
+use crate::pages::SortKey;
+use crate::portability::EdgePortability;
+use crate::progress::ProgressFormat;
+use crate::sampling::{self, SampleMode};
+use crate::typescript::TsResolution;
+use crate::vcs::{RemoteRepo, DEFAULT_CHURN_WINDOW};
+use std::env;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Copy)]
+pub enum GroupBy {
+    Tag,
+    Owner,
+}
+
+#[derive(Clone, Copy)]
+pub enum Breakdown {
+    Depth,
+    Folder,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DependencyClass {
+    Prod,
+    Dev,
+    Peer,
+    Optional,
+}
+
+impl DependencyClass {
+    pub const ALL: [DependencyClass; 4] = [
+        DependencyClass::Prod,
+        DependencyClass::Dev,
+        DependencyClass::Peer,
+        DependencyClass::Optional,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DependencyClass::Prod => "prod",
+            DependencyClass::Dev => "dev",
+            DependencyClass::Peer => "peer",
+            DependencyClass::Optional => "optional",
+        }
+    }
+
+    pub fn parse(label: &str) -> Option<DependencyClass> {
+        DependencyClass::ALL
+            .into_iter()
+            .find(|class| class.label() == label)
+    }
+
+    // the package.json field the class is declared in
+    pub fn field(self) -> &'static str {
+        match self {
+            DependencyClass::Prod => "dependencies",
+            DependencyClass::Dev => "devDependencies",
+            DependencyClass::Peer => "peerDependencies",
+            DependencyClass::Optional => "optionalDependencies",
+        }
+    }
+}
+
+pub struct Args {
+    // `walker show <package-name-or-path>` reports on a single package
+    pub show: Option<String>,
+    // `walker doctor` lists what to fix, most important first
+    pub doctor: bool,
+    // `walker graph [dot|mermaid|json|html]` prints the dependency graph instead of the report
+    pub graph: Option<GraphFormat>,
+    // `walker query <results file>` filters saved results, the --eval expressions or
+    // ones typed at a prompt
+    pub query: Option<PathBuf>,
+    pub eval: Vec<String>,
+    // which page of the matching packages a query shows
+    pub sort: Option<SortKey>,
+    pub offset: usize,
+    pub limit: Option<usize>,
+    // `walker diff <a> <b>` analyzes two trees, each a directory or a git ref of the
+    // repository walker runs in, and prints how their packages differ
+    pub diff: Option<(String, String)>,
+    pub timeout: Option<Duration>,
+    pub package_timeout: Option<Duration>,
+    pub metrics: bool,
+    // check the summaries agree with the package list before reporting, and fail if not
+    pub self_check: bool,
+    pub group_by: Option<GroupBy>,
+    pub breakdown: Option<Breakdown>,
+    // the dependency classes that count toward dependency totals, runtime ones by default
+    pub dependency_classes: Vec<DependencyClass>,
+    // list every directory the walk skipped, not just how many
+    pub list_skipped: bool,
+    // the most used external dependencies and the packages that pin them differently
+    pub dependency_usage: bool,
+    // how many packages declare each package.json field
+    pub field_census: bool,
+    // JSON reports on one line instead of indented
+    pub json_compact: bool,
+    pub run_manifest: Option<PathBuf>,
+    // exit code, its reason and finding counts, apart from the report
+    pub exit_summary_file: Option<PathBuf>,
+    // one row per (package, dependency) for tools that need more than the counts
+    pub emit_dependencies: Option<(ExportFormat, PathBuf)>,
+    pub save_results: Option<PathBuf>,
+    // progress of the walk, saved as it goes and resumed from when the file exists
+    pub checkpoint: Option<PathBuf>,
+    pub load_results: Option<PathBuf>,
+    // keep running, analyzing the tree again whenever a package.json changes
+    pub watch: bool,
+    // an earlier run's saved results to report the changes since
+    pub compare_with: Option<PathBuf>,
+    // like compare_with, and the run fails when a package regressed since. Growth in size
+    // past the threshold, a percentage, is one way to regress
+    pub baseline: Option<PathBuf>,
+    pub size_growth_threshold: f64,
+    // scan a shallow clone of this repository instead of the current directory
+    pub git: Option<RemoteRepo>,
+    // where clones are kept between runs, a temporary directory removed after the scan
+    // without one
+    pub git_cache: Option<PathBuf>,
+    pub output: OutputFormat,
+    pub max_name_len: Option<usize>,
+    pub owners: Option<PathBuf>,
+    // None means relative when the scan root is inside a git repository
+    pub relative_paths: Option<bool>,
+    pub includes: Vec<String>,
+    pub excludes: Vec<String>,
+    pub explain_excludes: Option<PathBuf>,
+    pub config: Option<PathBuf>,
+    // filled in once the config file is read
+    pub config_sha256: Option<String>,
+    // findings accepted earlier, .walker-acks.json in the scan root by default
+    pub acks: Option<PathBuf>,
+    pub skip_submodules: bool,
+    // don't cross into other filesystems, network mounts especially
+    pub one_file_system: bool,
+    // leave out directories holding more than this many bytes, and packages whose
+    // package.json is older than the duration
+    pub skip_dirs_larger_than: Option<u64>,
+    pub modified_since: Option<Duration>,
+    pub max_errors: Option<usize>,
+    pub max_manifest_size: Option<u64>,
+    // read package sources for what they import, slower than manifests alone
+    pub scan_sources: bool,
+    // when each package was created and last changed, and its commits in the window, from
+    // git, Mercurial or Sapling
+    pub git_history: bool,
+    pub churn_window: Duration,
+    // exit with an error when a dependency goes up the layers in the config file
+    pub fail_on_layer_violations: bool,
+    // only report packages with this edge portability
+    pub portability: Option<EdgePortability>,
+    // only report packages with one of these tags, from package.json or the config file
+    pub tags: Vec<String>,
+    // check that types resolve under this TypeScript moduleResolution
+    pub ts_resolution: Option<TsResolution>,
+    pub output_file: Option<PathBuf>,
+    pub output_mode: WriteMode,
+    // extra reports rendered from the same results, each in its own format and file
+    pub also_output: Vec<(OutputFormat, PathBuf)>,
+    pub report_limits: ReportLimits,
+    // every file written is encrypted to these, and stdout too
+    pub encrypt_to: Vec<Recipient>,
+    // strip the home directory and username from reports before they're written
+    pub redact: bool,
+    // None draws a running count when stderr is a terminal
+    pub progress_format: Option<ProgressFormat>,
+    pub sample: Option<SampleMode>,
+    // picked from the clock unless given, and reported so a sample can be reproduced
+    pub seed: u64,
+    // when the run started, for the exit summary's duration
+    pub started: Instant,
+}
+
+// parse durations like "90s", "10m", "1h", "30d", "500ms" - a bare number is taken as
+// seconds
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("Invalid duration '{}'", value))?;
+
+    match unit {
+        "ms" => Ok(Duration::from_millis(number)),
+        "" | "s" => Ok(Duration::from_secs(number)),
+        "m" => Ok(Duration::from_secs(number * 60)),
+        "h" => Ok(Duration::from_secs(number * 60 * 60)),
+        "d" => Ok(Duration::from_secs(number * 24 * 60 * 60)),
+        _ => Err(format!(
+            "Invalid duration unit '{}' in '{}', expected one of ms, s, m, h, d",
+            unit, value
+        )),
+    }
+}
+
+// a byte count with an optional binary unit, 10GB is 10 * 1024^3 bytes
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("Invalid size '{}'", value))?;
+
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        "T" | "TB" => 1 << 40,
+        _ => {
+            return Err(format!(
+                "Invalid size unit '{}' in '{}', expected one of B, KB, MB, GB, TB",
+                unit, value
+            ))
+        }
+    };
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("Invalid size '{}', too large", value))
+}
+
+// returns the value for an option given either as `--flag value` or `--flag=value`
+fn option_value(
+    flag: &str,
+    inline_value: Option<&str>,
+    rest: &mut impl Iterator<Item = String>,
+) -> Result<String, String> {
+    match inline_value {
+        Some(value) => Ok(value.to_string()),
+        None => rest
+            .next()
+            .ok_or_else(|| format!("Missing value for '{}'", flag)),
+    }
+}
+
+// pairs of flags that can't be given together
+const CONFLICTS: [(&str, &str); 18] = [
+    ("--load-results", "--save-results"),
+    ("--load-results", "--git"),
+    ("--load-results", "--checkpoint"),
+    // a resumed walk can't know which packages the earlier run's sample held
+    ("--checkpoint", "--sample"),
+    ("--checkpoint", "--sample-count"),
+    // there's nothing to watch in loaded results or a clone that's removed after the
+    // scan, and a watched scan never finishes to exit with a status
+    ("--watch", "--load-results"),
+    ("--watch", "--git"),
+    ("--watch", "--checkpoint"),
+    ("--watch", "--fail-on-layer-violations"),
+    ("--watch", "--baseline"),
+    ("--baseline", "--compare-with"),
+    ("--load-results", "--run-manifest"),
+    ("--sample", "--sample-count"),
+    ("--output-append", "--output-rotate"),
+    // an appended block would have to be decrypted on its own
+    ("--output-append", "--encrypt-to"),
+    ("--relative-paths", "--absolute-paths"),
+    ("--explain-excludes", "--output-file"),
+    ("--explain-excludes", "--run-manifest"),
+];
+
+// flags that only do something alongside another flag
+const REQUIRES: [(&str, &str); 4] = [
+    ("--git-cache", "--git"),
+    ("--size-growth-threshold", "--baseline"),
+    ("--output-append", "--output-file"),
+    ("--output-rotate", "--output-file"),
+];
+
+fn check_relationships(given: &[String]) -> Result<(), String> {
+    let is_given = |flag: &str| given.iter().any(|given| given == flag);
+
+    for (flag, other) in CONFLICTS {
+        if is_given(flag) && is_given(other) {
+            return Err(format!("'{}' can't be used with '{}'", flag, other));
+        }
+    }
+    for (flag, required) in REQUIRES {
+        if is_given(flag) && !is_given(required) {
+            return Err(format!("'{}' requires '{}'", flag, required));
+        }
+    }
+    Ok(())
+}
+
+pub fn parse_args() -> Result<Args, String> {
+    parse_args_from(env::args().skip(1))
+}
+
+pub fn parse_args_from(arguments: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut args = Args {
+        show: None,
+        doctor: false,
+        graph: None,
+        query: None,
+        eval: Vec::new(),
+        sort: None,
+        offset: 0,
+        limit: None,
+        diff: None,
+        timeout: None,
+        package_timeout: None,
+        metrics: false,
+        self_check: false,
+        group_by: None,
+        breakdown: None,
+        list_skipped: false,
+        dependency_usage: false,
+        field_census: false,
+        json_compact: false,
+        run_manifest: None,
+        exit_summary_file: None,
+        emit_dependencies: None,
+        save_results: None,
+        checkpoint: None,
+        load_results: None,
+        watch: false,
+        compare_with: None,
+        baseline: None,
+        size_growth_threshold: baseline::DEFAULT_SIZE_GROWTH_THRESHOLD,
+        git: None,
+        git_cache: None,
+        output: OutputFormat::Text,
+        max_name_len: None,
+        owners: None,
+        relative_paths: None,
+        includes: Vec::new(),
+        excludes: Vec::new(),
+        explain_excludes: None,
+        config: None,
+        config_sha256: None,
+        acks: None,
+        skip_submodules: false,
+        one_file_system: false,
+        skip_dirs_larger_than: None,
+        modified_since: None,
+        max_errors: None,
+        dependency_classes: vec![DependencyClass::Prod],
+        max_manifest_size: None,
+        scan_sources: false,
+        fail_on_layer_violations: false,
+        git_history: false,
+        churn_window: DEFAULT_CHURN_WINDOW,
+        portability: None,
+        tags: Vec::new(),
+        ts_resolution: None,
+        output_file: None,
+        output_mode: WriteMode::Replace,
+        also_output: Vec::new(),
+        report_limits: ReportLimits::default(),
+        encrypt_to: Vec::new(),
+        redact: false,
+        progress_format: None,
+        sample: None,
+        seed: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or(0),
+        started: Instant::now(),
+    };
+
+    let mut given = Vec::new();
+    let mut rest = arguments.peekable();
+    match rest.peek().map(String::as_str) {
+        Some("show") => {
+            rest.next();
+            match rest.next() {
+                Some(target) if !target.starts_with("--") => args.show = Some(target),
+                _ => return Err("'show' needs a package name or path".to_string()),
+            }
+        }
+        Some("doctor") => {
+            rest.next();
+            args.doctor = true;
+        }
+        Some("graph") => {
+            rest.next();
+            let format = match rest.next_if(|value| !value.starts_with("--")) {
+                Some(format) => GraphFormat::parse(&format)?,
+                None => GraphFormat::Dot,
+            };
+            args.graph = Some(format);
+        }
+        Some("query") => {
+            rest.next();
+            match rest.next() {
+                Some(path) if !path.starts_with("--") => args.query = Some(PathBuf::from(path)),
+                _ => return Err("'query' needs a results file".to_string()),
+            }
+        }
+        Some("diff") => {
+            rest.next();
+            let before = rest.next_if(|value| !value.starts_with("--"));
+            let after = rest.next_if(|value| !value.starts_with("--"));
+            match (before, after) {
+                (Some(before), Some(after)) => args.diff = Some((before, after)),
+                _ => return Err("'diff' needs two directories or git refs".to_string()),
+            }
+        }
+        // what walker does without a command, named for `walker analyze --git <url>`
+        Some("analyze") => {
+            rest.next();
+        }
+        _ => {}
+    }
+    while let Some(arg) = rest.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), Some(value)),
+            None => (arg.clone(), None),
+        };
+
+        match flag.as_str() {
+            "--timeout" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.timeout = Some(parse_duration(&value)?);
+            }
+            "--package-timeout" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.package_timeout = Some(parse_duration(&value)?);
+            }
+            "--max-errors" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                let max_errors = value
+                    .parse()
+                    .map_err(|_| format!("Invalid --max-errors '{}'", value))?;
+                args.max_errors = Some(max_errors);
+            }
+            "--report-max-errors" | "--report-max-packages" | "--report-max-items" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                let limit = value
+                    .parse()
+                    .map_err(|_| format!("Invalid {} '{}'", flag, value))?;
+                match flag.as_str() {
+                    "--report-max-errors" => args.report_limits.errors = Some(limit),
+                    "--report-max-packages" => args.report_limits.packages = Some(limit),
+                    _ => args.report_limits.list_items = Some(limit),
+                }
+            }
+            "--max-manifest-size" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.max_manifest_size = Some(parse_size(&value)?);
+            }
+            "--progress-format" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.progress_format = Some(ProgressFormat::parse(&value)?);
+            }
+            "--sample" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.sample = Some(SampleMode::Rate(sampling::parse_percentage(&value)?));
+            }
+            "--sample-count" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                let count = value
+                    .parse()
+                    .ok()
+                    .filter(|&count| count > 0)
+                    .ok_or_else(|| format!("Invalid --sample-count '{}'", value))?;
+                args.sample = Some(SampleMode::Count(count));
+            }
+            "--seed" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.seed = value
+                    .parse()
+                    .map_err(|_| format!("Invalid --seed '{}'", value))?;
+            }
+            "--metrics" => args.metrics = true,
+            "--self-check" => args.self_check = true,
+            "--watch" => args.watch = true,
+            "--skip-submodules" => args.skip_submodules = true,
+            "--one-file-system" => args.one_file_system = true,
+            "--skip-dirs-larger-than" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.skip_dirs_larger_than = Some(parse_size(&value)?);
+            }
+            "--modified-since" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.modified_since = Some(parse_duration(&value)?);
+            }
+            "--list-skipped" => args.list_skipped = true,
+            "--dependency-usage" => args.dependency_usage = true,
+            "--field-census" => args.field_census = true,
+            "--json-compact" => args.json_compact = true,
+            "--scan-sources" => args.scan_sources = true,
+            "--fail-on-layer-violations" => args.fail_on_layer_violations = true,
+            "--git-history" => args.git_history = true,
+            "--churn-window" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.churn_window = parse_duration(&value)?;
+            }
+            "--dep-classes" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.dependency_classes = value
+                    .split(',')
+                    .map(|label| {
+                        DependencyClass::parse(label.trim()).ok_or_else(|| {
+                            format!(
+                                "Invalid --dep-classes '{}', expected prod, dev, peer or optional",
+                                label
+                            )
+                        })
+                    })
+                    .collect::<Result<_, _>>()?;
+            }
+            "--eval" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.eval.push(value);
+            }
+            "--sort" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.sort = Some(SortKey::parse(&value)?);
+            }
+            "--offset" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.offset = value
+                    .parse()
+                    .map_err(|_| format!("Invalid --offset '{}'", value))?;
+            }
+            "--limit" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                let limit = value
+                    .parse()
+                    .ok()
+                    .filter(|&limit| limit > 0)
+                    .ok_or_else(|| format!("Invalid --limit '{}'", value))?;
+                args.limit = Some(limit);
+            }
+            "--tag" => args
+                .tags
+                .push(option_value(&flag, inline_value, &mut rest)?),
+            "--portability" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                let portability = EdgePortability::parse(&value).ok_or_else(|| {
+                    format!(
+                        "Invalid --portability '{}', expected portable, needs-shims or not-portable",
+                        value
+                    )
+                })?;
+                args.portability = Some(portability);
+            }
+            "--ts-resolution" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                let resolution = TsResolution::parse(&value).ok_or_else(|| {
+                    format!(
+                        "Invalid --ts-resolution '{}', expected node16, nodenext or bundler",
+                        value
+                    )
+                })?;
+                args.ts_resolution = Some(resolution);
+            }
+            "--group-by" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.group_by = match value.as_str() {
+                    "tag" => Some(GroupBy::Tag),
+                    "owner" => Some(GroupBy::Owner),
+                    _ => {
+                        return Err(format!(
+                            "Invalid --group-by '{}', expected tag or owner",
+                            value
+                        ))
+                    }
+                };
+            }
+            "--breakdown" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.breakdown = match value.as_str() {
+                    "depth" => Some(Breakdown::Depth),
+                    "folder" => Some(Breakdown::Folder),
+                    _ => {
+                        return Err(format!(
+                            "Invalid --breakdown '{}', expected depth or folder",
+                            value
+                        ))
+                    }
+                };
+            }
+            "--emit-dependencies" => {
+                let path = PathBuf::from(option_value(&flag, inline_value, &mut rest)?);
+                args.emit_dependencies = Some((ExportFormat::for_path(&path)?, path));
+            }
+            "--run-manifest" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.run_manifest = Some(PathBuf::from(value));
+            }
+            "--checkpoint" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.checkpoint = Some(PathBuf::from(value));
+            }
+            "--exit-summary-file" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.exit_summary_file = Some(PathBuf::from(value));
+            }
+            "--save-results" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.save_results = Some(PathBuf::from(value));
+            }
+            "--load-results" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.load_results = Some(PathBuf::from(value));
+            }
+            "--compare-with" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.compare_with = Some(PathBuf::from(value));
+            }
+            "--baseline" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.baseline = Some(PathBuf::from(value));
+            }
+            "--size-growth-threshold" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.size_growth_threshold = value
+                    .trim_end_matches('%')
+                    .parse()
+                    .ok()
+                    .filter(|threshold: &f64| *threshold >= 0.0)
+                    .ok_or_else(|| {
+                        format!(
+                            "Invalid --size-growth-threshold '{}', expected a percentage like 10%",
+                            value
+                        )
+                    })?;
+            }
+            "--git" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.git = Some(RemoteRepo::parse(&value)?);
+            }
+            "--git-cache" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.git_cache = Some(PathBuf::from(value));
+            }
+            "--output" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.output = OutputFormat::parse(&value)?;
+            }
+            "--max-name-len" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                let max_name_len = value
+                    .parse()
+                    .ok()
+                    .filter(|&max_name_len| max_name_len > 0)
+                    .ok_or_else(|| format!("Invalid --max-name-len '{}'", value))?;
+                args.max_name_len = Some(max_name_len);
+            }
+            "--output-file" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.output_file = Some(PathBuf::from(value));
+            }
+            // takes any number of format:path values, `--also-output csv:a.csv html:a.html`
+            "--also-output" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.also_output.push(output::parse_output_target(&value)?);
+                while let Some(value) = rest.next_if(|value| !value.starts_with("--")) {
+                    args.also_output.push(output::parse_output_target(&value)?);
+                }
+            }
+            "--redact" => args.redact = true,
+            "--encrypt-to" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.encrypt_to.push(Recipient::parse(&value));
+            }
+            "--output-append" => args.output_mode = WriteMode::Append,
+            "--output-rotate" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                let keep = value
+                    .parse()
+                    .ok()
+                    .filter(|&keep| keep > 0)
+                    .ok_or_else(|| format!("Invalid --output-rotate '{}'", value))?;
+                args.output_mode = WriteMode::Rotate(keep);
+            }
+            "--owners" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.owners = Some(PathBuf::from(value));
+            }
+            "--relative-paths" => {
+                args.relative_paths = match inline_value {
+                    None | Some("true") => Some(true),
+                    Some("false") => Some(false),
+                    Some(value) => {
+                        return Err(format!(
+                            "Invalid --relative-paths '{}', expected true or false",
+                            value
+                        ))
+                    }
+                };
+            }
+            "--absolute-paths" => args.relative_paths = Some(false),
+            "--include" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.includes.push(value);
+            }
+            "--exclude" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.excludes.push(value);
+            }
+            "--explain-excludes" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.explain_excludes = Some(PathBuf::from(value));
+            }
+            "--config" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.config = Some(PathBuf::from(value));
+            }
+            "--acks" => {
+                let value = option_value(&flag, inline_value, &mut rest)?;
+                args.acks = Some(PathBuf::from(value));
+            }
+            _ => return Err(format!("Unknown argument '{}'", arg)),
+        }
+        given.push(flag);
+    }
+
+    check_relationships(&given)?;
+    for (flag, given) in [
+        ("--eval", !args.eval.is_empty()),
+        ("--sort", args.sort.is_some()),
+        ("--offset", args.offset > 0),
+        ("--limit", args.limit.is_some()),
+    ] {
+        if given && args.query.is_none() {
+            return Err(format!(
+                "'{}' only works with `walker query <results file>`",
+                flag
+            ));
+        }
+    }
+    // both trees are scanned where they are
+    if args.diff.is_some() && (args.watch || args.git.is_some() || args.load_results.is_some()) {
+        return Err(
+            "`walker diff` can't be used with '--watch', '--git' or '--load-results'".to_string(),
+        );
+    }
+    output::check_recipients(&args.encrypt_to)?;
+
+    let mut destinations: Vec<&PathBuf> = args.output_file.iter().collect();
+    let extra_outputs = args.also_output.iter().map(|(_, path)| path);
+    for path in extra_outputs.chain(args.emit_dependencies.iter().map(|(_, path)| path)) {
+        if destinations.contains(&path) {
+            return Err(format!(
+                "'{}' is given as the destination of more than one output",
+                path.display()
+            ));
+        }
+        destinations.push(path);
+    }
+    Ok(args)
+}
@@ -0,0 +1,79 @@
+use crate::args::DependencyClass;
+use crate::identity;
+use crate::output::format_size;
+use crate::{supports_esm, PackageDetails};
+use std::path::PathBuf;
+
+// how much a package can grow from one run to the next before it counts as a regression
+pub const DEFAULT_SIZE_GROWTH_THRESHOLD: f64 = 10.0;
+
+// a package that got worse since the earlier run
+pub struct Regression {
+    pub name: String,
+    pub path: PathBuf,
+    // lost-esm, size-growth or new-dependency
+    pub kind: &'static str,
+    pub detail: String,
+}
+
+// compares each package with itself in the earlier run, however it moved or was bumped.
+// Added and removed packages are changes, not regressions
+pub fn regressions(
+    before: &[PackageDetails],
+    after: &[PackageDetails],
+    classes: &[DependencyClass],
+    size_growth_threshold: f64,
+) -> Vec<Regression> {
+    let mut regressions = Vec::new();
+    for (earlier, index) in identity::pair_up(before, after) {
+        let (earlier, package_details) = (&before[earlier], &after[index]);
+        let mut regression = |kind: &'static str, detail: String| {
+            regressions.push(Regression {
+                name: package_details.name.clone(),
+                path: package_details.path.clone(),
+                kind,
+                detail,
+            })
+        };
+
+        if supports_esm(&earlier.module_support) && !supports_esm(&package_details.module_support) {
+            regression("lost-esm", "no longer supports ESM".to_string());
+        }
+
+        if earlier.size > 0 {
+            let growth = (package_details.size as f64 / earlier.size as f64 - 1.0) * 100.0;
+            if growth > size_growth_threshold {
+                regression(
+                    "size-growth",
+                    format!(
+                        "grew {:.1}% from {} to {}",
+                        growth,
+                        format_size(earlier.size),
+                        format_size(package_details.size)
+                    ),
+                );
+            }
+        }
+
+        let new_dependencies: Vec<&str> = package_details
+            .declared_dependencies
+            .iter()
+            .filter(|dependency| classes.contains(&dependency.class))
+            .filter(|dependency| {
+                !earlier
+                    .declared_dependencies
+                    .iter()
+                    .any(|earlier| earlier.name == dependency.name)
+            })
+            .map(|dependency| dependency.name.as_str())
+            .collect();
+        if !new_dependencies.is_empty() {
+            regression(
+                "new-dependency",
+                format!("new dependencies: {}", new_dependencies.join(", ")),
+            );
+        }
+    }
+    regressions.sort_by(|a, b| a.path.cmp(&b.path).then(a.kind.cmp(b.kind)));
+    regressions
+}
@@ -0,0 +1,98 @@
+use serde_json::Value;
+use std::borrow::Cow;
+
+// how a resolver picks a package entrypoint: the conditions it matches in 'exports' and,
+// when there is no 'exports' field, the package.json fields it falls back to in order
+struct Resolver {
+    name: &'static str,
+    import_conditions: &'static [&'static str],
+    require_conditions: &'static [&'static str],
+    main_fields: &'static [&'static str],
+}
+
+const RESOLVERS: [Resolver; 3] = [
+    Resolver {
+        name: "node",
+        import_conditions: &["node", "import"],
+        require_conditions: &["node", "require"],
+        main_fields: &["main"],
+    },
+    Resolver {
+        name: "webpack",
+        import_conditions: &["webpack", "browser", "module", "import"],
+        require_conditions: &["webpack", "browser", "module", "require"],
+        main_fields: &["browser", "module", "main"],
+    },
+    Resolver {
+        name: "vite",
+        import_conditions: &["browser", "module", "development", "import"],
+        require_conditions: &["browser", "module", "development", "require"],
+        main_fields: &["browser", "module", "jsnext:main", "jsnext", "main"],
+    },
+];
+
+#[derive(Clone)]
+pub struct EntrypointResolution {
+    pub resolver: Cow<'static, str>,
+    // None when the package doesn't expose an entrypoint for that kind of import
+    pub import: Option<String>,
+    pub require: Option<String>,
+}
+
+fn normalize(path: &str) -> String {
+    path.trim_start_matches("./").to_string()
+}
+
+// walks an 'exports' target the way node does: the first key in object order that is
+// either "default" or an active condition wins, arrays are tried as fallbacks
+pub fn resolve_export_target(target: &Value, conditions: &[&str]) -> Option<String> {
+    match target {
+        Value::String(path) => Some(normalize(path)),
+        Value::Array(targets) => targets
+            .iter()
+            .find_map(|target| resolve_export_target(target, conditions)),
+        Value::Object(nested) => nested
+            .iter()
+            .filter(|(key, _)| *key == "default" || conditions.contains(&key.as_str()))
+            .find_map(|(_, target)| resolve_export_target(target, conditions)),
+        _ => None,
+    }
+}
+
+fn resolve(v: &Value, conditions: &[&str], main_fields: &[&str]) -> Option<String> {
+    let exports = &v["exports"];
+    if !exports.is_null() {
+        let root = match exports.as_object() {
+            Some(subpaths) if subpaths.keys().any(|key| key.starts_with('.')) => &exports["."],
+            _ => exports,
+        };
+        return resolve_export_target(root, conditions);
+    }
+
+    let main_field = main_fields
+        .iter()
+        .find_map(|field| v[*field].as_str())
+        .unwrap_or("index.js");
+    Some(normalize(main_field))
+}
+
+pub fn simulate_resolution(v: &Value) -> Vec<EntrypointResolution> {
+    RESOLVERS
+        .iter()
+        .map(|resolver| EntrypointResolution {
+            resolver: resolver.name.into(),
+            import: resolve(v, resolver.import_conditions, resolver.main_fields),
+            require: resolve(v, resolver.require_conditions, resolver.main_fields),
+        })
+        .collect()
+}
+
+// true when the resolvers don't all agree on the file loaded for `import` or `require`
+pub fn resolutions_diverge(resolutions: &[EntrypointResolution]) -> bool {
+    let diverges = |select: fn(&EntrypointResolution) -> &Option<String>| {
+        resolutions
+            .windows(2)
+            .any(|pair| select(&pair[0]) != select(&pair[1]))
+    };
+    diverges(|resolution| &resolution.import) || diverges(|resolution| &resolution.require)
+}
@@ -0,0 +1,230 @@
+use crate::error_context::{errors_from_json, errors_to_json, ContextError};
+use crate::output::{write_atomic, Redactor};
+use crate::results_file::{
+    frame, package_from_json, package_to_json, skipped_from_json, skipped_to_json, unframe,
+};
+use crate::{record_error, PackageDetails, Scan, WalkStatistics};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const FORMAT: &str = "walker-checkpoint";
+const FORMAT_VERSION: u64 = 1;
+
+// how often progress is written out, every save rewrites all the packages found so far
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(30);
+
+// what the walk had counted, or what one directory added to it
+#[derive(Clone, Copy, Default)]
+pub struct Counted {
+    directories: usize,
+    files: usize,
+    bytes: u64,
+}
+
+impl Counted {
+    pub fn of(walk: &WalkStatistics) -> Counted {
+        Counted {
+            directories: walk.directories_scanned,
+            files: walk.files_processed,
+            bytes: walk.bytes_read,
+        }
+    }
+
+    pub fn since(self, before: Counted) -> Counted {
+        Counted {
+            directories: self.directories - before.directories,
+            files: self.files - before.files,
+            bytes: self.bytes - before.bytes,
+        }
+    }
+}
+
+// the directories a scan has walked to the end, so a later run can pick up where it
+// stopped. Only whole subtrees are kept, a directory the scan was still inside when it
+// stopped is walked again, skipping the subdirectories it had already finished
+pub struct Checkpoint {
+    path: PathBuf,
+    // with --redact, the checkpoint is written redacted too
+    redactor: Option<Redactor>,
+    // none of them inside another, with what each added to the walk's counters
+    completed: BTreeMap<PathBuf, Counted>,
+    last_saved: Instant,
+}
+
+// what an earlier run had found by its last checkpoint
+pub struct Restored {
+    pub packages: Vec<PackageDetails>,
+    pub errors: Vec<ContextError>,
+    pub walk: WalkStatistics,
+}
+
+// the checkpoint at path, and what it restores when an earlier run of the same scan left
+// one behind
+pub fn load(path: &Path, root: &Path, redact: bool) -> io::Result<(Checkpoint, Option<Restored>)> {
+    let mut checkpoint = Checkpoint {
+        path: path.to_path_buf(),
+        redactor: redact.then(Redactor::from_env),
+        completed: BTreeMap::new(),
+        last_saved: Instant::now(),
+    };
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok((checkpoint, None)),
+        Err(err) => return Err(err),
+    };
+    let (header, payload) = unframe(&contents, FORMAT, FORMAT_VERSION, "checkpoint")?;
+    if header["scan_root"].as_str() != Some(root.display().to_string().as_str()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "the checkpoint is for a scan of {}, not {}",
+                header["scan_root"].as_str().unwrap_or_default(),
+                root.display()
+            ),
+        ));
+    }
+
+    let v: Value = serde_json::from_str(&payload)?;
+    let mut walk = WalkStatistics::default();
+    let completed = v["completed"].as_array().into_iter().flatten();
+    for (dir, counted) in completed.filter_map(|entry| {
+        let counted = Counted {
+            directories: entry[1].as_u64()? as usize,
+            files: entry[2].as_u64()? as usize,
+            bytes: entry[3].as_u64()?,
+        };
+        Some((PathBuf::from(entry[0].as_str()?), counted))
+    }) {
+        walk.directories_scanned += counted.directories;
+        walk.files_processed += counted.files;
+        walk.bytes_read += counted.bytes;
+        checkpoint.completed.insert(dir, counted);
+    }
+    walk.skipped = skipped_from_json(&v["skipped"]);
+
+    let restored = Restored {
+        packages: v["packages"]
+            .as_array()
+            .map(|packages| packages.iter().map(package_from_json).collect())
+            .unwrap_or_default(),
+        errors: errors_from_json(&v["errors"]),
+        walk,
+    };
+    Ok((checkpoint, Some(restored)))
+}
+
+impl Checkpoint {
+    pub fn is_completed(&self, dir: &Path) -> bool {
+        self.completed.contains_key(dir)
+    }
+
+    fn covers(&self, path: &Path) -> bool {
+        path.ancestors().any(|dir| self.completed.contains_key(dir))
+    }
+
+    // dir and everything under it is walked, it replaces the subdirectories recorded
+    // before it
+    pub fn complete(&mut self, dir: &Path, counted: Counted) {
+        let inside: Vec<PathBuf> = self
+            .completed
+            .range::<Path, _>((Bound::Included(dir), Bound::Unbounded))
+            .map(|(path, _)| path)
+            .take_while(|path| path.starts_with(dir))
+            .cloned()
+            .collect();
+        for path in inside {
+            self.completed.remove(&path);
+        }
+        self.completed.insert(dir.to_path_buf(), counted);
+    }
+
+    // the packages and skipped directories of the finished subtrees. Errors can't be told
+    // apart by where they happened, so all of them are kept
+    fn save(&mut self, scan: &Scan) -> io::Result<()> {
+        let completed: Vec<Value> = self
+            .completed
+            .iter()
+            .map(|(dir, counted)| {
+                json!([
+                    dir.display().to_string(),
+                    counted.directories,
+                    counted.files,
+                    counted.bytes
+                ])
+            })
+            .collect();
+        let packages: Vec<Value> = scan
+            .packages
+            .iter()
+            .filter(|p| self.covers(&p.path))
+            .map(package_to_json)
+            .collect();
+        let skipped: Vec<(PathBuf, _)> = scan
+            .walk
+            .skipped
+            .iter()
+            .filter(|(path, _)| self.covers(path))
+            .map(|(path, reason)| (path.clone(), *reason))
+            .collect();
+        let payload = serde_json::to_string(&json!({
+            "completed": completed,
+            "packages": packages,
+            "errors": errors_to_json(&scan.errors),
+            "skipped": skipped_to_json(&skipped),
+        }))?;
+        let header = json!({
+            "format": FORMAT,
+            "version": FORMAT_VERSION,
+            "scan_root": scan.root.display().to_string(),
+        });
+        write_atomic(
+            &self.path,
+            &frame(header, &payload, self.redactor.as_ref())?,
+        )?;
+        self.last_saved = Instant::now();
+        Ok(())
+    }
+}
+
+// writes the checkpoint when it's due, or whenever `now` is set
+pub fn save(scan: &mut Scan, now: bool) {
+    let mut checkpoint = match scan.checkpoint.take() {
+        Some(checkpoint) => checkpoint,
+        None => return,
+    };
+    if now || checkpoint.last_saved.elapsed() >= CHECKPOINT_INTERVAL {
+        if let Err(err) = checkpoint.save(scan) {
+            record_error(
+                scan,
+                format!(
+                    "Unable to write checkpoint {}: {}",
+                    checkpoint.path.display(),
+                    err
+                ),
+            );
+        }
+    }
+    scan.checkpoint = Some(checkpoint);
+}
+
+// a scan that walked the whole tree has nothing left to resume
+pub fn finish(scan: &mut Scan) {
+    if let Some(checkpoint) = scan.checkpoint.take() {
+        match fs::remove_file(&checkpoint.path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => record_error(
+                scan,
+                format!(
+                    "Unable to remove checkpoint {}: {}",
+                    checkpoint.path.display(),
+                    err
+                ),
+            ),
+            _ => {}
+        }
+    }
+}
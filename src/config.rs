@@ -0,0 +1,219 @@
+use crate::layers::Layer;
+use crate::output::ReportLimits;
+use crate::rules::{RuleSetting, Severity};
+use crate::sha256::sha256_hex;
+use crate::tags::PathTag;
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// JSON rather than TOML: serde_json already reads every package.json, and a TOML parser
+// would be a new dependency for this one file
+pub const DEFAULT_CONFIG_FILE: &str = ".walker.json";
+// what people tend to look for first, only warned about
+pub const TOML_CONFIG_FILE: &str = ".walker.toml";
+
+pub struct Config {
+    pub include_patterns: Vec<String>,
+    pub exclude_patterns: Vec<String>,
+    pub warn_unmatched_excludes: bool,
+    // of the file as read, for the run manifest. None without one
+    pub sha256: Option<String>,
+    pub max_manifest_size: Option<u64>,
+    pub layers: Vec<Layer>,
+    pub tags: Vec<PathTag>,
+    pub report_limits: ReportLimits,
+    pub rules: Vec<RuleSetting>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            include_patterns: Vec::new(),
+            exclude_patterns: Vec::new(),
+            warn_unmatched_excludes: true,
+            sha256: None,
+            max_manifest_size: None,
+            layers: Vec::new(),
+            tags: Vec::new(),
+            report_limits: ReportLimits::default(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+fn string_list(v: &Value, key: &str) -> Result<Vec<String>, String> {
+    match &v[key] {
+        Value::Null => Ok(Vec::new()),
+        Value::Array(values) => values
+            .iter()
+            .map(|value| match value.as_str() {
+                Some(value) => Ok(value.to_string()),
+                None => Err(format!("'{}' must only contain strings", key)),
+            })
+            .collect(),
+        _ => Err(format!("'{}' must be an array of strings", key)),
+    }
+}
+
+fn bool_or(v: &Value, key: &str, default: bool) -> Result<bool, String> {
+    match &v[key] {
+        Value::Null => Ok(default),
+        Value::Bool(value) => Ok(*value),
+        _ => Err(format!("'{}' must be true or false", key)),
+    }
+}
+
+fn size(v: &Value, key: &str) -> Result<Option<u64>, String> {
+    match &v[key] {
+        Value::Null => Ok(None),
+        value => match value.as_u64() {
+            Some(size) => Ok(Some(size)),
+            None => Err(format!("'{}' must be a size in bytes", key)),
+        },
+    }
+}
+
+fn count(v: &Value, key: &str) -> Result<Option<usize>, String> {
+    match &v[key] {
+        Value::Null => Ok(None),
+        value => match value.as_u64() {
+            Some(count) => Ok(Some(count as usize)),
+            None => Err(format!("'{}' must be a count", key)),
+        },
+    }
+}
+
+fn report_limits(v: &Value, key: &str) -> Result<ReportLimits, String> {
+    let limits = &v[key];
+    if !limits.is_null() && !limits.is_object() {
+        return Err(format!("'{}' must be an object", key));
+    }
+    Ok(ReportLimits {
+        errors: count(limits, "errors")?,
+        packages: count(limits, "packages")?,
+        list_items: count(limits, "list_items")?,
+    })
+}
+
+fn layers(v: &Value, key: &str) -> Result<Vec<Layer>, String> {
+    let entries = match &v[key] {
+        Value::Null => return Ok(Vec::new()),
+        Value::Array(entries) => entries,
+        _ => return Err(format!("'{}' must be an array of layers", key)),
+    };
+    let mut layers: Vec<Layer> = Vec::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let invalid = |message: String| format!("layer {}: {}", index + 1, message);
+        let name = match entry["name"].as_str() {
+            Some(name) if !name.is_empty() => name.to_string(),
+            _ => return Err(invalid("needs a 'name'".to_string())),
+        };
+        if layers.iter().any(|layer| layer.name == name) {
+            return Err(invalid(format!("'{}' is listed twice", name)));
+        }
+        let layer = Layer {
+            name,
+            paths: string_list(entry, "paths").map_err(invalid)?,
+            tags: string_list(entry, "tags").map_err(invalid)?,
+        };
+        if layer.paths.is_empty() && layer.tags.is_empty() {
+            return Err(invalid("needs 'paths' or 'tags'".to_string()));
+        }
+        layers.push(layer);
+    }
+    Ok(layers)
+}
+
+fn path_tags(v: &Value, key: &str) -> Result<Vec<PathTag>, String> {
+    let entries = match &v[key] {
+        Value::Null => return Ok(Vec::new()),
+        Value::Object(entries) => entries,
+        _ => return Err(format!("'{}' must map tags to arrays of paths", key)),
+    };
+    let mut tags = Vec::new();
+    for (tag, paths) in entries.iter() {
+        if tag.is_empty() {
+            return Err(format!("'{}' can't have an empty tag", key));
+        }
+        let paths = match paths.as_array() {
+            Some(paths) if paths.iter().all(Value::is_string) => paths
+                .iter()
+                .filter_map(|path| path.as_str().map(String::from))
+                .collect(),
+            _ => return Err(format!("tag '{}' must be an array of path globs", tag)),
+        };
+        tags.push(PathTag {
+            tag: tag.clone(),
+            paths,
+        });
+    }
+    Ok(tags)
+}
+
+fn rule_settings(v: &Value, key: &str) -> Result<Vec<RuleSetting>, String> {
+    let entries = match &v[key] {
+        Value::Null => return Ok(Vec::new()),
+        Value::Object(entries) => entries,
+        _ => return Err(format!("'{}' must map rules to off, warn or error", key)),
+    };
+    let mut settings = Vec::new();
+    for (rule, severity) in entries.iter() {
+        let severity = match severity.as_str().and_then(Severity::parse) {
+            Some(severity) => severity,
+            None => return Err(format!("rule '{}' must be off, warn or error", rule)),
+        };
+        settings.push(RuleSetting {
+            rule: rule.clone(),
+            severity,
+        });
+    }
+    Ok(settings)
+}
+
+// the config file is JSON, like the package.json files it sits next to
+//
+//   {
+//     "include_patterns": ["packages/", "apps/"],
+//     "exclude_patterns": ["node_modules/", "!packages/vendored/node_modules/"],
+//     "warn_unmatched_excludes": true,
+//     "max_manifest_size": 16777216,
+//     "layers": [
+//       { "name": "apps", "paths": ["apps/**"] },
+//       { "name": "features", "paths": ["features/**"] },
+//       { "name": "core", "paths": ["packages/core/**"], "tags": ["core"] }
+//     ],
+//     "tags": { "legacy": ["packages/old/**"], "tools": ["scripts/*"] },
+//     "report_limits": { "errors": 1000, "packages": 5000, "list_items": 100 },
+//     "rules": { "missing-exports": "error", "cjs-only": "off", "missing-engines": "warn" }
+//   }
+//
+// rules warn unless set otherwise, "error" fails the run. missing-engines and no-types
+// are only checked when listed
+//
+// a path of "-" reads the same JSON from stdin instead
+pub fn load_config(path: &Path) -> io::Result<Config> {
+    let contents = match path == Path::new("-") {
+        true => io::read_to_string(io::stdin())?,
+        false => fs::read_to_string(path)?,
+    };
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+    let v: Value = serde_json::from_str(&contents).map_err(|err| invalid(err.to_string()))?;
+    if !v.is_object() {
+        return Err(invalid("expected a JSON object".to_string()));
+    }
+
+    Ok(Config {
+        include_patterns: string_list(&v, "include_patterns").map_err(invalid)?,
+        exclude_patterns: string_list(&v, "exclude_patterns").map_err(invalid)?,
+        warn_unmatched_excludes: bool_or(&v, "warn_unmatched_excludes", true).map_err(invalid)?,
+        sha256: Some(sha256_hex(contents.as_bytes())),
+        max_manifest_size: size(&v, "max_manifest_size").map_err(invalid)?,
+        layers: layers(&v, "layers").map_err(invalid)?,
+        tags: path_tags(&v, "tags").map_err(invalid)?,
+        report_limits: report_limits(&v, "report_limits").map_err(invalid)?,
+        rules: rule_settings(&v, "rules").map_err(invalid)?,
+    })
+}
@@ -0,0 +1,102 @@
+use crate::PackageDetails;
+use serde_json::{json, Map, Value};
+use std::io::{self, Write};
+use std::path::Path;
+
+#[derive(Clone, Copy)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    // from the extension of the file the rows are written to
+    pub fn for_path(path: &Path) -> Result<ExportFormat, String> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("csv") => Ok(ExportFormat::Csv),
+            Some("json") => Ok(ExportFormat::Json),
+            _ => Err(format!(
+                "Invalid --emit-dependencies '{}', expected a .csv or .json file",
+                path.display()
+            )),
+        }
+    }
+}
+
+const COLUMNS: [&str; 7] = [
+    "package",
+    "package_version",
+    "package_path",
+    "dependency",
+    "class",
+    "spec",
+    "resolved",
+];
+
+// quoted only when it has to be, like most CSV writers
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+// one row per declared dependency of every package. The resolved version comes from the
+// package's own package-lock.json and is empty without one
+fn rows(packages: &[PackageDetails]) -> impl Iterator<Item = [String; 7]> + '_ {
+    packages.iter().flat_map(|package_details| {
+        package_details
+            .declared_dependencies
+            .iter()
+            .map(move |dependency| {
+                let resolved = package_details
+                    .lockfile
+                    .as_ref()
+                    .and_then(|lockfile| lockfile.resolved.get(&dependency.name))
+                    .cloned()
+                    .unwrap_or_default();
+                [
+                    package_details.name.clone(),
+                    package_details.version.clone(),
+                    package_details.path.display().to_string(),
+                    dependency.name.clone(),
+                    dependency.class.label().to_string(),
+                    dependency.spec.clone(),
+                    resolved,
+                ]
+            })
+    })
+}
+
+pub fn render_dependencies(
+    packages: &[PackageDetails],
+    format: ExportFormat,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            writeln!(out, "{}", COLUMNS.join(","))?;
+            for row in rows(packages) {
+                let fields: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+                writeln!(out, "{}", fields.join(","))?;
+            }
+        }
+        // written an object at a time, a large tree has a lot of rows
+        ExportFormat::Json => {
+            write!(out, "[")?;
+            for (index, row) in rows(packages).enumerate() {
+                let mut object = Map::new();
+                for (column, field) in COLUMNS.iter().zip(row) {
+                    object.insert(column.to_string(), json!(field));
+                }
+                let separator = match index {
+                    0 => "",
+                    _ => ",",
+                };
+                write!(out, "{}\n  {}", separator, Value::Object(object))?;
+            }
+            writeln!(out, "\n]")?;
+        }
+    }
+    Ok(())
+}
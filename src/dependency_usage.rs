@@ -0,0 +1,65 @@
+use crate::args::DependencyClass;
+use crate::{protocols, PackageDetails};
+use std::collections::{BTreeMap, HashSet};
+
+pub struct DependencyUsage<'a> {
+    pub name: &'a str,
+    pub declarations: usize,
+    // version spec -> the packages declaring it
+    pub specs: BTreeMap<&'a str, Vec<&'a str>>,
+}
+
+impl<'a> DependencyUsage<'a> {
+    // specs fewer packages use than the most common one, and who uses them. Those are
+    // the versions to consolidate onto the common one
+    pub fn odd_specs(&self) -> Vec<(&'a str, &[&'a str])> {
+        let most_common = self.specs.values().map(Vec::len).max().unwrap_or(0);
+        self.specs
+            .iter()
+            .filter(|(_, packages)| packages.len() < most_common)
+            .map(|(spec, packages)| (*spec, packages.as_slice()))
+            .collect()
+    }
+}
+
+// dependencies of the given classes that aren't packages in the scan, most declared
+// first. Local protocol dependencies always point into the repository and are left out
+pub fn external_usage<'a>(
+    packages: &'a [PackageDetails],
+    classes: &[DependencyClass],
+) -> Vec<DependencyUsage<'a>> {
+    let internal: HashSet<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+    let mut usage: BTreeMap<&str, DependencyUsage> = BTreeMap::new();
+    // a duplicate's declarations would be counted twice under the one name
+    for package_details in packages.iter().filter(|p| p.duplicate_of.is_none()) {
+        for dependency in &package_details.declared_dependencies {
+            if !classes.contains(&dependency.class)
+                || internal.contains(dependency.name.as_str())
+                || protocols::local_protocol(&dependency.spec).is_some()
+            {
+                continue;
+            }
+            let entry = usage
+                .entry(&dependency.name)
+                .or_insert_with(|| DependencyUsage {
+                    name: &dependency.name,
+                    declarations: 0,
+                    specs: BTreeMap::new(),
+                });
+            entry.declarations += 1;
+            entry
+                .specs
+                .entry(&dependency.spec)
+                .or_default()
+                .push(&package_details.name);
+        }
+    }
+
+    let mut usage: Vec<DependencyUsage> = usage.into_values().collect();
+    usage.sort_by(|a, b| {
+        b.declarations
+            .cmp(&a.declarations)
+            .then(b.specs.len().cmp(&a.specs.len()))
+    });
+    usage
+}
@@ -0,0 +1,143 @@
+use crate::{AnalysisResults, PackageDetails};
+use ansi_term::Colour::{Green, Red, Yellow};
+use std::fmt::Write;
+
+// packages listed under each action before the rest are summed up
+const LISTED_PACKAGES: usize = 5;
+
+fn plural(count: usize, noun: &str) -> String {
+    match count {
+        1 => format!("1 {}", noun),
+        _ => format!("{} {}s", count, noun),
+    }
+}
+
+struct Check {
+    action: &'static str,
+    // what each affected package shows for it
+    problems: fn(&PackageDetails) -> Vec<String>,
+}
+
+fn findings_for(package_details: &PackageDetails, rules: &[&str]) -> Vec<String> {
+    package_details
+        .findings
+        .iter()
+        .filter(|finding| rules.contains(&finding.rule.as_ref()))
+        .map(|finding| finding.message.clone())
+        .collect()
+}
+
+// most important first: a manifest that doesn't parse hides everything else about the
+// package, a broken entrypoint breaks every consumer, the rest only some of them
+const CHECKS: [Check; 5] = [
+    Check {
+        action: "Fix manifests and lockfiles that aren't valid JSON",
+        problems: |p| {
+            p.errors
+                .iter()
+                .filter(|error| {
+                    error.frames.iter().any(|frame| {
+                        frame.phase.starts_with("parsing package")
+                            || frame.phase == "reading package.json"
+                    })
+                })
+                .map(|error| error.to_string())
+                .collect()
+        },
+    },
+    Check {
+        action: "Point entrypoints at files that exist",
+        problems: |p| findings_for(p, &["broken-entrypoint", "files-excludes-entrypoint"]),
+    },
+    Check {
+        action: "Reorder export conditions: 'types' first, 'default' last",
+        problems: |p| findings_for(p, &["exports-unreachable-condition", "exports-types-order"]),
+    },
+    Check {
+        action: "Publish type declarations for TypeScript packages",
+        problems: |p| findings_for(p, &["missing-types"]),
+    },
+    Check {
+        action: "Keep a single lockfile per package",
+        problems: |p| findings_for(p, &["mixed-lockfiles"]),
+    },
+];
+
+// a short list of what to fix, in order, for people who don't want to learn every flag
+pub fn diagnose(results: &AnalysisResults) -> String {
+    let mut out = String::new();
+    let mut actions = Vec::new();
+    for check in &CHECKS {
+        let affected: Vec<(&PackageDetails, Vec<String>)> = results
+            .packages
+            .iter()
+            .map(|p| (p, (check.problems)(p)))
+            .filter(|(_, problems)| !problems.is_empty())
+            .collect();
+        if !affected.is_empty() {
+            actions.push((check.action, affected));
+        }
+    }
+
+    if actions.is_empty() {
+        writeln!(
+            out,
+            "{}",
+            Green.paint(format!(
+                "Checked {}, no problems found",
+                plural(results.packages.len(), "package")
+            ))
+        )
+        .unwrap();
+        return out;
+    }
+
+    writeln!(
+        out,
+        "Checked {}, {} to fix, most important first:",
+        plural(results.packages.len(), "package"),
+        plural(actions.len(), "thing")
+    )
+    .unwrap();
+    for (number, (action, affected)) in actions.iter().enumerate() {
+        let problems: usize = affected.iter().map(|(_, problems)| problems.len()).sum();
+        writeln!(
+            out,
+            "\n{}. {} ({} in {})",
+            number + 1,
+            Yellow.paint(*action),
+            plural(problems, "problem"),
+            plural(affected.len(), "package")
+        )
+        .unwrap();
+        for (package_details, problems) in affected.iter().take(LISTED_PACKAGES) {
+            for problem in problems {
+                writeln!(
+                    out,
+                    "   {}: {}",
+                    Green.paint(&package_details.name),
+                    problem
+                )
+                .unwrap();
+            }
+        }
+        if affected.len() > LISTED_PACKAGES {
+            writeln!(
+                out,
+                "   ... and {} more",
+                plural(affected.len() - LISTED_PACKAGES, "package")
+            )
+            .unwrap();
+        }
+    }
+
+    if results.partial() {
+        writeln!(
+            out,
+            "\n{}",
+            Red.paint("The scan stopped early, there may be more")
+        )
+        .unwrap();
+    }
+    out
+}
@@ -0,0 +1,185 @@
+use serde_json::{json, Value};
+use std::fmt::{self, Display};
+use std::io;
+use std::path::Path;
+
+// one step of what was being done when an error happened, like walking a directory or
+// parsing its package-lock.json
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Frame {
+    pub phase: String,
+    pub path: Option<String>,
+}
+
+// an error with the frames it passed through, outermost first. Displayed as a chain,
+// "analyze /repo/a: parsing package-lock.json: EOF while parsing", so it says where and
+// in which phase it happened, not just "IO error"
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ContextError {
+    pub frames: Vec<Frame>,
+    pub message: String,
+}
+
+impl ContextError {
+    fn within(mut self, phase: &str, path: Option<&Path>) -> ContextError {
+        let frame = Frame {
+            phase: phase.to_string(),
+            path: path.map(|path| path.display().to_string()),
+        };
+        self.frames.insert(0, frame);
+        self
+    }
+
+    pub fn at(self, phase: &str, path: &Path) -> ContextError {
+        self.within(phase, Some(path))
+    }
+
+    // rewrites a path prefix everywhere in the error, in its frames and its message
+    pub fn replace(&mut self, from: &str, to: &str) {
+        for frame in &mut self.frames {
+            if let Some(path) = &mut frame.path {
+                *path = path.replace(from, to);
+            }
+        }
+        self.message = self.message.replace(from, to);
+    }
+
+    pub fn to_json(&self) -> Value {
+        let frames: Vec<Value> = self
+            .frames
+            .iter()
+            .map(|frame| json!({ "phase": frame.phase, "path": frame.path }))
+            .collect();
+        json!({ "message": self.message, "context": frames })
+    }
+
+    // errors saved before they had frames are plain strings
+    pub fn from_json(v: &Value) -> Option<ContextError> {
+        if let Some(message) = v.as_str() {
+            return Some(message.into());
+        }
+        let frames = v["context"]
+            .as_array()?
+            .iter()
+            .filter_map(|frame| {
+                Some(Frame {
+                    phase: frame["phase"].as_str()?.to_string(),
+                    path: frame["path"].as_str().map(String::from),
+                })
+            })
+            .collect();
+        Some(ContextError {
+            frames,
+            message: v["message"].as_str()?.to_string(),
+        })
+    }
+}
+
+pub fn errors_to_json(errors: &[ContextError]) -> Vec<Value> {
+    errors.iter().map(ContextError::to_json).collect()
+}
+
+pub fn errors_from_json(v: &Value) -> Vec<ContextError> {
+    v.as_array()
+        .map(|errors| errors.iter().filter_map(ContextError::from_json).collect())
+        .unwrap_or_default()
+}
+
+impl Display for ContextError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for frame in &self.frames {
+            match &frame.path {
+                Some(path) => write!(f, "{} {}: ", frame.phase, path)?,
+                None => write!(f, "{}: ", frame.phase)?,
+            }
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<String> for ContextError {
+    fn from(message: String) -> ContextError {
+        ContextError {
+            frames: Vec::new(),
+            message,
+        }
+    }
+}
+
+impl From<&str> for ContextError {
+    fn from(message: &str) -> ContextError {
+        message.to_string().into()
+    }
+}
+
+impl From<io::Error> for ContextError {
+    fn from(err: io::Error) -> ContextError {
+        err.to_string().into()
+    }
+}
+
+impl From<serde_json::Error> for ContextError {
+    fn from(err: serde_json::Error) -> ContextError {
+        err.to_string().into()
+    }
+}
+
+// adds a frame to an error on its way out. Frames stack, the outermost call adds the
+// outermost frame
+pub trait ResultExt<T> {
+    fn context(self, phase: &str) -> Result<T, ContextError>;
+    fn context_at(self, phase: &str, path: &Path) -> Result<T, ContextError>;
+}
+
+impl<T, E: Into<ContextError>> ResultExt<T> for Result<T, E> {
+    fn context(self, phase: &str) -> Result<T, ContextError> {
+        self.map_err(|err| err.into().within(phase, None))
+    }
+
+    fn context_at(self, phase: &str, path: &Path) -> Result<T, ContextError> {
+        self.map_err(|err| err.into().within(phase, Some(path)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error() -> Result<(), ContextError> {
+        let parsed: Result<Value, _> = serde_json::from_str("{");
+        parsed.context("parsing package-lock.json")?;
+        Ok(())
+    }
+
+    #[test]
+    fn frames_stack_outermost_first() {
+        let error = parse_error()
+            .context_at("analyze", Path::new("/repo/a"))
+            .unwrap_err();
+        assert_eq!(error.frames.len(), 2);
+        assert_eq!(error.frames[0].phase, "analyze");
+        assert_eq!(error.frames[0].path.as_deref(), Some("/repo/a"));
+        assert_eq!(error.frames[1].path, None);
+        assert!(error
+            .to_string()
+            .starts_with("analyze /repo/a: parsing package-lock.json: EOF"));
+    }
+
+    #[test]
+    fn frames_round_trip_through_json() {
+        let mut error = parse_error()
+            .context_at("analyze", Path::new("/repo/a"))
+            .unwrap_err();
+        let v = error.to_json();
+        assert_eq!(v["context"][0]["phase"], "analyze");
+        assert_eq!(v["context"][1]["path"], Value::Null);
+        assert_eq!(ContextError::from_json(&v), Some(error.clone()));
+
+        error.replace("/repo/", "");
+        assert_eq!(error.frames[0].path.as_deref(), Some("a"));
+        assert_eq!(
+            ContextError::from_json(&json!("Unable to read history")),
+            Some("Unable to read history".into())
+        );
+    }
+}
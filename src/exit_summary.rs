@@ -0,0 +1,62 @@
+use crate::args::Args;
+use crate::output::{write_atomic, Redactor};
+use crate::AnalysisResults;
+use ansi_term::Colour::Red;
+use serde_json::json;
+use std::io;
+use std::process;
+
+// why the run ended, and how many findings of each severity it reported, for tools that
+// only need pass or fail and shouldn't have to parse the report. Written unencrypted,
+// it holds no names or paths
+pub fn write_exit_summary(args: &Args, code: i32, reason: &str, results: Option<&AnalysisResults>) {
+    let path = match &args.exit_summary_file {
+        Some(path) => path,
+        None => return,
+    };
+
+    let counts = results.map(|results| {
+        let (findings, suppressed_findings) = results.finding_counts();
+        let notes: usize = results.packages.iter().map(|p| p.notes.len()).sum();
+        json!({
+            "error": results.errors.len(),
+            "warning": findings,
+            "info": notes,
+            "acknowledged": results.acknowledged_count(),
+            "suppressed": suppressed_findings,
+        })
+    });
+    let summary = json!({
+        "walker_version": env!("CARGO_PKG_VERSION"),
+        "exit_code": code,
+        "reason": reason,
+        "partial": results.map(|results| results.partial()),
+        "packages": results.map(|results| results.packages.len()),
+        "counts": counts,
+        "duration_ms": args.started.elapsed().as_millis() as u64,
+    });
+
+    let written = serde_json::to_string_pretty(&summary)
+        .map_err(io::Error::from)
+        .map(|summary| match args.redact {
+            true => Redactor::from_env().redact(&summary),
+            false => summary,
+        })
+        .and_then(|summary| write_atomic(path, &summary));
+    if let Err(err) = written {
+        eprintln!(
+            "{}",
+            Red.paint(format!(
+                "Unable to write exit summary {}: {}",
+                path.display(),
+                err
+            ))
+        );
+    }
+}
+
+// ends the run with a summary of why
+pub fn exit(args: &Args, code: i32, reason: &str, results: Option<&AnalysisResults>) -> ! {
+    write_exit_summary(args, code, reason, results);
+    process::exit(code)
+}
@@ -0,0 +1,99 @@
+use crate::Finding;
+use serde_json::{Map, Value};
+
+// node picks the first condition that matches, in the order they're written, so
+// 'default' has to come last and TypeScript wants 'types' before anything else.
+// Relies on serde_json keeping object keys in file order (its preserve_order feature)
+
+// the conditions in the order a fix would put them: 'types' first, 'default' last and
+// everything else where it was
+fn fixed_order<'a>(conditions: &[&'a str]) -> Vec<&'a str> {
+    let mut fixed: Vec<&str> = conditions
+        .iter()
+        .filter(|&&condition| condition == "types")
+        .copied()
+        .collect();
+    fixed.extend(
+        conditions
+            .iter()
+            .filter(|&&condition| condition != "types" && condition != "default"),
+    );
+    fixed.extend(
+        conditions
+            .iter()
+            .filter(|&&condition| condition == "default"),
+    );
+    fixed
+}
+
+fn lint_conditions(conditions: &Map<String, Value>, location: &str, findings: &mut Vec<Finding>) {
+    let keys: Vec<&str> = conditions.keys().map(String::as_str).collect();
+    let fix = || format!("reorder as {}", fixed_order(&keys).join(", "));
+
+    if let Some(position) = keys.iter().position(|&key| key == "default") {
+        let unreachable = &keys[position + 1..];
+        if !unreachable.is_empty() {
+            findings.push(Finding {
+                rule: "exports-unreachable-condition".into(),
+                subject: location.to_string(),
+                message: format!(
+                    "{}: {} after 'default' can never match, {}",
+                    location,
+                    unreachable.join(", "),
+                    fix()
+                ),
+            });
+        }
+    }
+
+    if let Some(position) = keys.iter().position(|&key| key == "types") {
+        let before_types: Vec<&str> = keys[..position]
+            .iter()
+            .copied()
+            .filter(|&key| key != "default")
+            .collect();
+        if !before_types.is_empty() {
+            findings.push(Finding {
+                rule: "exports-types-order".into(),
+                subject: location.to_string(),
+                message: format!(
+                    "{}: 'types' comes after {} and is ignored by TypeScript, {}",
+                    location,
+                    before_types.join(", "),
+                    fix()
+                ),
+            });
+        }
+    }
+
+    for (condition, target) in conditions {
+        lint_target(target, &format!("{}.{}", location, condition), findings);
+    }
+}
+
+fn lint_target(target: &Value, location: &str, findings: &mut Vec<Finding>) {
+    match target {
+        Value::Object(conditions) => lint_conditions(conditions, location, findings),
+        // fallback arrays are tried in order, each entry can be a condition object
+        Value::Array(fallbacks) => {
+            for (index, fallback) in fallbacks.iter().enumerate() {
+                lint_target(fallback, &format!("{}[{}]", location, index), findings);
+            }
+        }
+        _ => {}
+    }
+}
+
+// checks the condition order of every subpath in the 'exports' field
+pub fn lint_exports(exports: &Value) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    match exports.as_object() {
+        Some(subpaths) if subpaths.keys().any(|key| key.starts_with('.')) => {
+            for (subpath, target) in subpaths {
+                lint_target(target, &format!("exports[\"{}\"]", subpath), &mut findings);
+            }
+        }
+        _ => lint_target(exports, "exports", &mut findings),
+    }
+    findings
+}
@@ -0,0 +1,63 @@
+use crate::PackageDetails;
+use std::collections::BTreeMap;
+
+// the fields that mark a modern manifest, always listed so their adoption shows up even
+// where nothing uses them yet
+const TRACKED_FIELDS: [&str; 12] = [
+    "type",
+    "exports",
+    "imports",
+    "module",
+    "browser",
+    "sideEffects",
+    "types",
+    "typesVersions",
+    "engines",
+    "files",
+    "workspaces",
+    "packageManager",
+];
+
+pub struct FieldUsage<'a> {
+    pub field: &'a str,
+    pub packages: usize,
+    pub percentage: f64,
+}
+
+pub struct FieldCensus<'a> {
+    // packages whose package.json could be read, the others declare nothing and aren't
+    // counted
+    pub packages: usize,
+    // most used first
+    pub fields: Vec<FieldUsage<'a>>,
+}
+
+// how many packages declare each package.json field
+pub fn field_census(packages: &[PackageDetails]) -> FieldCensus<'_> {
+    let mut counts: BTreeMap<&str, usize> =
+        TRACKED_FIELDS.into_iter().map(|field| (field, 0)).collect();
+    let mut read = 0;
+    for package_details in packages.iter().filter(|p| !p.manifest_fields.is_empty()) {
+        read += 1;
+        for field in &package_details.manifest_fields {
+            *counts.entry(field).or_default() += 1;
+        }
+    }
+
+    let mut fields: Vec<FieldUsage> = counts
+        .into_iter()
+        .map(|(field, count)| FieldUsage {
+            field,
+            packages: count,
+            percentage: match read {
+                0 => 0.0,
+                read => count as f64 * 100.0 / read as f64,
+            },
+        })
+        .collect();
+    fields.sort_by(|a, b| b.packages.cmp(&a.packages).then(a.field.cmp(b.field)));
+    FieldCensus {
+        packages: read,
+        fields,
+    }
+}
@@ -0,0 +1,109 @@
+use crate::filesystem::{FileSystem, FileType};
+use crate::glob::{gitignore_glob, glob_match};
+use crate::Finding;
+use serde_json::Value;
+use std::path::Path;
+
+// entrypoint fields npm doesn't add to the tarball on its own. 'main' isn't checked,
+// npm always packs it whatever 'files' says
+const ENTRYPOINT_FIELDS: [&str; 3] = ["module", "types", "typings"];
+
+// every file below `dir` as a '/' separated path relative to the package, leaving out
+// what npm never packs
+fn list_files(filesystem: &dyn FileSystem, dir: &Path, prefix: &str, files: &mut Vec<String>) {
+    let entries = match filesystem.read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        if name == "node_modules" || name == ".git" {
+            continue;
+        }
+        let relative = format!("{}{}", prefix, name);
+        match entry.file_type {
+            FileType::Dir => list_files(filesystem, &entry.path, &format!("{}/", relative), files),
+            _ => files.push(relative),
+        }
+    }
+}
+
+fn normalize(path: &str) -> &str {
+    let path = path.strip_prefix("./").unwrap_or(path);
+    path.trim_start_matches('/').trim_end_matches('/')
+}
+
+// a pattern naming a directory takes in everything below it
+fn pattern_matches(glob: &str, path: &str) -> bool {
+    glob_match(glob, path)
+        || path
+            .match_indices('/')
+            .any(|(index, _)| glob_match(glob, &path[..index]))
+}
+
+// like npm, the last pattern that matches decides, so "!dist/test" can take back part of
+// "dist"
+fn is_included(globs: &[(String, bool)], path: &str) -> bool {
+    globs
+        .iter()
+        .rev()
+        .find(|(glob, _)| pattern_matches(glob, path))
+        .is_some_and(|(_, negated)| !negated)
+}
+
+// checks the 'files' allowlist against what's on disk: patterns that match nothing
+// usually point at a build output that wasn't built, and entrypoints left out of the
+// tarball break every consumer
+pub fn check_files_field(
+    filesystem: &dyn FileSystem,
+    v: &Value,
+    package_dir: &Path,
+) -> Vec<Finding> {
+    let patterns = match v["files"].as_array() {
+        Some(patterns) => patterns,
+        None => return Vec::new(),
+    };
+    let globs: Vec<(String, bool)> = patterns
+        .iter()
+        .filter_map(|pattern| pattern.as_str())
+        .map(|pattern| match pattern.strip_prefix('!') {
+            Some(negated) => (gitignore_glob(normalize(negated)), true),
+            None => (gitignore_glob(normalize(pattern)), false),
+        })
+        .collect();
+
+    let mut files = Vec::new();
+    list_files(filesystem, package_dir, "", &mut files);
+
+    let mut findings = Vec::new();
+    for pattern in patterns.iter().filter_map(|pattern| pattern.as_str()) {
+        if pattern.starts_with('!') {
+            continue;
+        }
+        let glob = gitignore_glob(normalize(pattern));
+        if !files.iter().any(|file| pattern_matches(&glob, file)) {
+            findings.push(Finding {
+                rule: "files-unmatched".into(),
+                subject: pattern.to_string(),
+                message: format!("'files' pattern '{}' matches no files", pattern),
+            });
+        }
+    }
+
+    for field in ENTRYPOINT_FIELDS {
+        if let Some(entrypoint) = v[field].as_str() {
+            if !is_included(&globs, normalize(entrypoint)) {
+                findings.push(Finding {
+                    rule: "files-excludes-entrypoint".into(),
+                    subject: field.to_string(),
+                    message: format!(
+                        "'{}' entrypoint '{}' isn't included by 'files' and won't be published",
+                        field, entrypoint
+                    ),
+                });
+            }
+        }
+    }
+
+    findings
+}
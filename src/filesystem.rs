@@ -0,0 +1,135 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FileType {
+    File,
+    Dir,
+    Symlink,
+    Other,
+}
+
+impl From<fs::FileType> for FileType {
+    fn from(file_type: fs::FileType) -> FileType {
+        if file_type.is_symlink() {
+            FileType::Symlink
+        } else if file_type.is_dir() {
+            FileType::Dir
+        } else if file_type.is_file() {
+            FileType::File
+        } else {
+            FileType::Other
+        }
+    }
+}
+
+// an entry of a directory listing, its type not following symlinks
+pub struct DirEntry {
+    pub path: PathBuf,
+    pub file_type: FileType,
+}
+
+impl DirEntry {
+    pub fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+pub struct Metadata {
+    pub file_type: FileType,
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+    // the device it's on, what --one-file-system compares
+    pub device: u64,
+}
+
+pub type DirEntries<'a> = Box<dyn Iterator<Item = io::Result<DirEntry>> + 'a>;
+
+// everything the walk and the analysis read goes through this, so a tree can be scanned
+// from somewhere other than the disk, like memory in a test or a tarball that was never
+// extracted. Config, acknowledgement and output files are always on the real filesystem
+pub trait FileSystem {
+    fn read_dir(&self, path: &Path) -> io::Result<DirEntries<'_>>;
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + '_>>;
+    // follows symlinks
+    fn metadata(&self, path: &Path) -> io::Result<Metadata>;
+    fn symlink_metadata(&self, path: &Path) -> io::Result<Metadata>;
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.open(path)?.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.metadata(path)
+            .is_ok_and(|metadata| metadata.file_type == FileType::Dir)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.metadata(path)
+            .is_ok_and(|metadata| metadata.file_type == FileType::File)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.metadata(path).is_ok()
+    }
+}
+
+// the real filesystem, what walker scans unless given another
+pub struct StdFileSystem;
+
+fn metadata(metadata: fs::Metadata) -> Metadata {
+    Metadata {
+        file_type: metadata.file_type().into(),
+        len: metadata.len(),
+        modified: metadata.modified().ok(),
+        device: metadata.dev(),
+    }
+}
+
+impl FileSystem for StdFileSystem {
+    fn read_dir(&self, path: &Path) -> io::Result<DirEntries<'_>> {
+        let entries = fs::read_dir(path)?.map(|entry| {
+            let entry = entry?;
+            Ok(DirEntry {
+                path: entry.path(),
+                file_type: entry.file_type()?.into(),
+            })
+        });
+        Ok(Box::new(entries))
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + '_>> {
+        Ok(Box::new(File::open(path)?))
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        fs::metadata(path).map(metadata)
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> io::Result<Metadata> {
+        fs::symlink_metadata(path).map(metadata)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        fs::canonicalize(path)
+    }
+
+    // std reads straight into a buffer of the file's size
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+}
@@ -0,0 +1,233 @@
+use crate::glob::{gitignore_glob, glob_match, glob_match_below};
+use std::fmt::Write;
+
+// patterns follow .gitignore: they're matched against '/' separated paths relative to
+// the scan root, a trailing '/' only matches directories and a leading '!' reverses an
+// earlier pattern
+pub struct PathPattern {
+    pub pattern: String,
+    glob: String,
+    negated: bool,
+    dir_only: bool,
+    // set once the pattern has matched anything during the walk
+    matched: bool,
+}
+
+// include patterns are applied first and restrict the walk to the subtrees they match,
+// exclude patterns then remove paths from what's left. Without include patterns
+// everything is included
+pub struct Filters {
+    pub includes: Vec<PathPattern>,
+    pub excludes: Vec<PathPattern>,
+}
+
+pub fn parse_pattern(pattern: &str) -> PathPattern {
+    let (negated, rest) = match pattern.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+    let dir_only = rest.ends_with('/');
+
+    PathPattern {
+        pattern: pattern.to_string(),
+        glob: gitignore_glob(rest.trim_end_matches('/')),
+        negated,
+        dir_only,
+        matched: false,
+    }
+}
+
+fn pattern_matches(pattern: &PathPattern, relative_path: &str, is_dir: bool) -> bool {
+    (is_dir || !pattern.dir_only) && glob_match(&pattern.glob, relative_path)
+}
+
+// the last matching pattern decides, as in .gitignore
+fn last_match<'a>(
+    patterns: &'a [PathPattern],
+    relative_path: &str,
+    is_dir: bool,
+) -> Option<&'a PathPattern> {
+    patterns
+        .iter()
+        .rev()
+        .find(|pattern| pattern_matches(pattern, relative_path, is_dir))
+}
+
+// only looks at the path itself, the walk never descends into excluded directories
+fn is_excluded(excludes: &[PathPattern], relative_path: &str, is_dir: bool) -> bool {
+    last_match(excludes, relative_path, is_dir).is_some_and(|pattern| !pattern.negated)
+}
+
+// the include pattern that puts the path inside an included subtree, decided by the
+// deepest of the path and its parent directories that any include pattern matches
+fn including_pattern<'a>(
+    includes: &'a [PathPattern],
+    relative_path: &str,
+    is_dir: bool,
+) -> Option<(&'a PathPattern, String)> {
+    let components: Vec<&str> = relative_path.split('/').collect();
+    (1..=components.len()).rev().find_map(|depth| {
+        let path = components[..depth].join("/");
+        let path_is_dir = depth < components.len() || is_dir;
+        last_match(includes, &path, path_is_dir)
+            .filter(|pattern| !pattern.negated)
+            .map(|pattern| (pattern, path))
+    })
+}
+
+// directories above an included subtree have to be walked to reach it
+fn leads_to_include(includes: &[PathPattern], relative_path: &str) -> bool {
+    includes
+        .iter()
+        .any(|pattern| !pattern.negated && glob_match_below(&pattern.glob, relative_path))
+}
+
+fn is_included(includes: &[PathPattern], relative_path: &str, is_dir: bool) -> bool {
+    includes.is_empty()
+        || including_pattern(includes, relative_path, is_dir).is_some()
+        || (is_dir && leads_to_include(includes, relative_path))
+}
+
+pub fn is_filtered(filters: &mut Filters, relative_path: &str, is_dir: bool) -> bool {
+    if !is_included(&filters.includes, relative_path, is_dir) {
+        return true;
+    }
+
+    for pattern in &mut filters.excludes {
+        if !pattern.matched && pattern_matches(pattern, relative_path, is_dir) {
+            pattern.matched = true;
+        }
+    }
+    is_excluded(&filters.excludes, relative_path, is_dir)
+}
+
+// exclude patterns that didn't match a single path during the walk, usually typos
+pub fn unmatched_excludes(filters: &Filters) -> Vec<&str> {
+    filters
+        .excludes
+        .iter()
+        .filter(|pattern| !pattern.matched)
+        .map(|pattern| pattern.pattern.as_str())
+        .collect()
+}
+
+// describes how each pattern applies to the path and to the directories above it
+pub fn explain(filters: &Filters, relative_path: &str, is_dir: bool) -> String {
+    let mut out = String::new();
+    let relative_path = relative_path.trim_matches('/');
+
+    if !filters.includes.is_empty() {
+        match including_pattern(&filters.includes, relative_path, is_dir) {
+            Some((pattern, path)) => {
+                writeln!(out, "{}: included by '{}'", path, pattern.pattern).unwrap()
+            }
+            None if is_dir && leads_to_include(&filters.includes, relative_path) => writeln!(
+                out,
+                "{}: walked to reach the include patterns below it",
+                relative_path
+            )
+            .unwrap(),
+            None => {
+                writeln!(
+                    out,
+                    "{} is excluded because it is outside every include pattern",
+                    relative_path
+                )
+                .unwrap();
+                return out;
+            }
+        }
+    }
+
+    let components: Vec<&str> = relative_path.split('/').collect();
+    for depth in 1..=components.len() {
+        let path = components[..depth].join("/");
+        let path_is_dir = depth < components.len() || is_dir;
+
+        for pattern in &filters.excludes {
+            if pattern_matches(pattern, &path, path_is_dir) {
+                let action = match pattern.negated {
+                    true => "re-included",
+                    false => "excluded",
+                };
+                writeln!(out, "{}: {} by '{}'", path, action, pattern.pattern).unwrap();
+            }
+        }
+
+        if is_excluded(&filters.excludes, &path, path_is_dir) {
+            match depth < components.len() {
+                true => writeln!(
+                    out,
+                    "{} is excluded because its parent directory {} is excluded",
+                    relative_path, path
+                )
+                .unwrap(),
+                false => writeln!(out, "{} is excluded", relative_path).unwrap(),
+            }
+            return out;
+        }
+    }
+
+    writeln!(out, "{} is not excluded", relative_path).unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filters(includes: &[&str], excludes: &[&str]) -> Filters {
+        Filters {
+            includes: includes
+                .iter()
+                .map(|pattern| parse_pattern(pattern))
+                .collect(),
+            excludes: excludes
+                .iter()
+                .map(|pattern| parse_pattern(pattern))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn excludes_follow_gitignore() {
+        let mut filters = filters(&[], &["*.log", "!keep.log", "build/", "/dist"]);
+        assert!(is_filtered(&mut filters, "a/debug.log", false));
+        assert!(!is_filtered(&mut filters, "keep.log", false));
+        assert!(is_filtered(&mut filters, "a/build", true));
+        assert!(!is_filtered(&mut filters, "a/build", false));
+        assert!(is_filtered(&mut filters, "dist", true));
+        assert!(!is_filtered(&mut filters, "a/dist", true));
+    }
+
+    #[test]
+    fn includes_restrict_the_walk_to_their_subtrees() {
+        let mut filters = filters(&["packages/app"], &[]);
+        assert!(!is_filtered(&mut filters, "packages", true));
+        assert!(!is_filtered(&mut filters, "packages/app", true));
+        assert!(!is_filtered(
+            &mut filters,
+            "packages/app/src/index.js",
+            false
+        ));
+        assert!(is_filtered(&mut filters, "packages/lib", true));
+        assert!(is_filtered(&mut filters, "vendor", true));
+    }
+
+    #[test]
+    fn reports_excludes_that_never_matched() {
+        let mut filters = filters(&[], &["node_modules", "fixturs"]);
+        is_filtered(&mut filters, "a/node_modules", true);
+        is_filtered(&mut filters, "a/fixtures", true);
+        assert_eq!(unmatched_excludes(&filters), vec!["fixturs"]);
+    }
+
+    #[test]
+    fn explains_the_pattern_that_decided() {
+        let filters = filters(&[], &["vendor"]);
+        let explanation = explain(&filters, "vendor/lib/index.js", false);
+        assert!(explanation.contains("vendor: excluded by 'vendor'"));
+        assert!(explanation.contains("because its parent directory vendor is excluded"));
+        assert!(explain(&filters, "src", true).ends_with("src is not excluded\n"));
+    }
+}
@@ -0,0 +1,146 @@
+use crate::error_context::ContextError;
+use crate::sha256::sha256_hex;
+use crate::Finding;
+
+// words that are followed by a position, which moves whenever the file is edited
+const POSITION_WORDS: [&str; 5] = ["line", "column", "col", "byte", "offset"];
+
+fn is_path(token: &str) -> bool {
+    let token = token.trim_start_matches(['(', '\'', '"']);
+    let bytes = token.as_bytes();
+    token.starts_with('/')
+        || token.starts_with("~/")
+        || (bytes.len() > 2 && bytes[0].is_ascii_alphabetic() && &bytes[1..3] == b":\\")
+}
+
+// the file in "index.js:12" or "src/a.ts:3:7", without the position in it
+fn location_file(token: &str) -> Option<&str> {
+    let (file, position) = token.split_once(':')?;
+    let is_position = position
+        .split(':')
+        .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()));
+    match file.contains('.') && is_position {
+        true => Some(file),
+        false => None,
+    }
+}
+
+// the parts of an error that say what's wrong, without where the checkout lives or the
+// line a problem happens to be on
+fn salient(message: &str) -> String {
+    let mut previous = "";
+    message
+        .split_whitespace()
+        .map(|token| {
+            let word = token.trim_end_matches([':', ',', ')', '.', '\'', '"']);
+            let is_position = POSITION_WORDS.contains(&previous)
+                && !word.is_empty()
+                && word.bytes().all(|b| b.is_ascii_digit());
+            let salient = if is_path(word) {
+                "<path>"
+            } else if is_position {
+                "<n>"
+            } else if let Some(file) = location_file(word) {
+                file
+            } else {
+                token
+            };
+            previous = word;
+            salient
+        })
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+// identifies a finding or error across runs and checkouts: the same problem in the same
+// package hashes the same wherever the tree is and however the file around it changed.
+// Packages without a name go by their path, so theirs only hold with relative paths
+fn fingerprint(package_name: &str, rule: &str, subject: &str) -> String {
+    let identity = format!("{}\0{}\0{}", package_name, rule, subject);
+    sha256_hex(identity.as_bytes())[..16].to_string()
+}
+
+// findings go by what they're about rather than their message, which carries line numbers
+// and wording that change without the problem changing
+pub fn finding_fingerprint(package_name: &str, finding: &Finding) -> String {
+    fingerprint(package_name, &finding.rule, &finding.subject)
+}
+
+// errors have no rule or subject of their own, only the message with its paths and
+// positions taken out
+pub fn error_fingerprint(package_name: &str, error: &ContextError) -> String {
+    fingerprint(package_name, "error", &salient(&error.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finding(rule: &'static str, subject: &str, message: &str) -> Finding {
+        Finding {
+            rule: rule.into(),
+            subject: subject.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn findings_hold_when_lines_shift() {
+        let before = finding(
+            "top-level-await",
+            "index.js",
+            "index.js:1 uses top-level await, require() of it fails",
+        );
+        let after = finding(
+            "top-level-await",
+            "index.js",
+            "index.js:2 uses top-level await, require() of it fails",
+        );
+        assert_eq!(
+            finding_fingerprint("app", &before),
+            finding_fingerprint("app", &after)
+        );
+    }
+
+    #[test]
+    fn findings_differ_by_package_rule_and_subject() {
+        let base = finding("top-level-await", "index.js", "");
+        let fingerprint = finding_fingerprint("app", &base);
+        assert_ne!(fingerprint, finding_fingerprint("lib", &base));
+        assert_ne!(
+            fingerprint,
+            finding_fingerprint("app", &finding("top-level-await", "cli.js", ""))
+        );
+        assert_ne!(
+            fingerprint,
+            finding_fingerprint("app", &finding("dynamic-import", "index.js", ""))
+        );
+        assert_eq!(fingerprint.len(), 16);
+    }
+
+    #[test]
+    fn errors_ignore_paths_and_positions() {
+        assert_eq!(
+            error_fingerprint(
+                "app",
+                &"Unable to read /home/a/app/package.json: denied".into()
+            ),
+            error_fingerprint(
+                "app",
+                &"Unable to read /srv/ci/app/package.json: denied".into()
+            )
+        );
+        assert_eq!(
+            error_fingerprint("app", &"expected value at line 3 column 5".into()),
+            error_fingerprint("app", &"expected value at line 9 column 1".into())
+        );
+        assert_eq!(
+            error_fingerprint("app", &"failed to parse index.js:1:4".into()),
+            error_fingerprint("app", &"failed to parse index.js:8:2".into())
+        );
+        assert_ne!(
+            error_fingerprint("app", &"failed to parse index.js:1".into()),
+            error_fingerprint("app", &"failed to parse cli.js:1".into())
+        );
+    }
+}
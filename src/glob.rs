@@ -0,0 +1,103 @@
+// matches '/' separated paths against glob patterns: `?` and `*` match within a single
+// path component, `**` matches across any number of components
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    match_bytes(pattern.as_bytes(), path.as_bytes())
+}
+
+fn match_bytes(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            // "**/" also matches zero directories, so "a/**/b" matches "a/b"
+            match rest.strip_prefix(b"/") {
+                Some(rest) => (0..=path.len())
+                    .filter(|&i| i == 0 || path[i - 1] == b'/')
+                    .any(|i| match_bytes(rest, &path[i..])),
+                None => (0..=path.len()).any(|i| match_bytes(rest, &path[i..])),
+            }
+        }
+        [b'*', rest @ ..] => {
+            let component_end = path
+                .iter()
+                .position(|&byte| byte == b'/')
+                .unwrap_or(path.len());
+            (0..=component_end).any(|i| match_bytes(rest, &path[i..]))
+        }
+        [b'?', rest @ ..] => match path {
+            [byte, path_rest @ ..] if *byte != b'/' => match_bytes(rest, path_rest),
+            _ => false,
+        },
+        [byte, rest @ ..] => match path {
+            [path_byte, path_rest @ ..] if path_byte == byte => match_bytes(rest, path_rest),
+            _ => false,
+        },
+    }
+}
+
+// turns a gitignore style pattern into a glob for `glob_match`: a leading '/' anchors the
+// pattern to the root, a pattern without any other '/' matches at any depth
+pub fn gitignore_glob(pattern: &str) -> String {
+    match pattern.strip_prefix('/') {
+        Some(anchored) => anchored.to_string(),
+        None if !pattern.contains('/') => format!("**/{}", pattern),
+        None => pattern.to_string(),
+    }
+}
+
+// true when paths below the directory `dir` could still match the pattern, so a walk
+// has to descend into it
+pub fn glob_match_below(pattern: &str, dir: &str) -> bool {
+    let mut pattern_components = pattern.split('/');
+    for component in dir.split('/') {
+        match pattern_components.next() {
+            Some(pattern_component) if pattern_component.contains("**") => return true,
+            Some(pattern_component) if glob_match(pattern_component, component) => {}
+            _ => return false,
+        }
+    }
+    pattern_components.next().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_stars_stay_within_a_component() {
+        assert!(glob_match("*.js", "index.js"));
+        assert!(!glob_match("*.js", "lib/index.js"));
+        assert!(glob_match("packages/*", "packages/app"));
+        assert!(!glob_match("packages/*", "packages/app/src"));
+        assert!(glob_match("a?c", "abc"));
+        assert!(!glob_match("a?c", "a/c"));
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "a"));
+    }
+
+    #[test]
+    fn double_stars_cross_components() {
+        assert!(glob_match("**/*.js", "index.js"));
+        assert!(glob_match("**/*.js", "a/b/index.js"));
+        assert!(glob_match("a/**/b", "a/b"));
+        assert!(glob_match("a/**/b", "a/x/y/b"));
+        assert!(!glob_match("a/**/b", "ab"));
+        assert!(!glob_match("a/**/b", "a/xb"));
+        assert!(glob_match("a/**", "a/b/c"));
+    }
+
+    #[test]
+    fn gitignore_patterns_anchor_with_a_slash() {
+        assert_eq!(gitignore_glob("node_modules"), "**/node_modules");
+        assert_eq!(gitignore_glob("/dist"), "dist");
+        assert_eq!(gitignore_glob("src/generated"), "src/generated");
+    }
+
+    #[test]
+    fn walks_into_directories_a_pattern_can_still_reach() {
+        assert!(glob_match_below("packages/*/src", "packages"));
+        assert!(glob_match_below("packages/*/src", "packages/app"));
+        assert!(!glob_match_below("packages/*/src", "packages/app/src"));
+        assert!(!glob_match_below("packages/*/src", "vendor"));
+        assert!(glob_match_below("**/fixtures", "a/b"));
+    }
+}
@@ -0,0 +1,228 @@
+use crate::args::DependencyClass;
+use crate::layers::{Layer, Layering};
+use crate::{interop, Finding, PackageDetails};
+use std::borrow::Cow;
+use std::path::Path;
+
+// a dependency of one package in the scan on another
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub class: DependencyClass,
+    // the dependent's sources import computed specifiers, so what it actually loads at
+    // runtime may differ from what it declares
+    pub approximate: bool,
+}
+
+// the packages in the scan, by index, and the dependencies between them. Dependencies
+// on packages outside the scan aren't in it
+pub struct DependencyGraph {
+    pub edges: Vec<Edge>,
+}
+
+impl DependencyGraph {
+    pub fn build(packages: &[PackageDetails], root: &Path) -> DependencyGraph {
+        let (by_path, by_name) = interop::index_packages(packages);
+        let mut graph = DependencyGraph { edges: Vec::new() };
+        for (from, package_details) in packages.iter().enumerate() {
+            for dependency in &package_details.declared_dependencies {
+                let to = interop::resolve_dependency(
+                    packages,
+                    from,
+                    &dependency.name,
+                    Some(&dependency.spec),
+                    root,
+                    &by_path,
+                    &by_name,
+                );
+                if let Some(to) = to {
+                    graph.edges.push(Edge {
+                        from,
+                        to,
+                        class: dependency.class,
+                        approximate: !package_details.dynamic_imports.is_empty(),
+                    });
+                }
+            }
+        }
+        graph
+    }
+}
+
+// what the graph analyses found, for the report
+#[derive(Default)]
+pub struct GraphSummary {
+    pub edges: usize,
+    pub approximate_edges: usize,
+    // analysis -> the findings it added
+    pub findings: Vec<(Cow<'static, str>, usize)>,
+}
+
+// a pass over the whole graph. Its findings are merged into the packages they're
+// about, alongside the built in ones, and ignoreRules apply to them like to any other
+pub trait GraphAnalysis {
+    fn name(&self) -> &'static str;
+    // findings by the index of the package they belong to
+    fn analyze(
+        &self,
+        graph: &DependencyGraph,
+        packages: &[PackageDetails],
+    ) -> Vec<(usize, Finding)>;
+}
+
+// packages that end up depending on themselves. Dev dependencies are left out, a test
+// helper depending back on the package it tests is common and harmless
+struct Cycles;
+
+impl GraphAnalysis for Cycles {
+    fn name(&self) -> &'static str {
+        "dependency-cycles"
+    }
+
+    fn analyze(
+        &self,
+        graph: &DependencyGraph,
+        packages: &[PackageDetails],
+    ) -> Vec<(usize, Finding)> {
+        let mut successors = vec![Vec::new(); packages.len()];
+        for edge in &graph.edges {
+            if edge.class != DependencyClass::Dev {
+                successors[edge.from].push(edge.to);
+            }
+        }
+
+        let mut findings = Vec::new();
+        for component in strongly_connected(&successors) {
+            let cyclic = component.len() > 1 || successors[component[0]].contains(&component[0]);
+            if !cyclic {
+                continue;
+            }
+            let mut names: Vec<&str> = component
+                .iter()
+                .map(|&package| packages[package].name.as_str())
+                .collect();
+            names.sort();
+            for &package in &component {
+                findings.push((
+                    package,
+                    Finding {
+                        rule: "dependency-cycle".into(),
+                        subject: names.join(", "),
+                        message: format!("in a dependency cycle between {}", names.join(", ")),
+                    },
+                ));
+            }
+        }
+        findings
+    }
+}
+
+// Tarjan's algorithm, without recursion so deep chains of packages don't overflow the
+// stack
+fn strongly_connected(successors: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut order: Vec<Option<usize>> = vec![None; successors.len()];
+    let mut low = vec![0; successors.len()];
+    let mut on_stack = vec![false; successors.len()];
+    let mut stack = Vec::new();
+    let mut components = Vec::new();
+    let mut visited = 0;
+
+    for start in 0..successors.len() {
+        if order[start].is_some() {
+            continue;
+        }
+        // the packages being visited, with how many of their successors were looked at
+        let mut calls = vec![(start, 0)];
+        order[start] = Some(visited);
+        low[start] = visited;
+        visited += 1;
+        stack.push(start);
+        on_stack[start] = true;
+
+        while let Some(&mut (package, ref mut next)) = calls.last_mut() {
+            if let Some(&successor) = successors[package].get(*next) {
+                *next += 1;
+                match order[successor] {
+                    None => {
+                        order[successor] = Some(visited);
+                        low[successor] = visited;
+                        visited += 1;
+                        stack.push(successor);
+                        on_stack[successor] = true;
+                        calls.push((successor, 0));
+                    }
+                    Some(successor_order) if on_stack[successor] => {
+                        low[package] = low[package].min(successor_order);
+                    }
+                    Some(_) => {}
+                }
+                continue;
+            }
+
+            calls.pop();
+            if let Some(&(caller, _)) = calls.last() {
+                low[caller] = low[caller].min(low[package]);
+            }
+            if Some(low[package]) == order[package] {
+                let mut component = Vec::new();
+                while let Some(member) = stack.pop() {
+                    on_stack[member] = false;
+                    component.push(member);
+                    if member == package {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+// the analyses every scan runs. Custom passes go here too
+pub fn analyses(layers: &[Layer]) -> Vec<Box<dyn GraphAnalysis>> {
+    let mut analyses: Vec<Box<dyn GraphAnalysis>> = vec![Box::new(Cycles)];
+    if !layers.is_empty() {
+        analyses.push(Box::new(Layering {
+            order: layers.iter().map(|layer| layer.name.clone()).collect(),
+        }));
+    }
+    analyses
+}
+
+// builds the graph and merges what each analysis finds into the packages
+pub fn run_analyses(
+    analyses: &[Box<dyn GraphAnalysis>],
+    packages: &mut [PackageDetails],
+    root: &Path,
+) -> GraphSummary {
+    let graph = DependencyGraph::build(packages, root);
+    // kept with the packages, so the graph can be exported from results loaded later
+    for edge in &graph.edges {
+        let to = packages[edge.to].path.clone();
+        packages[edge.from].scan_dependencies.push((to, edge.class));
+    }
+    let mut summary = GraphSummary {
+        edges: graph.edges.len(),
+        approximate_edges: graph.edges.iter().filter(|edge| edge.approximate).count(),
+        findings: Vec::new(),
+    };
+    let mut findings = Vec::new();
+    for analysis in analyses {
+        let found = analysis.analyze(&graph, packages);
+        summary.findings.push((analysis.name().into(), found.len()));
+        findings.extend(found);
+    }
+    for (package, finding) in findings {
+        let package_details = &mut packages[package];
+        match package_details
+            .ignore_rules
+            .iter()
+            .any(|rule| *rule == finding.rule)
+        {
+            true => package_details.suppressed_findings.push(finding),
+            false => package_details.findings.push(finding),
+        }
+    }
+    summary
+}
@@ -0,0 +1,469 @@
+use crate::args::DependencyClass;
+use crate::{supports_cjs, supports_esm, PackageDetails};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+// the most packages the HTML page draws, its layout compares every pair of nodes on
+// every step and a browser stalls well before a large scan's node_modules
+pub const MAX_HTML_NODES: usize = 500;
+
+type Edge = (usize, usize, DependencyClass);
+
+#[derive(Clone, Copy)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+    Json,
+    Html,
+}
+
+impl GraphFormat {
+    pub fn parse(value: &str) -> Result<GraphFormat, String> {
+        match value {
+            "dot" => Ok(GraphFormat::Dot),
+            "mermaid" => Ok(GraphFormat::Mermaid),
+            "json" => Ok(GraphFormat::Json),
+            "html" => Ok(GraphFormat::Html),
+            _ => Err(format!(
+                "Invalid graph format '{}', expected dot, mermaid, json or html",
+                value
+            )),
+        }
+    }
+}
+
+// what a node is colored by
+pub fn module_support(package_details: &PackageDetails) -> &'static str {
+    match (
+        supports_esm(&package_details.module_support),
+        supports_cjs(&package_details.module_support),
+    ) {
+        (true, true) => "dual",
+        (true, false) => "esm",
+        (false, true) => "cjs",
+        (false, false) => "unknown",
+    }
+}
+
+// dual, ESM only, CommonJS only, unknown
+const NODE_COLORS: [(&str, &str); 4] = [
+    ("dual", "#4caf50"),
+    ("esm", "#2196f3"),
+    ("cjs", "#ff9800"),
+    ("unknown", "#9e9e9e"),
+];
+
+fn node_color(package_details: &PackageDetails) -> &'static str {
+    let support = module_support(package_details);
+    NODE_COLORS
+        .iter()
+        .find(|(class, _)| *class == support)
+        .map(|(_, color)| *color)
+        .unwrap_or("#9e9e9e")
+}
+
+fn node_label(package_details: &PackageDetails) -> String {
+    match package_details.version.is_empty() {
+        true => package_details.name.clone(),
+        false => format!("{}@{}", package_details.name, package_details.version),
+    }
+}
+
+// the edges between the packages given, by index. Dependencies on packages left out of
+// the report are left out of the graph too
+fn edges(packages: &[PackageDetails]) -> Vec<Edge> {
+    let by_path: HashMap<&Path, usize> = packages
+        .iter()
+        .enumerate()
+        .map(|(index, p)| (p.path.as_path(), index))
+        .collect();
+    let mut edges = Vec::new();
+    for (from, package_details) in packages.iter().enumerate() {
+        for (path, class) in &package_details.scan_dependencies {
+            if let Some(&to) = by_path.get(path.as_path()) {
+                edges.push((from, to, *class));
+            }
+        }
+    }
+    edges
+}
+
+fn dot_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn render_dot(packages: &[PackageDetails]) -> String {
+    let mut out =
+        String::from("digraph dependencies {\n  rankdir=LR;\n  node [shape=box, style=filled];\n");
+    for (index, package_details) in packages.iter().enumerate() {
+        out.push_str(&format!(
+            "  n{} [label={}, tooltip={}, fillcolor={}];\n",
+            index,
+            dot_string(&node_label(package_details)),
+            dot_string(&package_details.path.display().to_string()),
+            dot_string(node_color(package_details))
+        ));
+    }
+    for (from, to, class) in edges(packages) {
+        let style = match class {
+            DependencyClass::Prod => "",
+            DependencyClass::Dev => " [style=dashed, label=dev]",
+            DependencyClass::Peer => " [style=dotted, label=peer]",
+            DependencyClass::Optional => " [style=dotted, label=optional]",
+        };
+        out.push_str(&format!("  n{} -> n{}{};\n", from, to, style));
+    }
+    out.push_str("}\n");
+    out
+}
+
+// mermaid labels are quoted, and a quote inside one has to be an entity
+fn render_mermaid(packages: &[PackageDetails]) -> String {
+    let mut out = String::from("graph LR\n");
+    for (index, package_details) in packages.iter().enumerate() {
+        out.push_str(&format!(
+            "  n{}[\"{}\"]:::{}\n",
+            index,
+            node_label(package_details).replace('"', "#quot;"),
+            module_support(package_details)
+        ));
+    }
+    for (from, to, class) in edges(packages) {
+        let arrow = match class {
+            DependencyClass::Prod => "-->".to_string(),
+            class => format!("-. {} .->", class.label()),
+        };
+        out.push_str(&format!("  n{} {} n{}\n", from, arrow, to));
+    }
+    for (class, color) in NODE_COLORS {
+        out.push_str(&format!("  classDef {} fill:{}\n", class, color));
+    }
+    out
+}
+
+// the nodes are the packages at the indices in `nodes`, numbered in that order, and the
+// edges between them
+fn graph_json(packages: &[PackageDetails], nodes: &[usize], edges: &[Edge]) -> Value {
+    let ids: HashMap<usize, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(id, &index)| (index, id))
+        .collect();
+    let node_values: Vec<Value> = nodes
+        .iter()
+        .enumerate()
+        .map(|(id, &index)| {
+            let p = &packages[index];
+            json!({
+                "id": id,
+                "name": p.name,
+                "version": p.version,
+                "path": p.path.display().to_string(),
+                "module_support": module_support(p),
+                "size": p.size,
+            })
+        })
+        .collect();
+    let edge_values: Vec<Value> = edges
+        .iter()
+        .filter_map(|&(from, to, class)| {
+            Some(json!({
+                "from": ids.get(&from)?,
+                "to": ids.get(&to)?,
+                "class": class.label(),
+                "approximate": !packages[from].dynamic_imports.is_empty(),
+            }))
+        })
+        .collect();
+    json!({ "nodes": node_values, "edges": edge_values })
+}
+
+fn render_json(packages: &[PackageDetails]) -> String {
+    let nodes: Vec<usize> = (0..packages.len()).collect();
+    serde_json::to_string_pretty(&graph_json(packages, &nodes, &edges(packages))).unwrap() + "\n"
+}
+
+// what the HTML page draws: the workspace packages, the packages they depend on directly
+// and the edges from one to the other. A scan without workspaces draws every package.
+// The nodes come workspace packages first, so capping them at MAX_HTML_NODES drops
+// dependencies before the workspace
+pub fn html_graph(packages: &[PackageDetails]) -> (Vec<usize>, Vec<Edge>) {
+    let workspace: Vec<usize> = (0..packages.len())
+        .filter(|&index| packages[index].workspace_root.is_some())
+        .collect();
+    if workspace.is_empty() {
+        return ((0..packages.len()).collect(), edges(packages));
+    }
+    let edges: Vec<Edge> = edges(packages)
+        .into_iter()
+        .filter(|(from, _, _)| packages[*from].workspace_root.is_some())
+        .collect();
+    let mut seen: HashSet<usize> = workspace.iter().copied().collect();
+    let mut nodes = workspace;
+    for &(_, to, _) in &edges {
+        if seen.insert(to) {
+            nodes.push(to);
+        }
+    }
+    (nodes, edges)
+}
+
+const GRAPH_STYLE: &str = "body { font-family: system-ui, sans-serif; margin: 0; color: #222; }
+header { padding: 0.5em 1em; border-bottom: 1px solid #ddd; }
+h1 { font-size: 1.2em; display: inline; margin-right: 1em; }
+.legend span { display: inline-block; width: 0.8em; height: 0.8em; border-radius: 50%; margin: 0 0.3em 0 1em; }
+#filter { margin-left: 1em; padding: 0.2em; width: 14em; }
+#details { float: right; }
+svg { display: block; width: 100vw; height: calc(100vh - 3em); cursor: grab; }
+line { stroke: #999; stroke-opacity: 0.6; }
+line.dev { stroke-dasharray: 6 3; } line.peer, line.optional { stroke-dasharray: 2 3; }
+circle { stroke: #fff; stroke-width: 1.5; cursor: pointer; }
+text { font-size: 10px; pointer-events: none; fill: #333; }
+.faded { opacity: 0.12; }";
+
+// lays the graph out with a small force simulation: nodes push each other apart, edges
+// pull their ends together and everything drifts to the middle, cooling until it
+// settles. Nodes can be dragged, the view panned and zoomed, and clicking a node fades
+// everything it isn't directly connected to
+const GRAPH_SCRIPT: &str = "const graph = JSON.parse(document.getElementById('graph').textContent);
+const svg = document.querySelector('svg');
+const namespace = 'http://www.w3.org/2000/svg';
+const element = (name, attributes, parent) => {
+  const created = document.createElementNS(namespace, name);
+  for (const [key, value] of Object.entries(attributes)) created.setAttribute(key, value);
+  parent.appendChild(created);
+  return created;
+};
+const view = element('g', {}, svg);
+const largest = Math.max(1, ...graph.nodes.map((node) => node.size));
+const spread = 40 * Math.sqrt(graph.nodes.length + 1);
+graph.nodes.forEach((node, index) => {
+  const angle = index * 2.399963;
+  const distance = spread * Math.sqrt((index + 0.5) / graph.nodes.length);
+  Object.assign(node, { x: distance * Math.cos(angle), y: distance * Math.sin(angle), vx: 0, vy: 0 });
+  node.radius = 5 + 20 * Math.sqrt(node.size / largest);
+  node.neighbors = new Set([index]);
+});
+for (const edge of graph.edges) {
+  graph.nodes[edge.from].neighbors.add(edge.to);
+  graph.nodes[edge.to].neighbors.add(edge.from);
+}
+const lines = graph.edges.map((edge) => element('line', { class: edge.class }, view));
+const circles = graph.nodes.map((node, index) => {
+  const circle = element('circle', { r: node.radius, fill: COLORS[node.module_support] }, view);
+  element('title', {}, circle).textContent =
+    `${node.name}@${node.version}\n${node.path}\n${node.module_support}, ${node.size} bytes, ${node.neighbors.size - 1} connections`;
+  circle.addEventListener('pointerdown', (event) => startDrag(event, node));
+  circle.addEventListener('click', () => focus(index));
+  return circle;
+});
+const labels = graph.nodes.map((node) => {
+  const label = element('text', { dy: '0.35em' }, view);
+  label.textContent = node.name;
+  return label;
+});
+
+let alpha = 1;
+let dragged = null;
+let panning = null;
+const tick = () => {
+  const nodes = graph.nodes;
+  for (let i = 0; i < nodes.length; i++) {
+    for (let j = i + 1; j < nodes.length; j++) {
+      const [a, b] = [nodes[i], nodes[j]];
+      let [dx, dy] = [b.x - a.x, b.y - a.y];
+      const squared = Math.max(dx * dx + dy * dy, 1);
+      const force = (900 * alpha) / squared;
+      dx *= force; dy *= force;
+      a.vx -= dx; a.vy -= dy; b.vx += dx; b.vy += dy;
+    }
+  }
+  for (const edge of graph.edges) {
+    const [a, b] = [nodes[edge.from], nodes[edge.to]];
+    const [dx, dy] = [b.x - a.x, b.y - a.y];
+    const distance = Math.sqrt(dx * dx + dy * dy) || 1;
+    const force = ((distance - 60 - a.radius - b.radius) / distance) * 0.05 * alpha;
+    a.vx += dx * force; a.vy += dy * force; b.vx -= dx * force; b.vy -= dy * force;
+  }
+  for (const node of nodes) {
+    node.vx -= node.x * 0.01 * alpha;
+    node.vy -= node.y * 0.01 * alpha;
+    if (node !== dragged) {
+      node.x += node.vx; node.y += node.vy;
+    }
+    node.vx *= 0.6; node.vy *= 0.6;
+  }
+};
+const draw = () => {
+  graph.edges.forEach((edge, index) => {
+    const [a, b] = [graph.nodes[edge.from], graph.nodes[edge.to]];
+    lines[index].setAttribute('x1', a.x); lines[index].setAttribute('y1', a.y);
+    lines[index].setAttribute('x2', b.x); lines[index].setAttribute('y2', b.y);
+  });
+  graph.nodes.forEach((node, index) => {
+    circles[index].setAttribute('cx', node.x); circles[index].setAttribute('cy', node.y);
+    labels[index].setAttribute('x', node.x + node.radius + 3); labels[index].setAttribute('y', node.y);
+  });
+};
+const run = () => {
+  tick();
+  draw();
+  alpha *= 0.99;
+  if (alpha > 0.01) requestAnimationFrame(run);
+};
+const reheat = () => {
+  const settled = alpha <= 0.01;
+  alpha = Math.max(alpha, 0.3);
+  if (settled) requestAnimationFrame(run);
+};
+run();
+
+let [panX, panY, scale] = [svg.clientWidth / 2, svg.clientHeight / 2, 1];
+const transform = () => view.setAttribute('transform', `translate(${panX},${panY}) scale(${scale})`);
+transform();
+svg.addEventListener('wheel', (event) => {
+  event.preventDefault();
+  const factor = Math.exp(-event.deltaY * 0.001);
+  panX = event.offsetX - (event.offsetX - panX) * factor;
+  panY = event.offsetY - (event.offsetY - panY) * factor;
+  scale *= factor;
+  transform();
+});
+const startDrag = (event, node) => {
+  event.stopPropagation();
+  dragged = node;
+  svg.setPointerCapture(event.pointerId);
+};
+svg.addEventListener('pointerdown', (event) => {
+  panning = [event.clientX - panX, event.clientY - panY];
+  svg.setPointerCapture(event.pointerId);
+});
+svg.addEventListener('pointermove', (event) => {
+  if (dragged) {
+    dragged.x = (event.offsetX - panX) / scale;
+    dragged.y = (event.offsetY - panY) / scale;
+    reheat();
+    draw();
+  } else if (panning) {
+    [panX, panY] = [event.clientX - panning[0], event.clientY - panning[1]];
+    transform();
+  }
+});
+svg.addEventListener('pointerup', () => { dragged = null; panning = null; });
+
+const details = document.getElementById('details');
+let focused = null;
+const fade = (visible) => {
+  graph.nodes.forEach((node, index) => {
+    circles[index].classList.toggle('faded', !visible(index));
+    labels[index].classList.toggle('faded', !visible(index));
+  });
+  graph.edges.forEach((edge, index) => {
+    lines[index].classList.toggle('faded', !(visible(edge.from) && visible(edge.to)));
+  });
+};
+const focus = (index) => {
+  focused = focused === index ? null : index;
+  if (focused === null) {
+    fade(() => true);
+    details.textContent = '';
+    return;
+  }
+  const node = graph.nodes[index];
+  fade((other) => node.neighbors.has(other));
+  const dependents = graph.edges.filter((edge) => edge.to === index).length;
+  const dependencies = graph.edges.filter((edge) => edge.from === index).length;
+  details.textContent = `${node.name}: ${node.module_support}, ${dependencies} dependencies, ${dependents} dependents`;
+};
+document.getElementById('filter').addEventListener('input', (event) => {
+  const text = event.target.value.toLowerCase();
+  focused = null;
+  details.textContent = '';
+  fade((index) => !text || graph.nodes[index].name.toLowerCase().includes(text));
+});";
+
+// a page that draws the graph itself, no network needed. Nodes colored by module support
+// and sized by package size, so the large CommonJS packages many others depend on stand
+// out. The graph goes in as JSON, with `</` broken up so a name can't end the script
+fn render_html(packages: &[PackageDetails]) -> String {
+    let (mut nodes, edges) = html_graph(packages);
+    nodes.truncate(MAX_HTML_NODES);
+    let colors: serde_json::Map<String, Value> = NODE_COLORS
+        .iter()
+        .map(|(class, color)| (class.to_string(), json!(color)))
+        .collect();
+    let mut legend = String::new();
+    for (class, color) in NODE_COLORS {
+        let count = nodes
+            .iter()
+            .filter(|&&index| module_support(&packages[index]) == class)
+            .count();
+        legend.push_str(&format!(
+            "<span style=\"background: {}\"></span>{} {}",
+            color, class, count
+        ));
+    }
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>walker dependency graph</title>\n<style>\n{}\n</style>\n</head>\n<body>\n<header><h1>walker dependency graph</h1><span class=\"legend\">{}</span><input id=\"filter\" placeholder=\"Filter packages\"><span id=\"details\"></span></header>\n<svg></svg>\n<script type=\"application/json\" id=\"graph\">{}</script>\n<script>\nconst COLORS = {};\n{}\n</script>\n</body>\n</html>\n",
+        GRAPH_STYLE,
+        legend,
+        graph_json(packages, &nodes, &edges)
+            .to_string()
+            .replace("</", "<\\/"),
+        Value::Object(colors),
+        GRAPH_SCRIPT
+    )
+}
+
+// the dependencies between the packages in the scan, for Graphviz, Mermaid, any tool that
+// reads JSON, or a browser. Colored by module support, dev, peer and optional dependencies
+// drawn apart from runtime ones
+pub fn render_graph(packages: &[PackageDetails], format: GraphFormat) -> String {
+    match format {
+        GraphFormat::Dot => render_dot(packages),
+        GraphFormat::Mermaid => render_mermaid(packages),
+        GraphFormat::Json => render_json(packages),
+        GraphFormat::Html => render_html(packages),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn package(path: &str, workspace: bool, dependencies: &[&str]) -> PackageDetails {
+        PackageDetails {
+            path: PathBuf::from(path),
+            workspace_root: workspace.then(|| PathBuf::from("/repo")),
+            scan_dependencies: dependencies
+                .iter()
+                .map(|path| (PathBuf::from(path), DependencyClass::Prod))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn html_draws_the_workspace_and_its_direct_dependencies() {
+        let packages = vec![
+            package("/repo/node_modules/a", false, &["/repo/node_modules/b"]),
+            package("/repo", true, &["/repo/packages/app"]),
+            package("/repo/node_modules/b", false, &[]),
+            package("/repo/packages/app", true, &["/repo/node_modules/a"]),
+        ];
+        let (nodes, edges) = html_graph(&packages);
+        assert_eq!(nodes, vec![1, 3, 0]);
+        let edges: Vec<(usize, usize)> = edges.iter().map(|&(from, to, _)| (from, to)).collect();
+        assert_eq!(edges, vec![(1, 3), (3, 0)]);
+    }
+
+    #[test]
+    fn html_draws_everything_without_a_workspace() {
+        let packages = vec![package("/a", false, &["/b"]), package("/b", false, &[])];
+        let (nodes, edges) = html_graph(&packages);
+        assert_eq!(nodes, vec![0, 1]);
+        assert_eq!(edges.len(), 1);
+    }
+}
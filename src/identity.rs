@@ -0,0 +1,172 @@
+use crate::baseline::Regression;
+use crate::PackageDetails;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+// packages without a name are reported by their directory. With relative paths a
+// package can also be named like its directory, then only its manifest tells them apart
+fn is_unnamed(package_details: &PackageDetails) -> bool {
+    package_details.name.is_empty()
+        || (package_details.name == package_details.path.display().to_string()
+            && !package_details
+                .manifest_fields
+                .iter()
+                .any(|field| field == "name"))
+}
+
+// a package from one run to the next, wherever it lives in the tree: its name and version,
+// or the hash of its package.json for a package without a name
+pub fn identity(package_details: &PackageDetails) -> String {
+    match is_unnamed(package_details) {
+        true => format!("sha256:{}", package_details.manifest_hash),
+        false => format!("{}@{}", package_details.name, package_details.version),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Moved,
+    Updated,
+}
+
+impl ChangeKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Moved => "moved",
+            ChangeKind::Updated => "updated",
+        }
+    }
+}
+
+pub struct PackageChange {
+    pub kind: ChangeKind,
+    pub name: String,
+    // version and path in the earlier run and in this one
+    pub before: Option<(String, PathBuf)>,
+    pub after: Option<(String, PathBuf)>,
+}
+
+// how this run's packages differ from an earlier run's
+pub struct Comparison {
+    pub compared_with: PathBuf,
+    pub changes: Vec<PackageChange>,
+    pub regressions: Vec<Regression>,
+}
+
+impl Comparison {
+    pub fn count(&self, kind: ChangeKind) -> usize {
+        self.changes
+            .iter()
+            .filter(|change| change.kind == kind)
+            .count()
+    }
+}
+
+fn version_and_path(package_details: &PackageDetails) -> (String, PathBuf) {
+    (
+        package_details.version.clone(),
+        package_details.path.clone(),
+    )
+}
+
+// pairs the packages of two runs up, first by identity, then by the hash of their
+// package.json, then by name at the same path for a version bump in place. Paths only
+// break ties, so a package that moved is paired rather than removed and added. Gives the
+// index before and after of every pair, unchanged ones included
+pub fn pair_up(before: &[PackageDetails], after: &[PackageDetails]) -> Vec<(usize, usize)> {
+    let stages: [fn(&PackageDetails) -> Option<String>; 3] = [
+        |p| Some(identity(p)),
+        |p| Some(p.manifest_hash.clone()).filter(|hash| !hash.is_empty()),
+        |p| Some(format!("{}\0{}", p.name, p.path.display())).filter(|_| !is_unnamed(p)),
+    ];
+
+    let mut before_order: Vec<usize> = (0..before.len()).collect();
+    before_order.sort_by(|&a, &b| before[a].path.cmp(&before[b].path));
+    let mut after_order: Vec<usize> = (0..after.len()).collect();
+    after_order.sort_by(|&a, &b| after[a].path.cmp(&after[b].path));
+
+    let mut before_matched = vec![false; before.len()];
+    let mut after_matched = vec![false; after.len()];
+    let mut pairs = Vec::new();
+    for key in stages {
+        let mut candidates: HashMap<String, Vec<usize>> = HashMap::new();
+        for &index in before_order.iter().filter(|&&index| !before_matched[index]) {
+            if let Some(key) = key(&before[index]) {
+                candidates.entry(key).or_default().push(index);
+            }
+        }
+        for &index in &after_order {
+            if after_matched[index] {
+                continue;
+            }
+            let package_details = &after[index];
+            let candidates = match key(package_details).and_then(|key| candidates.get_mut(&key)) {
+                Some(candidates) if !candidates.is_empty() => candidates,
+                _ => continue,
+            };
+            let position = candidates
+                .iter()
+                .position(|&candidate| before[candidate].path == package_details.path)
+                .unwrap_or(0);
+            let earlier_index = candidates.remove(position);
+            before_matched[earlier_index] = true;
+            after_matched[index] = true;
+            pairs.push((earlier_index, index));
+        }
+    }
+    pairs
+}
+
+// how the packages of two runs differ: the ones that moved, changed version, or are only
+// in one of them
+pub fn compare(before: &[PackageDetails], after: &[PackageDetails]) -> Vec<PackageChange> {
+    let mut before_matched = vec![false; before.len()];
+    let mut after_matched = vec![false; after.len()];
+    let mut changes = Vec::new();
+    for (earlier_index, index) in pair_up(before, after) {
+        before_matched[earlier_index] = true;
+        after_matched[index] = true;
+        let (earlier, package_details) = (&before[earlier_index], &after[index]);
+        let kind = match (
+            earlier.path == package_details.path,
+            identity(earlier) == identity(package_details),
+        ) {
+            (true, true) => continue,
+            (false, _) => ChangeKind::Moved,
+            (true, false) => ChangeKind::Updated,
+        };
+        changes.push(PackageChange {
+            kind,
+            name: package_details.name.clone(),
+            before: Some(version_and_path(earlier)),
+            after: Some(version_and_path(package_details)),
+        });
+    }
+
+    let mut before_order: Vec<usize> = (0..before.len()).collect();
+    before_order.sort_by(|&a, &b| before[a].path.cmp(&before[b].path));
+    let mut after_order: Vec<usize> = (0..after.len()).collect();
+    after_order.sort_by(|&a, &b| after[a].path.cmp(&after[b].path));
+    for &index in before_order.iter().filter(|&&index| !before_matched[index]) {
+        changes.push(PackageChange {
+            kind: ChangeKind::Removed,
+            name: before[index].name.clone(),
+            before: Some(version_and_path(&before[index])),
+            after: None,
+        });
+    }
+    for &index in after_order.iter().filter(|&&index| !after_matched[index]) {
+        changes.push(PackageChange {
+            kind: ChangeKind::Added,
+            name: after[index].name.clone(),
+            before: None,
+            after: Some(version_and_path(&after[index])),
+        });
+    }
+    changes.sort_by_key(|change| change.kind.label());
+    changes
+}
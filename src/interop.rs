@@ -0,0 +1,307 @@
+use crate::bundlers::EntrypointResolution;
+use crate::filesystem::FileSystem;
+use crate::protocols;
+use crate::{list_locations, supports_cjs, supports_esm, Finding, PackageDetails};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+// entry files bigger than this are bundles, scanning them costs more than it tells
+const MAX_ENTRY_SIZE: u64 = 1024 * 1024;
+
+// finds the package a dependency of `packages[from]` resolves to: the nearest
+// node_modules on the way up to the scan root, like node does, or failing that the
+// package in the scan with that name, as in a workspace. Dependencies with a local
+// protocol go straight to their target, workspace: ones only ever to a workspace package,
+// by the name they alias when they do
+pub fn resolve_dependency(
+    packages: &[PackageDetails],
+    from: usize,
+    name: &str,
+    spec: Option<&str>,
+    root: &Path,
+    by_path: &HashMap<&Path, usize>,
+    by_name: &HashMap<&str, Vec<usize>>,
+) -> Option<usize> {
+    let dir = packages[from].path.as_path();
+    let workspace_root = packages[from].workspace_root.as_ref();
+    // of several packages with the name, the one in the dependent's own workspace
+    let named = |name: &str| -> Option<usize> {
+        let candidates: Vec<usize> = match by_name.get(name)?.as_slice() {
+            [index] => return Some(*index),
+            candidates => candidates
+                .iter()
+                .filter(|&&index| {
+                    workspace_root.is_some()
+                        && packages[index].workspace_root.as_ref() == workspace_root
+                })
+                .copied()
+                .collect(),
+        };
+        match candidates.as_slice() {
+            [index] => Some(*index),
+            _ => None,
+        }
+    };
+    match spec.and_then(protocols::local_protocol) {
+        Some("workspace") => {
+            let spec = spec?;
+            return match protocols::link_target(dir, spec) {
+                Some(target) => by_path.get(target.as_path()).copied(),
+                None => named(protocols::workspace_alias(spec).unwrap_or(name)),
+            };
+        }
+        Some(_) => {
+            let target = protocols::link_target(dir, spec?)?;
+            return by_path.get(target.as_path()).copied();
+        }
+        None => {}
+    }
+    let installed = dir
+        .ancestors()
+        .take_while(|ancestor| ancestor.starts_with(root))
+        .find_map(|ancestor| by_path.get(ancestor.join("node_modules").join(name).as_path()));
+    if let Some(&index) = installed {
+        return Some(index);
+    }
+    // a workspace that doesn't link its packages installs this one from the registry
+    let index = named(name)?;
+    let unlinked = workspace_root
+        .and_then(|workspace_root| by_path.get(workspace_root.as_path()))
+        .is_some_and(|&workspace_root| packages[workspace_root].unlinked_workspace_packages);
+    match unlinked && packages[index].workspace_root.as_ref() == workspace_root {
+        true => None,
+        false => Some(index),
+    }
+}
+
+// the packages by directory and by name, what resolve_dependency looks them up by
+pub fn index_packages(
+    packages: &[PackageDetails],
+) -> (HashMap<&Path, usize>, HashMap<&str, Vec<usize>>) {
+    let by_path: HashMap<&Path, usize> = packages
+        .iter()
+        .enumerate()
+        .map(|(index, p)| (p.path.as_path(), index))
+        .collect();
+    let mut by_name: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, package_details) in packages.iter().enumerate() {
+        if package_details.duplicate_of.is_some() {
+            continue;
+        }
+        by_name
+            .entry(package_details.name.as_str())
+            .or_default()
+            .push(index);
+    }
+    (by_path, by_name)
+}
+
+// packages outside node_modules declaring the same name, usually a copy-paste fork. Copies
+// of a dependency installed at several places in node_modules are normal and left alone.
+// The first by path keeps the name, the others are left out of anything keyed by name
+pub fn find_duplicate_names(packages: &mut [PackageDetails], root: &Path) {
+    let mut by_name: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (index, package_details) in packages.iter().enumerate() {
+        let installed = package_details
+            .path
+            .components()
+            .any(|component| component.as_os_str() == "node_modules");
+        let unnamed = package_details.name == package_details.path.display().to_string();
+        if !installed && !unnamed {
+            by_name
+                .entry(package_details.name.as_str())
+                .or_default()
+                .push(index);
+        }
+    }
+    let duplicates: Vec<Vec<usize>> = by_name
+        .into_values()
+        .filter(|indexes| indexes.len() > 1)
+        .map(|mut indexes| {
+            indexes.sort_by(|&a, &b| packages[a].path.cmp(&packages[b].path));
+            indexes
+        })
+        .collect();
+
+    for indexes in duplicates {
+        let paths: Vec<String> = indexes
+            .iter()
+            .map(|&index| {
+                let path = &packages[index].path;
+                path.strip_prefix(root)
+                    .unwrap_or(path)
+                    .display()
+                    .to_string()
+            })
+            .collect();
+        let kept = packages[indexes[0]].path.clone();
+        for (position, &index) in indexes.iter().enumerate() {
+            let others: Vec<String> = paths
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != position)
+                .map(|(_, path)| path.clone())
+                .collect();
+            let package_details = &mut packages[index];
+            let finding = Finding {
+                rule: "duplicate-name".into(),
+                subject: String::new(),
+                message: format!(
+                    "package name {} is also declared by {}",
+                    package_details.name,
+                    list_locations(&others)
+                ),
+            };
+            match package_details
+                .ignore_rules
+                .iter()
+                .any(|rule| *rule == finding.rule)
+            {
+                true => package_details.suppressed_findings.push(finding),
+                false => package_details.findings.push(finding),
+            }
+            if position > 0 {
+                package_details.duplicate_of = Some(kept.clone());
+            }
+        }
+    }
+}
+
+// flags ESM (and dual) packages that depend at runtime on packages in the scan that only
+// support CommonJS, each of those forces an interop shim in the ESM build
+pub fn find_cjs_only_dependencies(packages: &mut [PackageDetails], root: &Path) {
+    let mut cjs_only_dependencies = Vec::new();
+    {
+        let (by_path, by_name) = index_packages(packages);
+        for (index, package_details) in packages.iter().enumerate() {
+            if !supports_esm(&package_details.module_support) {
+                continue;
+            }
+            let dependencies: Vec<String> = package_details
+                .dependencies
+                .iter()
+                .filter_map(|name| {
+                    let spec = package_details.local_dependencies.get(name);
+                    resolve_dependency(
+                        packages,
+                        index,
+                        name,
+                        spec.map(String::as_str),
+                        root,
+                        &by_path,
+                        &by_name,
+                    )
+                })
+                .map(|dependency| &packages[dependency])
+                .filter(|dependency| {
+                    supports_cjs(&dependency.module_support)
+                        && !supports_esm(&dependency.module_support)
+                })
+                .map(|dependency| format!("{}@{}", dependency.name, dependency.version))
+                .collect();
+            if !dependencies.is_empty() {
+                cjs_only_dependencies.push((index, dependencies));
+            }
+        }
+    }
+
+    for (index, dependencies) in cjs_only_dependencies {
+        let package_details = &mut packages[index];
+        let finding = Finding {
+            rule: "esm-cjs-dependency".into(),
+            subject: String::new(),
+            message: format!(
+                "ESM package depends on CommonJS-only packages: {}",
+                dependencies.join(", ")
+            ),
+        };
+        match package_details
+            .ignore_rules
+            .iter()
+            .any(|rule| *rule == finding.rule)
+        {
+            true => package_details.suppressed_findings.push(finding),
+            false => package_details.findings.push(finding),
+        }
+        package_details.cjs_only_dependencies = dependencies;
+    }
+}
+
+fn read_entry(filesystem: &dyn FileSystem, package_dir: &Path, entry: &str) -> Option<String> {
+    let path = package_dir.join(entry);
+    match filesystem.metadata(&path) {
+        Ok(metadata) if metadata.len <= MAX_ENTRY_SIZE => filesystem.read_to_string(&path).ok(),
+        _ => None,
+    }
+}
+
+fn contains_any(source: &str, patterns: &[&str]) -> bool {
+    patterns.iter().any(|pattern| source.contains(pattern))
+}
+
+// looks at the entrypoints node picks for `import` and `require` of a dual package for the
+// classic default export mismatch. Only a heuristic: the sources are searched for the
+// shapes transpilers emit, nothing is parsed
+pub fn default_export_hazards(
+    filesystem: &dyn FileSystem,
+    package_dir: &Path,
+    resolutions: &[EntrypointResolution],
+) -> Vec<Finding> {
+    let node = match resolutions.iter().find(|r| r.resolver == "node") {
+        Some(node) => node,
+        None => return Vec::new(),
+    };
+    let (import, require) = match (&node.import, &node.require) {
+        (Some(import), Some(require)) if import != require => (import, require),
+        _ => return Vec::new(),
+    };
+    let (esm, cjs) = match (
+        read_entry(filesystem, package_dir, import),
+        read_entry(filesystem, package_dir, require),
+    ) {
+        (Some(esm), Some(cjs)) => (esm, cjs),
+        _ => return Vec::new(),
+    };
+
+    // babel and tsc mark transpiled ES modules and put the default export on .default
+    let cjs_transpiled = contains_any(
+        &cjs,
+        &[
+            "__esModule",
+            "exports.default =",
+            "exports.default=",
+            "exports[\"default\"]",
+        ],
+    );
+    let cjs_replaces_exports = contains_any(&cjs, &["module.exports =", "module.exports="]);
+    let esm_default = contains_any(&esm, &["export default", " as default"]);
+    let cjs_file = Path::new(require)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let esm_wraps_cjs = !cjs_file.is_empty() && esm.contains(&cjs_file);
+
+    let message = match (cjs_transpiled, cjs_replaces_exports, esm_default) {
+        (true, _, true) if esm_wraps_cjs => Some(format!(
+            "ESM {} re-exports transpiled CommonJS {}, import gets {{ default }}, not the value",
+            import, require
+        )),
+        (true, _, true) => Some(format!(
+            "CommonJS {} exports .default but ESM {} exports the value, require() and import disagree",
+            require, import
+        )),
+        (false, true, false) => Some(format!(
+            "CommonJS {} sets module.exports but ESM {} has no default export",
+            require, import
+        )),
+        _ => None,
+    };
+    message
+        .map(|message| Finding {
+            rule: "interop-default-export".into(),
+            subject: String::new(),
+            message,
+        })
+        .into_iter()
+        .collect()
+}
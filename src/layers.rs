@@ -0,0 +1,82 @@
+use crate::args::DependencyClass;
+use crate::glob::glob_match;
+use crate::graph::{DependencyGraph, GraphAnalysis};
+use crate::{Finding, PackageDetails};
+
+// a layer of the architecture, from the config file. A package is in the first layer
+// one of whose path globs matches its path relative to the scan root, or one of whose
+// tags it has
+pub struct Layer {
+    pub name: String,
+    pub paths: Vec<String>,
+    pub tags: Vec<String>,
+}
+
+pub fn find_layer<'a>(
+    layers: &'a [Layer],
+    relative_path: &str,
+    tags: &[String],
+) -> Option<&'a str> {
+    layers
+        .iter()
+        .find(|layer| {
+            layer
+                .paths
+                .iter()
+                .any(|pattern| glob_match(pattern, relative_path))
+                || layer.tags.iter().any(|tag| tags.contains(tag))
+        })
+        .map(|layer| layer.name.as_str())
+}
+
+// layers are listed top down, `apps -> features -> core`: a package may depend on its
+// own layer and the ones below it, never on one above. Packages outside every layer
+// aren't checked, and neither are dev dependencies
+pub struct Layering {
+    pub order: Vec<String>,
+}
+
+impl GraphAnalysis for Layering {
+    fn name(&self) -> &'static str {
+        "layering"
+    }
+
+    fn analyze(
+        &self,
+        graph: &DependencyGraph,
+        packages: &[PackageDetails],
+    ) -> Vec<(usize, Finding)> {
+        let rank = |package: usize| {
+            let layer = packages[package].layer.as_ref()?;
+            self.order.iter().position(|name| name == layer)
+        };
+        let mut findings = Vec::new();
+        for edge in &graph.edges {
+            if edge.class == DependencyClass::Dev {
+                continue;
+            }
+            let (from, to) = match (rank(edge.from), rank(edge.to)) {
+                (Some(from), Some(to)) if to < from => (from, to),
+                _ => continue,
+            };
+            let dependency = &packages[edge.to];
+            findings.push((
+                edge.from,
+                Finding {
+                    rule: "layer-violation".into(),
+                    subject: dependency.name.clone(),
+                    message: format!(
+                        "{} package depends on {}@{} in {}, {} may only depend on {}",
+                        self.order[from],
+                        dependency.name,
+                        dependency.version,
+                        self.order[to],
+                        self.order[from],
+                        self.order[from..].join(", ")
+                    ),
+                },
+            ));
+        }
+        findings
+    }
+}
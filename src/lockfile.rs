@@ -0,0 +1,100 @@
+use crate::error_context::{ContextError, ResultExt};
+use crate::filesystem::FileSystem;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+// there's no way to know what an install weighs without running it, so the size is
+// estimated from a typical installed npm package
+const AVERAGE_INSTALLED_SIZE: u64 = 200 * 1024;
+
+#[derive(Clone)]
+pub struct LockfileStats {
+    pub locked: usize,
+    pub unique_versions: usize,
+    // packages locked at more than one version
+    pub duplicated: usize,
+    pub estimated_size: u64,
+    // name -> version installed at the top of node_modules, what the package's own
+    // dependencies resolve to
+    pub resolved: BTreeMap<String, String>,
+}
+
+// the package name of a lockfile v2/v3 key like "node_modules/a/node_modules/@scope/b"
+fn package_name(key: &str) -> Option<&str> {
+    key.rsplit_once("node_modules/").map(|(_, name)| name)
+}
+
+fn collect_v1(dependencies: &Map<String, Value>, versions: &mut Vec<(String, String)>) {
+    for (name, dependency) in dependencies {
+        if let Some(version) = dependency["version"].as_str() {
+            versions.push((name.clone(), version.to_string()));
+        }
+        if let Some(nested) = dependency["dependencies"].as_object() {
+            collect_v1(nested, versions);
+        }
+    }
+}
+
+// reads the npm package-lock.json next to a package.json, if there is one, adding its
+// size to `bytes_read`
+pub fn read_lockfile(
+    filesystem: &dyn FileSystem,
+    package_dir: &Path,
+    bytes_read: &mut u64,
+) -> Result<Option<LockfileStats>, ContextError> {
+    let path = package_dir.join("package-lock.json");
+    let contents = match filesystem.read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(_) => return Ok(None),
+    };
+    *bytes_read += contents.len() as u64;
+    let v: Value = serde_json::from_str(&contents).context("parsing package-lock.json")?;
+
+    // lockfile v2 and v3 list every installed path under "packages", v1 nests them
+    // under "dependencies". Workspace links point back into the tree and aren't installs
+    let mut versions = Vec::new();
+    let mut resolved = BTreeMap::new();
+    match v["packages"].as_object() {
+        Some(packages) => {
+            for (key, entry) in packages {
+                if entry["link"].as_bool() == Some(true) {
+                    continue;
+                }
+                if let (Some(name), Some(version)) = (package_name(key), entry["version"].as_str())
+                {
+                    versions.push((name.to_string(), version.to_string()));
+                    if key.strip_prefix("node_modules/") == Some(name) {
+                        resolved.insert(name.to_string(), version.to_string());
+                    }
+                }
+            }
+        }
+        None => {
+            if let Some(dependencies) = v["dependencies"].as_object() {
+                collect_v1(dependencies, &mut versions);
+                for (name, dependency) in dependencies {
+                    if let Some(version) = dependency["version"].as_str() {
+                        resolved.insert(name.clone(), version.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    let mut by_name: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for (name, version) in &versions {
+        by_name.entry(name).or_default().insert(version);
+    }
+
+    Ok(Some(LockfileStats {
+        locked: versions.len(),
+        unique_versions: by_name.values().map(|versions| versions.len()).sum(),
+        duplicated: by_name
+            .values()
+            .filter(|versions| versions.len() > 1)
+            .count(),
+        estimated_size: versions.len() as u64 * AVERAGE_INSTALLED_SIZE,
+        resolved,
+    }))
+}
@@ -1,17 +1,95 @@
-use ansi_term::Colour::{Green, Red};
+// the report and results file JSON literals nest deeper than the default allows
+#![recursion_limit = "256"]
+
+use acks::Ack;
+use ansi_term::Colour::{Red, Yellow};
+use args::{Args, Breakdown, DependencyClass};
+use bundlers::EntrypointResolution;
+use checkpoint::{Checkpoint, Counted};
+use config::Config;
+use error_context::{ContextError, ResultExt};
+use filesystem::{FileSystem, FileType, Metadata, StdFileSystem};
+use filters::Filters;
+use graph::GraphSummary;
+use identity::Comparison;
+use lockfile::LockfileStats;
+use module_format::FormatMismatch;
+use output::OutputFormat;
+use pipeline::Output;
+use portability::EdgePortability;
+use progress::ProgressFormat;
+use sampling::{SampleSummary, Sampler};
 use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::env;
 use std::ffi::OsStr;
-use std::fs::{self, DirEntry};
 use std::io::{self};
-use std::os::unix::fs::MetadataExt;
+use std::mem;
 use std::path::{Path, PathBuf};
+use std::process;
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime};
+use typescript::TsResolution;
+use vcs::PackageHistory;
+
+mod acks;
+mod args;
+mod baseline;
+mod bundlers;
+mod checkpoint;
+mod config;
+mod dependency_export;
+mod dependency_usage;
+mod doctor;
+mod error_context;
+mod exit_summary;
+mod exports_lint;
+mod field_census;
+mod files_field;
+mod filesystem;
+mod filters;
+mod fingerprint;
+mod glob;
+mod graph;
+mod graph_export;
+mod identity;
+mod interop;
+mod layers;
+mod lockfile;
+mod manifest;
+mod module_format;
+mod output;
+mod owners;
+mod package_checks;
+mod pages;
+mod pipeline;
+mod portability;
+mod progress;
+mod protocols;
+mod query;
+mod results_file;
+mod rules;
+mod run_manifest;
+mod sampling;
+mod self_check;
+mod sha256;
+mod sources;
+mod syntax;
+mod tags;
+mod tree_diff;
+mod typescript;
+mod vcs;
+mod watcher;
+mod workspaces;
 
 // from https://stackoverflow.com/questions/45291832/extracting-a-file-extension-from-a-given-path-in-rust-idiomatically
 fn get_extension_from_filename(filename: &str) -> Option<&str> {
     Path::new(filename).extension().and_then(OsStr::to_str)
 }
 
+#[derive(Clone, Default)]
 struct ModuleSupport {
     esm_main_mjs: bool,
     esm_type: bool,
@@ -21,141 +99,1126 @@ struct ModuleSupport {
     cjs_exports: bool,
 }
 
+#[derive(Clone, Default)]
 struct PackageDetails {
     name: String,
     version: String,
+    // directory containing the package.json and the sha256 of its contents
+    path: PathBuf,
+    manifest_hash: String,
+    // the top-level fields its package.json declares, only the ones classification needs
+    // when it was too big to read whole
+    manifest_fields: Vec<String>,
+    // "private": true, npm refuses to publish it
+    private: bool,
+    // the node versions its "engines" field supports
+    node_engine: Option<String>,
     module_support: ModuleSupport,
     size: u64,
+    has_exports: bool,
+    // subpath -> condition -> target from the 'exports' field
+    entrypoints: BTreeMap<String, BTreeMap<String, String>>,
+    // ships TypeScript declarations, via 'types'/'typings' or a "types" export condition
+    has_types: bool,
+    // names of the runtime 'dependencies'
+    dependencies: Vec<String>,
+    // every dependency in every class, in manifest order
+    declared_dependencies: Vec<DeclaredDependency>,
+    // name -> version of every dependency declared with a workspace:, file:, link: or
+    // portal: protocol, whatever field it's in
+    local_dependencies: BTreeMap<String, String>,
+    // runtime dependencies found in the scan that only support CommonJS, set for ESM
+    // packages once the whole tree is known
+    cjs_only_dependencies: Vec<String>,
+    // the packages in the scan its dependencies resolve to, by path, the edges of the
+    // dependency graph
+    scan_dependencies: Vec<(PathBuf, DependencyClass)>,
+    // the entrypoint each bundler/runtime would load for `import` and `require`
+    resolutions: Vec<EntrypointResolution>,
+    // number of non-JS entrypoints by asset type, e.g. "css" -> 2
+    asset_exports: BTreeMap<String, usize>,
+    // what gave away a native addon (binding.gyp, prebuilds, ...), empty for pure JS packages
+    native_addon: Vec<String>,
+    lockfile: Option<LockfileStats>,
+    // the ECMAScript edition its entrypoints need, and the syntax that needs it
+    min_es_version: Option<u16>,
+    es_features: Vec<String>,
+    // node builtin modules its sources import, empty unless sources were scanned
+    node_builtins: Vec<String>,
+    // file:line of import() and require() calls with a computed specifier. With any, the
+    // imports found in its sources are approximate
+    dynamic_imports: Vec<String>,
+    // file:line of relative imports that rely on CommonJS-style extension or index
+    // resolution, empty unless sources were scanned
+    implicit_imports: Vec<String>,
+    // entry files written for the other module system than the one they're loaded as
+    format_mismatches: Vec<FormatMismatch>,
+    // with --ts-resolution, the mode and whether its types resolve cleanly under it. None
+    // for packages without types
+    typescript: Option<(TsResolution, bool)>,
+    // whether it runs on edge runtimes, and what stands in the way
+    edge_portability: EdgePortability,
+    edge_portability_reasons: Vec<String>,
+    // runtimes the package targets: node, browser, electron, react-native
+    platforms: Vec<Cow<'static, str>>,
+    // other paths (e.g. through symlinks) that lead to this same package
+    aliases: Vec<PathBuf>,
+    // the package outside node_modules that keeps this one's name, when both declare it
+    duplicate_of: Option<PathBuf>,
+    // the globs of its "workspaces" field or pnpm-workspace.yaml, set for workspace roots
+    workspace_patterns: Vec<String>,
+    // taken in by a workspace root's patterns, and the root that did. A root has itself as
+    // its workspace_root without being a member
+    workspace_member: bool,
+    workspace_root: Option<PathBuf>,
+    // a workspace root whose .npmrc sets link-workspace-packages=false
+    unlinked_workspace_packages: bool,
+    // team from the --owners mapping
+    owner: Option<String>,
+    // architecture layer from the config file
+    layer: Option<String>,
+    // from git, with --git-history
+    history: Option<PackageHistory>,
+    // from the optional "walker" block in package.json
+    tags: Vec<String>,
+    ignore_rules: Vec<String>,
+    partial: bool,
+    findings: Vec<Finding>,
+    suppressed_findings: Vec<Finding>,
+    // accepted in the acknowledgements file, listed but not counted
+    acknowledged_findings: Vec<Finding>,
+    // informational, worth knowing about but not a problem, never counted
+    notes: Vec<Finding>,
+    errors: Vec<ContextError>,
+}
+
+#[derive(Clone)]
+struct DeclaredDependency {
+    name: String,
+    class: DependencyClass,
+    // the version range, tag or protocol as written in package.json
+    spec: String,
+}
+
+#[derive(Clone)]
+struct Finding {
+    rule: Cow<'static, str>,
+    // what the finding is about, the file, field or dependency it names without a line or
+    // the checkout around it, so its fingerprint holds while the code moves. Empty when
+    // the rule can only fire once per package
+    subject: String,
+    message: String,
+}
+
+// locations a single finding lists before summing up the rest
+const MAX_LISTED_LOCATIONS: usize = 5;
+
+fn list_locations(locations: &[String]) -> String {
+    match locations.len() > MAX_LISTED_LOCATIONS {
+        true => format!(
+            "{}, {} more",
+            locations[..MAX_LISTED_LOCATIONS].join(", "),
+            locations.len() - MAX_LISTED_LOCATIONS
+        ),
+        false => locations.join(", "),
+    }
+}
+
+// the files a list of "file:line" locations is in, for a finding's subject
+fn location_files(locations: &[String]) -> String {
+    locations
+        .iter()
+        .map(|location| {
+            location
+                .rsplit_once(':')
+                .map_or(location.as_str(), |(file, _)| file)
+        })
+        .collect::<BTreeSet<&str>>()
+        .into_iter()
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
+
+// settings dir_handler needs, the same for every package in the walk
+struct AnalysisOptions<'a> {
+    filesystem: &'a dyn FileSystem,
+    max_manifest_size: u64,
+    // read each package's own source files too, not just its manifest
+    scan_sources: bool,
+    ts_resolution: Option<TsResolution>,
+    // manifests already parsed, by the sha256 of their bytes. Vendored copies of a package
+    // often have byte for byte the same package.json
+    manifests: RefCell<ManifestCache>,
+}
+
+// the most manifests kept parsed. --watch keeps the cache from one run to the next, so
+// without a limit it would end up holding every package.json the tree ever had
+const MANIFEST_CACHE_ENTRIES: usize = 20_000;
+
+#[derive(Default)]
+struct ManifestCache {
+    // each with how long it took to parse
+    parsed: HashMap<String, (Rc<ParsedManifest>, Duration)>,
+    // oldest first, the first to go once the cache is full
+    order: VecDeque<String>,
+    statistics: CacheStatistics,
+}
+
+#[derive(Clone, Copy, Default)]
+struct CacheStatistics {
+    hits: usize,
+    misses: usize,
+    evictions: usize,
+    // how long parsing the manifests that were hits would have taken
+    time_saved: Duration,
+}
+
+impl ManifestCache {
+    fn get(&mut self, hash: &str) -> Option<Rc<ParsedManifest>> {
+        match self.parsed.get(hash) {
+            Some((parsed, parse_time)) => {
+                self.statistics.hits += 1;
+                self.statistics.time_saved += *parse_time;
+                Some(parsed.clone())
+            }
+            None => {
+                self.statistics.misses += 1;
+                None
+            }
+        }
+    }
+
+    fn insert(&mut self, hash: String, parsed: Rc<ParsedManifest>, parse_time: Duration) {
+        if self.order.len() >= MANIFEST_CACHE_ENTRIES {
+            if let Some(oldest) = self.order.pop_front() {
+                self.parsed.remove(&oldest);
+                self.statistics.evictions += 1;
+            }
+        }
+        self.order.push_back(hash.clone());
+        self.parsed.insert(hash, (parsed, parse_time));
+    }
+}
+
+// what a package.json alone decides, the same wherever the manifest is
+struct ParsedManifest {
+    value: Value,
+    encoding: Option<&'static str>,
+    exports_findings: Vec<Finding>,
+}
+
+impl ParsedManifest {
+    fn new(value: Value, encoding: Option<&'static str>) -> ParsedManifest {
+        let exports_findings = exports_lint::lint_exports(&value["exports"]);
+        ParsedManifest {
+            value,
+            encoding,
+            exports_findings,
+        }
+    }
 }
 
 struct PackageValidation {
     is_package: bool,
     package_details: PackageDetails,
+    // size of the manifest and lockfile read to analyze the package
+    bytes_read: u64,
+}
+
+struct AnalysisResults {
+    packages: Vec<PackageDetails>,
+    errors: Vec<ContextError>,
+    timed_out: bool,
+    error_limit_reached: bool,
+    // set when only a sample of the discovered packages was analyzed
+    sample: Option<SampleSummary>,
+    breakdown: Option<(Breakdown, Vec<(String, LocationStatistics)>)>,
+    walk: WalkStatistics,
+    graph: Option<GraphSummary>,
+    // with --compare-with, what changed since the earlier run
+    comparison: Option<Comparison>,
+}
+
+impl AnalysisResults {
+    // returns the number of asset-bearing packages and their asset entrypoints by type
+    fn asset_counts(&self) -> (usize, BTreeMap<&str, usize>) {
+        let mut asset_bearing = 0;
+        let mut counts = BTreeMap::new();
+        for package_details in &self.packages {
+            if !package_details.asset_exports.is_empty() {
+                asset_bearing += 1;
+            }
+            for (asset_type, count) in &package_details.asset_exports {
+                *counts.entry(asset_type.as_str()).or_insert(0) += count;
+            }
+        }
+        (asset_bearing, counts)
+    }
+
+    fn platform_counts(&self) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        for package_details in &self.packages {
+            for platform in &package_details.platforms {
+                *counts.entry(platform.as_ref()).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    fn partial(&self) -> bool {
+        self.timed_out || self.error_limit_reached || self.packages.iter().any(|p| p.partial)
+    }
+
+    // returns the number of reported and suppressed findings
+    fn finding_counts(&self) -> (usize, usize) {
+        let findings = self.packages.iter().map(|p| p.findings.len()).sum();
+        let suppressed_findings = self
+            .packages
+            .iter()
+            .map(|p| p.suppressed_findings.len())
+            .sum();
+        (findings, suppressed_findings)
+    }
+
+    // dependencies going up the configured layers, as reported (not suppressed or
+    // acknowledged) findings
+    fn layer_violations(&self) -> Vec<(&PackageDetails, &Finding)> {
+        self.packages
+            .iter()
+            .flat_map(|p| p.findings.iter().map(move |finding| (p, finding)))
+            .filter(|(_, finding)| finding.rule == "layer-violation")
+            .collect()
+    }
+
+    fn acknowledged_count(&self) -> usize {
+        self.packages
+            .iter()
+            .map(|p| p.acknowledged_findings.len())
+            .sum()
+    }
 }
 
-fn print_result(package_validation: PackageValidation) {
-    let PackageDetails {
-        module_support,
-        name,
-        version,
-        size,
-    } = package_validation.package_details;
+struct PerformanceMetrics {
+    started: Instant,
+    package_timings: Vec<(String, Duration)>,
+    manifest_cache: CacheStatistics,
+}
 
-    let esm = module_support.esm_type
+// state shared across the whole walk
+struct Scan<'a> {
+    // where the tree is read from
+    filesystem: &'a dyn FileSystem,
+    deadline: Option<Instant>,
+    package_timeout: Option<Duration>,
+    // budget deadline and time spent in nested packages for the package currently being walked
+    package_deadline: Option<Instant>,
+    nested_package_time: Duration,
+    packages: Vec<PackageDetails>,
+    errors: Vec<ContextError>,
+    metrics: PerformanceMetrics,
+    walk: WalkStatistics,
+    // canonical path -> the path it was first walked through, and the other paths
+    // that led back to an already walked directory
+    visited: HashMap<PathBuf, PathBuf>,
+    aliases: HashMap<PathBuf, Vec<PathBuf>>,
+    // include and exclude patterns are matched against paths relative to the scan root
+    root: PathBuf,
+    filters: Filters,
+    // packages inside git submodules and vendored repositories are tagged as such
+    skip_submodules: bool,
+    submodules: HashSet<PathBuf>,
+    nested_repo: Option<&'static str>,
+    // the device the walk started on, with --one-file-system mount points aren't crossed
+    one_file_system: bool,
+    root_device: Option<u64>,
+    // with --skip-dirs-larger-than, the sizes of directories not reached yet, each
+    // counted once
+    max_dir_size: Option<u64>,
+    dir_sizes: HashMap<PathBuf, u64>,
+    // with --modified-since
+    modified_since: Option<SystemTime>,
+    // warnings and errors found so far, shown live on a terminal or as JSON events
+    max_errors: Option<usize>,
+    warning_count: usize,
+    error_count: usize,
+    progress: Option<ProgressFormat>,
+    sampler: Option<Sampler>,
+    // set once the whole tree is known and the graph analyses ran
+    graph: Option<GraphSummary>,
+    // with --checkpoint, the subtrees walked so far
+    checkpoint: Option<Checkpoint>,
+    // called with each package as soon as it's analyzed, before the rest of the tree is
+    on_package: Option<&'a dyn Fn(&PackageDetails)>,
+}
+
+fn supports_esm(module_support: &ModuleSupport) -> bool {
+    module_support.esm_type
         || module_support.esm_exports
         || module_support.esm_partial
-        || module_support.esm_main_mjs;
+        || module_support.esm_main_mjs
+}
 
-    let cjs = module_support.cjs_type
-        || (!module_support.esm_type
-            && !module_support.esm_exports
-            && !module_support.esm_partial
-            && !module_support.esm_main_mjs);
+fn supports_cjs(module_support: &ModuleSupport) -> bool {
+    module_support.cjs_type || !supports_esm(module_support)
+}
 
-    let print_esm = match esm {
-        true => Green.paint("true"),
-        false => Red.paint("false"),
-    };
+fn package_findings(package_details: &PackageDetails) -> Vec<Finding> {
+    let mut findings = Vec::new();
 
-    let print_cjs = match cjs {
-        true => Green.paint("true"),
-        false => Red.paint("false"),
-    };
+    if !supports_esm(&package_details.module_support) {
+        findings.push(Finding {
+            rule: "cjs-only".into(),
+            subject: String::new(),
+            message: "package only supports CommonJS".to_string(),
+        });
+    }
+
+    if !package_details.has_exports {
+        findings.push(Finding {
+            rule: "missing-exports".into(),
+            subject: String::new(),
+            message: "package has no 'exports' field".to_string(),
+        });
+    }
+
+    if bundlers::resolutions_diverge(&package_details.resolutions) {
+        let describe = |select: fn(&EntrypointResolution) -> &Option<String>| {
+            package_details
+                .resolutions
+                .iter()
+                .map(|resolution| {
+                    let target = match select(resolution) {
+                        Some(path) => path.as_str(),
+                        None => "not exported",
+                    };
+                    format!("{}: {}", resolution.resolver, target)
+                })
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        findings.push(Finding {
+            rule: "bundler-divergence".into(),
+            subject: String::new(),
+            message: format!(
+                "bundlers resolve different entrypoints - import ({}), require ({})",
+                describe(|resolution| &resolution.import),
+                describe(|resolution| &resolution.require)
+            ),
+        });
+    }
+
+    findings
+}
+
+// packages without tags are grouped under "untagged", packages with several tags
+// show up in each of their groups
+fn group_by_tag(packages: &[PackageDetails]) -> BTreeMap<&str, Vec<&PackageDetails>> {
+    let mut groups: BTreeMap<&str, Vec<&PackageDetails>> = BTreeMap::new();
+    for package_details in packages {
+        if package_details.tags.is_empty() {
+            groups.entry("untagged").or_default().push(package_details);
+        }
+        for tag in &package_details.tags {
+            groups
+                .entry(tag.as_str())
+                .or_default()
+                .push(package_details);
+        }
+    }
+    groups
+}
+
+fn group_by_owner(packages: &[PackageDetails]) -> BTreeMap<&str, Vec<&PackageDetails>> {
+    let mut groups: BTreeMap<&str, Vec<&PackageDetails>> = BTreeMap::new();
+    for package_details in packages {
+        let owner = package_details.owner.as_deref().unwrap_or("unowned");
+        groups.entry(owner).or_default().push(package_details);
+    }
+    groups
+}
+
+// dependencies of the given classes
+fn dependency_count(package_details: &PackageDetails, classes: &[DependencyClass]) -> usize {
+    package_details
+        .declared_dependencies
+        .iter()
+        .filter(|dependency| classes.contains(&dependency.class))
+        .count()
+}
+
+fn dependency_counts(package_details: &PackageDetails) -> BTreeMap<&'static str, usize> {
+    let mut counts = BTreeMap::new();
+    for dependency in &package_details.declared_dependencies {
+        *counts.entry(dependency.class.label()).or_insert(0) += 1;
+    }
+    counts
+}
+
+struct DependencyStatistics {
+    total: usize,
+    median: usize,
+    p90: usize,
+}
+
+fn dependency_statistics<'a>(
+    packages: impl IntoIterator<Item = &'a PackageDetails>,
+    classes: &[DependencyClass],
+) -> Option<DependencyStatistics> {
+    let mut counts: Vec<usize> = packages
+        .into_iter()
+        .map(|p| dependency_count(p, classes))
+        .collect();
+    counts.sort_unstable();
+    // nearest rank, so every percentile is a count some package actually has
+    let percentile = |p: usize| counts[(counts.len() * p).div_ceil(100).max(1) - 1];
+    match counts.is_empty() {
+        true => None,
+        false => Some(DependencyStatistics {
+            total: counts.iter().sum(),
+            median: percentile(50),
+            p90: percentile(90),
+        }),
+    }
+}
+
+struct TagStatistics {
+    packages: usize,
+    esm_percentage: f64,
+    average_size: u64,
+    errors: usize,
+}
+
+fn tag_statistics(packages: &[PackageDetails]) -> BTreeMap<&str, TagStatistics> {
+    group_by_tag(packages)
+        .into_iter()
+        .map(|(tag, packages)| {
+            let esm_count = packages
+                .iter()
+                .filter(|p| supports_esm(&p.module_support))
+                .count();
+            let total_size: u64 = packages.iter().map(|p| p.size).sum();
+
+            let statistics = TagStatistics {
+                packages: packages.len(),
+                esm_percentage: esm_count as f64 * 100.0 / packages.len() as f64,
+                average_size: total_size / packages.len() as u64,
+                errors: packages.iter().map(|p| p.errors.len()).sum(),
+            };
+            (tag, statistics)
+        })
+        .collect()
+}
+
+// apps and libraries weigh differently on the numbers, so the summary is also given for
+// each side: packages that can be published and the ones marked "private": true, and
+// workspace packages and vendored ones
+struct ScopeStatistics {
+    packages: usize,
+    esm_percentage: f64,
+    cjs_only: usize,
+    total_size: u64,
+    average_size: u64,
+    dependencies: Option<DependencyStatistics>,
+    findings: usize,
+    errors: usize,
+}
+
+fn scope_statistics(
+    packages: &[PackageDetails],
+    classes: &[DependencyClass],
+) -> Vec<(&'static str, ScopeStatistics)> {
+    split_statistics(
+        packages,
+        classes,
+        &[("published", |p| !p.private), ("private", |p| p.private)],
+    )
+}
 
+// packages of the workspaces in the tree and the rest, copies vendored into it and
+// dependencies installed under node_modules. Empty when there's no workspace
+fn workspace_statistics(
+    packages: &[PackageDetails],
+    classes: &[DependencyClass],
+) -> Vec<(&'static str, ScopeStatistics)> {
+    if !packages.iter().any(|p| p.workspace_root.is_some()) {
+        return Vec::new();
+    }
+    split_statistics(
+        packages,
+        classes,
+        &[
+            ("workspace", |p| p.workspace_root.is_some()),
+            ("vendored", |p| p.workspace_root.is_none()),
+        ],
+    )
+}
+
+// a side's label and which packages are on it
+type SplitSide = (&'static str, fn(&PackageDetails) -> bool);
+
+// the statistics of each side of a split, sides without packages left out
+fn split_statistics(
+    packages: &[PackageDetails],
+    classes: &[DependencyClass],
+    sides: &[SplitSide],
+) -> Vec<(&'static str, ScopeStatistics)> {
+    sides
+        .iter()
+        .filter_map(|&(scope, side)| {
+            let packages: Vec<&PackageDetails> = packages.iter().filter(|p| side(p)).collect();
+            if packages.is_empty() {
+                return None;
+            }
+            let esm_count = packages
+                .iter()
+                .filter(|p| supports_esm(&p.module_support))
+                .count();
+            let total_size: u64 = packages.iter().map(|p| p.size).sum();
+            let statistics = ScopeStatistics {
+                packages: packages.len(),
+                esm_percentage: esm_count as f64 * 100.0 / packages.len() as f64,
+                cjs_only: packages
+                    .iter()
+                    .filter(|p| supports_cjs(&p.module_support) && !supports_esm(&p.module_support))
+                    .count(),
+                total_size,
+                average_size: total_size / packages.len() as u64,
+                dependencies: dependency_statistics(packages.iter().copied(), classes),
+                findings: packages.iter().map(|p| p.findings.len()).sum(),
+                errors: packages.iter().map(|p| p.errors.len()).sum(),
+            };
+            Some((scope, statistics))
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum SkipReason {
+    Excluded,
+    Submodule,
+    PermissionDenied,
+    OtherFilesystem,
+    TooLarge,
+    NotModified,
+}
+
+impl SkipReason {
+    fn label(self) -> &'static str {
+        match self {
+            SkipReason::Excluded => "excluded",
+            SkipReason::Submodule => "submodule",
+            SkipReason::PermissionDenied => "permission denied",
+            SkipReason::OtherFilesystem => "other filesystem",
+            SkipReason::TooLarge => "too large",
+            SkipReason::NotModified => "not modified",
+        }
+    }
+
+    fn parse(label: &str) -> Option<SkipReason> {
+        [
+            SkipReason::Excluded,
+            SkipReason::Submodule,
+            SkipReason::PermissionDenied,
+            SkipReason::OtherFilesystem,
+            SkipReason::TooLarge,
+            SkipReason::NotModified,
+        ]
+        .into_iter()
+        .find(|reason| reason.label() == label)
+    }
+}
+
+#[derive(Default)]
+struct WalkStatistics {
+    // every directory listed and every file looked at, once each however many symlinks
+    // lead to them
+    directories_scanned: usize,
+    files_processed: usize,
+    // file contents actually read, the manifests and lockfiles of analyzed packages
+    bytes_read: u64,
+    // directories the walk didn't go into, and why
+    skipped: Vec<(PathBuf, SkipReason)>,
+    // device id -> what was walked on that filesystem
+    filesystems: BTreeMap<u64, FilesystemStatistics>,
+}
+
+#[derive(Default)]
+struct FilesystemStatistics {
+    directories: usize,
+    // time spent in the directories themselves, not in the ones below them
+    time: Duration,
+}
+
+impl WalkStatistics {
+    fn skipped_counts(&self) -> BTreeMap<&'static str, usize> {
+        let mut counts = BTreeMap::new();
+        for (_, reason) in &self.skipped {
+            *counts.entry(reason.label()).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+struct LocationStatistics {
+    packages: usize,
+    esm_percentage: f64,
+    cjs_only: usize,
+}
+
+// the directory depth of a package below the scan root, or the top-level folder it's in
+fn location_key(package_details: &PackageDetails, root: &Path, breakdown: Breakdown) -> String {
+    let relative_path = package_details
+        .path
+        .strip_prefix(root)
+        .unwrap_or(&package_details.path);
+    let mut components = relative_path
+        .components()
+        .filter(|component| component.as_os_str() != ".");
+
+    match breakdown {
+        Breakdown::Depth => components.count().to_string(),
+        Breakdown::Folder => match components.next() {
+            Some(folder) => folder.as_os_str().to_string_lossy().into_owned(),
+            None => ".".to_string(),
+        },
+    }
+}
+
+fn location_statistics(
+    packages: &[PackageDetails],
+    root: &Path,
+    breakdown: Breakdown,
+) -> Vec<(String, LocationStatistics)> {
+    let mut locations: BTreeMap<String, Vec<&PackageDetails>> = BTreeMap::new();
+    for package_details in packages {
+        locations
+            .entry(location_key(package_details, root, breakdown))
+            .or_default()
+            .push(package_details);
+    }
+
+    let mut statistics: Vec<(String, LocationStatistics)> = locations
+        .into_iter()
+        .map(|(location, packages)| {
+            let esm_count = packages
+                .iter()
+                .filter(|p| supports_esm(&p.module_support))
+                .count();
+            let statistics = LocationStatistics {
+                packages: packages.len(),
+                esm_percentage: esm_count as f64 * 100.0 / packages.len() as f64,
+                cjs_only: packages
+                    .iter()
+                    .filter(|p| supports_cjs(&p.module_support) && !supports_esm(&p.module_support))
+                    .count(),
+            };
+            (location, statistics)
+        })
+        .collect();
+
+    // depths sort numerically rather than as strings
+    if let Breakdown::Depth = breakdown {
+        statistics.sort_by_key(|(depth, _)| depth.parse::<usize>().unwrap_or(0));
+    }
+    statistics
+}
+
+fn timed_out(deadline: Option<Instant>) -> bool {
+    match deadline {
+        Some(deadline) => Instant::now() >= deadline,
+        None => false,
+    }
+}
+
+fn error_limit_reached(scan: &Scan) -> bool {
+    match scan.max_errors {
+        Some(max_errors) => scan.error_count >= max_errors,
+        None => false,
+    }
+}
+
+// keeps a running count of warnings and errors on stderr while the walk is going
+fn report_progress(scan: &mut Scan, message: &str, warnings: usize, errors: usize) {
+    scan.warning_count += warnings;
+    scan.error_count += errors;
+    match scan.progress {
+        Some(ProgressFormat::Text) if warnings + errors > 0 => eprint!(
+            "\r{} warnings, {} errors",
+            Yellow.paint(scan.warning_count.to_string()),
+            Red.paint(scan.error_count.to_string())
+        ),
+        Some(ProgressFormat::Json) => {
+            progress::emit_event("discover", scan.packages.len(), None, message)
+        }
+        _ => {}
+    }
+}
+
+// an error that isn't tied to a package, the walk carries on past it
+fn record_error(scan: &mut Scan, error: impl Into<ContextError>) {
+    let error = error.into();
+    report_progress(scan, &error.to_string(), 0, 1);
+    scan.errors.push(error);
+}
+
+fn print_metrics(metrics: &mut PerformanceMetrics) {
+    println!("Performance:");
+    println!("  Scan time: {:?}", metrics.started.elapsed());
+    println!("  Packages analyzed: {}", metrics.package_timings.len());
+    let cache = metrics.manifest_cache;
     println!(
-        "Package: {}@{}, size: {} - ESM Support: {}, CommonJS: {}",
-        Green.paint(name),
-        Green.paint(version),
-        size,
-        print_esm,
-        print_cjs
+        "  Manifest cache: {} hits, {} misses, {} evictions, {:?} saved",
+        cache.hits, cache.misses, cache.evictions, cache.time_saved
     );
 
-    //if esm {
-    //    let print_esm_type = match module_support.esm_type {
-    //        true => Green.paint("true"),
-    //        false => Red.paint("false"),
-    //    };
+    metrics.package_timings.sort_by(|(_, a), (_, b)| b.cmp(a));
 
-    //    let print_esm_exports = match module_support.esm_exports {
-    //        true => Green.paint("true"),
-    //        false => Red.paint("false"),
-    //    };
-
-    //    let print_esm_partial = match module_support.esm_partial {
-    //        true => Green.paint("true"),
-    //        false => Red.paint("false"),
-    //    };
+    println!("  Slowest packages:");
+    for (name, elapsed) in metrics.package_timings.iter().take(10) {
+        println!("    {} - {:?}", name, elapsed);
+    }
+}
 
-    //    let print_esm_main = match module_support.esm_main_mjs {
-    //        true => Green.paint("true"),
-    //        false => Red.paint("false"),
-    //    };
+// the size of everything under dir, symlinks not followed. Computing it lists the whole
+// subtree, so the sizes of its subdirectories are kept for when the walk reaches them
+fn dir_size(filesystem: &dyn FileSystem, dir: &Path, sizes: &mut HashMap<PathBuf, u64>) -> u64 {
+    if let Some(size) = sizes.get(dir) {
+        return *size;
+    }
+    let mut size = 0;
+    for entry in filesystem.read_dir(dir).into_iter().flatten().flatten() {
+        match entry.file_type {
+            FileType::Dir => size += dir_size(filesystem, &entry.path, sizes),
+            FileType::File => {
+                size += filesystem
+                    .symlink_metadata(&entry.path)
+                    .map(|metadata| metadata.len)
+                    .unwrap_or(0)
+            }
+            _ => {}
+        }
+    }
+    sizes.insert(dir.to_path_buf(), size);
+    size
+}
 
-    //    println!(
-    //         "'type' set to 'module': {}\n'exports' field defined with 'import' prop: {}\n'module' field set: {}\n'main' field references an '.mjs' file: {}",
-    //         print_esm_type, print_esm_exports, print_esm_partial, print_esm_main
-    //     );
-    //}
+// whether the walk leaves a directory out for its size, or a package for the age of its
+// package.json. A skipped package's nested packages are left out with it
+fn traversal_skip(scan: &mut Scan, dir: &Path) -> Option<SkipReason> {
+    if let Some(max_dir_size) = scan.max_dir_size {
+        let size = dir_size(scan.filesystem, dir, &mut scan.dir_sizes);
+        scan.dir_sizes.remove(dir);
+        if size > max_dir_size {
+            return Some(SkipReason::TooLarge);
+        }
+    }
+    if let Some(since) = scan.modified_since {
+        let modified = scan
+            .filesystem
+            .metadata(&dir.join("package.json"))
+            .ok()
+            .and_then(|metadata| metadata.modified);
+        if matches!(modified, Some(modified) if modified < since) {
+            return Some(SkipReason::NotModified);
+        }
+    }
+    None
 }
 
 // one possible implementation of walking a directory only visiting files
-fn walk_dirs(dir: &PathBuf, cb: &dyn Fn(&DirEntry) -> PackageValidation) -> io::Result<()> {
-    if dir.is_dir() {
+// once the deadline passes the walk stops and returns a `TimedOut` error, printing
+// any package found so far on the way back up. A package that runs over its own
+// budget is recorded as an error and the walk carries on with its siblings. Time
+// spent in nested packages doesn't count against the enclosing package. Directories
+// reached a second time (symlinks, overlapping roots) are only recorded as aliases.
+// Git submodules and other nested repositories are tagged, or skipped entirely
+fn walk_dirs(
+    dir: &PathBuf,
+    cb: &dyn Fn(&Path, &Metadata) -> PackageValidation,
+    scan: &mut Scan,
+) -> io::Result<()> {
+    let mut result = Ok(());
+    let filesystem = scan.filesystem;
+
+    // finished by an earlier run, its packages came back with the checkpoint
+    if scan
+        .checkpoint
+        .as_ref()
+        .is_some_and(|checkpoint| checkpoint.is_completed(dir))
+    {
+        return Ok(());
+    }
+
+    if filesystem.is_dir(dir) {
+        let canonical_path = match filesystem.canonicalize(dir).context_at("walk", dir) {
+            Ok(canonical_path) => canonical_path,
+            Err(error) => {
+                record_error(scan, error);
+                return Ok(());
+            }
+        };
+        if let Some(first_path) = scan.visited.get(&canonical_path) {
+            scan.aliases
+                .entry(first_path.clone())
+                .or_default()
+                .push(dir.clone());
+            return Ok(());
+        }
+        scan.visited.insert(canonical_path, dir.clone());
+
+        let device = match filesystem.metadata(dir).context_at("walk", dir) {
+            Ok(metadata) => metadata.device,
+            Err(error) => {
+                record_error(scan, error);
+                return Ok(());
+            }
+        };
+        let root_device = *scan.root_device.get_or_insert(device);
+        if scan.one_file_system && device != root_device {
+            scan.walk
+                .skipped
+                .push((dir.clone(), SkipReason::OtherFilesystem));
+            return Ok(());
+        }
+
+        // an unreadable directory is left out rather than ending the whole walk
+        let entries = match filesystem.read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
+                scan.walk
+                    .skipped
+                    .push((dir.clone(), SkipReason::PermissionDenied));
+                return Ok(());
+            }
+            Err(err) => {
+                record_error(scan, ContextError::from(err).at("walk", dir));
+                return Ok(());
+            }
+        };
+
+        let outer_nested_repo = scan.nested_repo;
+        if *dir != scan.root && filesystem.exists(&dir.join(".git")) {
+            if scan.skip_submodules {
+                scan.walk.skipped.push((dir.clone(), SkipReason::Submodule));
+                return Ok(());
+            }
+            scan.nested_repo = match scan.submodules.contains(dir) {
+                true => Some("submodule"),
+                false => Some("vendored-repo"),
+            };
+        }
+        for submodule in read_gitmodules(filesystem, dir) {
+            scan.submodules.insert(dir.join(submodule));
+        }
+        let counted_before = Counted::of(&scan.walk);
+        scan.walk.directories_scanned += 1;
+
+        let started = Instant::now();
+        let is_package_root = filesystem.is_file(&dir.join("package.json"));
+
+        let outer_package_deadline = scan.package_deadline;
+        let outer_nested_time = scan.nested_package_time;
+        if is_package_root {
+            scan.package_deadline = scan.package_timeout.map(|budget| started + budget);
+            scan.nested_package_time = Duration::ZERO;
+        }
+
+        // packages left out of the sample are still walked through for the ones nested
+        // inside them, but their package.json is never parsed
+        let sampled = match (&mut scan.sampler, is_package_root) {
+            (Some(sampler), true) => {
+                let (sampled, evicted) = sampler.select(dir);
+                if let Some(evicted) = evicted {
+                    scan.packages.retain(|p| p.path != evicted);
+                }
+                sampled
+            }
+            _ => true,
+        };
+
         let mut package_validation = PackageValidation {
             is_package: false,
-            package_details: PackageDetails {
-                name: "".to_string(),
-                version: "".to_string(),
-                module_support: ModuleSupport {
-                    esm_main_mjs: false,
-                    esm_type: false,
-                    esm_exports: false,
-                    esm_partial: false,
-                    cjs_type: false,
-                    cjs_exports: false,
-                },
-                size: 0,
-            },
+            package_details: PackageDetails::default(),
+            bytes_read: 0,
         };
         let mut package_size: u64 = 0;
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_dir() {
-                walk_dirs(&path, cb)?;
+        let mut children_time = Duration::ZERO;
+        for entry in entries {
+            if timed_out(scan.deadline) || timed_out(scan.package_deadline) {
+                result = Err(io::Error::new(io::ErrorKind::TimedOut, "scan timed out"));
+                break;
+            }
+            if error_limit_reached(scan) {
+                result = Err(io::Error::new(
+                    io::ErrorKind::Interrupted,
+                    "error limit reached",
+                ));
+                break;
+            }
+
+            let entry = match entry.context_at("walk", dir) {
+                Ok(entry) => entry,
+                Err(error) => {
+                    record_error(scan, error);
+                    continue;
+                }
+            };
+            let path = entry.path;
+            let is_dir = filesystem.is_dir(&path);
+            let relative_path = path.strip_prefix(&scan.root).unwrap_or(&path);
+            if filters::is_filtered(&mut scan.filters, &relative_path.to_string_lossy(), is_dir) {
+                if is_dir {
+                    scan.walk.skipped.push((path, SkipReason::Excluded));
+                }
+                continue;
+            }
+
+            if is_dir {
+                if let Some(reason) = traversal_skip(scan, &path) {
+                    scan.walk.skipped.push((path, reason));
+                    continue;
+                }
+                let child_started = Instant::now();
+                let child_result = walk_dirs(&path, cb, scan);
+                children_time += child_started.elapsed();
+                if let Err(err) = child_result {
+                    result = Err(err);
+                    break;
+                }
             } else {
-                let metadata = entry.metadata().expect("Unable to retrieve file metadata");
+                let metadata = match filesystem.symlink_metadata(&path).context_at("walk", &path) {
+                    Ok(metadata) => metadata,
+                    Err(error) => {
+                        record_error(scan, error);
+                        continue;
+                    }
+                };
 
-                let file_size = metadata.len();
+                let file_size = metadata.len;
+                scan.walk.files_processed += 1;
 
                 package_size = package_size + file_size;
 
-                let file_package_validation = cb(&entry);
-                if file_package_validation.is_package {
-                    package_validation = file_package_validation
+                if sampled {
+                    let file_package_validation = cb(&path, &metadata);
+                    scan.walk.bytes_read += file_package_validation.bytes_read;
+                    if file_package_validation.is_package {
+                        package_validation = file_package_validation
+                    }
                 }
             }
         }
 
-        if package_validation.is_package {
-            package_validation.package_details.size = package_size;
-            print_result(package_validation)
+        if is_package_root {
+            let total_time = started.elapsed();
+            let package_time = total_time.saturating_sub(scan.nested_package_time);
+
+            let partial = result.is_err();
+            let over_budget = match scan.package_timeout {
+                Some(budget) => partial && package_time >= budget && !timed_out(scan.deadline),
+                None => false,
+            };
+            if over_budget {
+                let name = match package_validation.is_package {
+                    true => package_validation.package_details.name.clone(),
+                    false => dir.display().to_string(),
+                };
+                let error = format!(
+                    "Package {} exceeded its {:?} analysis budget after {:?}, results are partial",
+                    name,
+                    scan.package_timeout.unwrap(),
+                    package_time
+                );
+                match package_validation.is_package {
+                    true => package_validation.package_details.errors.push(error.into()),
+                    false => record_error(scan, error),
+                }
+                result = Ok(());
+            }
+
+            let still_sampled = match &scan.sampler {
+                Some(sampler) => sampler.keeps(dir),
+                None => true,
+            };
+            if package_validation.is_package && still_sampled {
+                let mut package_details = package_validation.package_details;
+                package_details.size = package_size;
+                package_details.partial = partial;
+                scan.metrics
+                    .package_timings
+                    .push((package_details.name.clone(), package_time));
+
+                // dir_handler may already have found problems that need the package
+                // directory or the raw manifest, like 'files' patterns that match nothing
+                // on disk or misordered export conditions
+                let (suppressed, findings) = mem::take(&mut package_details.findings)
+                    .into_iter()
+                    .chain(package_findings(&package_details))
+                    .partition(|finding| {
+                        package_details
+                            .ignore_rules
+                            .iter()
+                            .any(|rule| *rule == finding.rule)
+                    });
+                package_details.findings = findings;
+                package_details.suppressed_findings = suppressed;
+                if let Some(nested_repo) = scan.nested_repo {
+                    package_details.tags.push(nested_repo.to_string());
+                }
+
+                let name = package_details.name.clone();
+                let (warnings, errors) =
+                    (package_details.findings.len(), package_details.errors.len());
+                if let Some(on_package) = scan.on_package {
+                    on_package(&package_details);
+                }
+                scan.packages.push(package_details);
+                report_progress(scan, &name, warnings, errors);
+            }
+
+            // the enclosing package gets the time spent here back on its own budget
+            scan.package_deadline = outer_package_deadline.map(|deadline| deadline + total_time);
+            scan.nested_package_time = outer_nested_time + total_time;
+        }
+
+        let filesystem = scan.walk.filesystems.entry(device).or_default();
+        filesystem.directories += 1;
+        filesystem.time += started.elapsed().saturating_sub(children_time);
+
+        scan.nested_repo = outer_nested_repo;
+
+        if result.is_ok() {
+            if let Some(checkpoint) = &mut scan.checkpoint {
+                checkpoint.complete(dir, Counted::of(&scan.walk).since(counted_before));
+            }
+            checkpoint::save(scan, false);
         }
     }
-    Ok(())
+    result
+}
+
+// the submodule paths declared in a directory's .gitmodules file
+fn read_gitmodules(filesystem: &dyn FileSystem, dir: &Path) -> Vec<PathBuf> {
+    let contents = match filesystem.read_to_string(&dir.join(".gitmodules")) {
+        Ok(contents) => contents,
+        Err(_) => return Vec::new(),
+    };
+
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            match key.trim() == "path" {
+                true => Some(PathBuf::from(value.trim())),
+                false => None,
+            }
+        })
+        .collect()
 }
 
 fn parse_exports(exports: &Map<String, Value>) -> ModuleSupport {
     const SUB_PATH_PATTERNS: [&str; 4] = ["import", "require", "default", "node"];
-    let mut module_support = ModuleSupport {
-        esm_main_mjs: false,
-        esm_type: false,
-        esm_exports: false,
-        esm_partial: false,
-        cjs_type: false,
-        cjs_exports: false,
-    };
+    let mut module_support = ModuleSupport::default();
 
     for (key, value) in exports {
         if value.is_string() {
@@ -182,20 +1245,97 @@ fn parse_exports(exports: &Map<String, Value>) -> ModuleSupport {
     module_support
 }
 
-fn parse_package(v: Value) -> PackageDetails {
-    let mut package_details = PackageDetails {
-        name: "".to_string(),
-        version: "".to_string(),
-        module_support: ModuleSupport {
-            esm_main_mjs: false,
-            esm_type: false,
-            esm_exports: false,
-            esm_partial: false,
-            cjs_type: false,
-            cjs_exports: false,
-        },
-        size: 0,
-    };
+// flattens the 'exports' field into subpath -> condition -> target. Nested conditions are
+// joined with '+' (e.g. "import+types"), fallback arrays resolve to their first usable
+// target and null targets (excluded subpaths) are left out
+fn parse_exports_entrypoints(exports: &Value) -> BTreeMap<String, BTreeMap<String, String>> {
+    let mut entrypoints = BTreeMap::new();
+
+    match exports.as_object() {
+        Some(subpaths) if subpaths.keys().any(|key| key.starts_with('.')) => {
+            for (subpath, target) in subpaths {
+                let mut conditions = BTreeMap::new();
+                collect_export_targets(target, "", &mut conditions);
+                if !conditions.is_empty() {
+                    entrypoints.insert(subpath.clone(), conditions);
+                }
+            }
+        }
+        _ => {
+            let mut conditions = BTreeMap::new();
+            collect_export_targets(exports, "", &mut conditions);
+            if !conditions.is_empty() {
+                entrypoints.insert(".".to_string(), conditions);
+            }
+        }
+    }
+
+    entrypoints
+}
+
+fn collect_export_targets(
+    target: &Value,
+    condition: &str,
+    conditions: &mut BTreeMap<String, String>,
+) {
+    match target {
+        Value::String(path) => {
+            let condition = match condition.is_empty() {
+                true => "default",
+                false => condition,
+            };
+            conditions
+                .entry(condition.to_string())
+                .or_insert_with(|| path.clone());
+        }
+        Value::Array(targets) => {
+            let fallback = targets
+                .iter()
+                .find(|target| target.is_string() || target.is_object());
+            if let Some(fallback) = fallback {
+                collect_export_targets(fallback, condition, conditions);
+            }
+        }
+        Value::Object(nested) => {
+            for (key, value) in nested {
+                let nested_condition = match condition.is_empty() {
+                    true => key.clone(),
+                    false => format!("{}+{}", condition, key),
+                };
+                collect_export_targets(value, &nested_condition, conditions);
+            }
+        }
+        _ => {}
+    }
+}
+
+const ASSET_EXTENSIONS: [&str; 4] = ["css", "json", "wasm", "node"];
+
+// counts the distinct 'main' and 'exports' targets that aren't JavaScript
+fn count_asset_exports(
+    main_field: Option<&str>,
+    entrypoints: &BTreeMap<String, BTreeMap<String, String>>,
+) -> BTreeMap<String, usize> {
+    let targets: BTreeSet<&str> = entrypoints
+        .values()
+        .flat_map(|conditions| conditions.values())
+        .map(|target| target.as_str())
+        .chain(main_field)
+        .collect();
+
+    let mut asset_exports = BTreeMap::new();
+    for target in targets {
+        if let Some(extension) = get_extension_from_filename(target) {
+            if ASSET_EXTENSIONS.contains(&extension) {
+                *asset_exports.entry(extension.to_string()).or_insert(0) += 1;
+            }
+        }
+    }
+    asset_exports
+}
+
+fn parse_package(v: &Value) -> PackageDetails {
+    let mut package_details = PackageDetails::default();
 
     // get the package name
     let package_name = v["name"].as_str();
@@ -242,6 +1382,53 @@ fn parse_package(v: Value) -> PackageDetails {
     }
 
     // check the 'exports' field in package.json
+    package_details.has_exports = !v["exports"].is_null();
+    package_details.entrypoints = parse_exports_entrypoints(&v["exports"]);
+    package_details.resolutions = bundlers::simulate_resolution(v);
+    package_details.asset_exports = count_asset_exports(main_field, &package_details.entrypoints);
+    package_details.has_types = !v["types"].is_null()
+        || !v["typings"].is_null()
+        || package_details
+            .entrypoints
+            .values()
+            .flat_map(|conditions| conditions.keys())
+            .any(|condition| condition.split('+').any(|c| c == "types"));
+    package_details.dependencies = v["dependencies"]
+        .as_object()
+        .map(|dependencies| dependencies.keys().cloned().collect())
+        .unwrap_or_default();
+    package_details.declared_dependencies = DependencyClass::ALL
+        .into_iter()
+        .filter_map(|class| Some((class, v[class.field()].as_object()?)))
+        .flat_map(|(class, dependencies)| {
+            dependencies
+                .iter()
+                .map(move |(name, spec)| DeclaredDependency {
+                    name: name.clone(),
+                    class,
+                    spec: spec.as_str().unwrap_or_default().to_string(),
+                })
+        })
+        .collect();
+    package_details.local_dependencies = DependencyClass::ALL
+        .iter()
+        .filter_map(|class| v[class.field()].as_object())
+        .flatten()
+        .filter_map(|(name, spec)| {
+            let spec = spec.as_str()?;
+            protocols::local_protocol(spec)?;
+            Some((name.clone(), spec.to_string()))
+        })
+        .collect();
+
+    package_details.private = v["private"].as_bool() == Some(true);
+    package_details.node_engine = v["engines"]["node"].as_str().map(String::from);
+    package_details.workspace_patterns = workspaces::manifest_patterns(v);
+    package_details.manifest_fields = v
+        .as_object()
+        .map(|fields| fields.keys().cloned().collect())
+        .unwrap_or_default();
+
     let exports = v["exports"].as_object();
     if exports.is_some() {
         let export_module_support = parse_exports(exports.unwrap());
@@ -254,49 +1441,914 @@ fn parse_package(v: Value) -> PackageDetails {
         };
     }
 
+    // check the 'walker' config block in package.json
+    package_details.tags = string_array(&v["walker"]["tags"]);
+    package_details.ignore_rules = string_array(&v["walker"]["ignoreRules"]);
+
+    let platforms = detect_platforms(v, &package_details.entrypoints);
+    for platform in ["electron", "react-native"] {
+        if platforms.contains(&platform) {
+            package_details.tags.push(platform.to_string());
+        }
+    }
+    package_details.platforms = platforms.into_iter().map(Cow::Borrowed).collect();
+
     package_details
 }
 
-fn dir_handler(entry: &DirEntry) -> PackageValidation {
-    let path = entry.path();
-    let file_name = entry.file_name();
+fn has_dependency(v: &Value, name: &str) -> bool {
+    ["dependencies", "devDependencies", "peerDependencies"]
+        .iter()
+        .any(|field| !v[*field][name].is_null())
+}
+
+fn detect_platforms(
+    v: &Value,
+    entrypoints: &BTreeMap<String, BTreeMap<String, String>>,
+) -> Vec<&'static str> {
+    let conditions: BTreeSet<&str> = entrypoints
+        .values()
+        .flat_map(|conditions| conditions.keys())
+        .flat_map(|condition| condition.split('+'))
+        .collect();
+
+    let mut platforms = Vec::new();
+
+    if !v["browser"].is_null() || conditions.contains("browser") {
+        platforms.push("browser");
+    }
+    if conditions.contains("electron") || has_dependency(v, "electron") {
+        platforms.push("electron");
+    }
+    if !v["react-native"].is_null()
+        || conditions.contains("react-native")
+        || has_dependency(v, "react-native")
+    {
+        platforms.push("react-native");
+    }
+    if platforms.is_empty() || conditions.contains("node") || !v["engines"]["node"].is_null() {
+        platforms.insert(0, "node");
+    }
+
+    platforms
+}
+
+const NATIVE_ADDON_DEPENDENCIES: [&str; 6] = [
+    "node-gyp-build",
+    "prebuild-install",
+    "@mapbox/node-pre-gyp",
+    "node-pre-gyp",
+    "node-addon-api",
+    "nan",
+];
+
+fn detect_native_addon(filesystem: &dyn FileSystem, v: &Value, package_dir: &Path) -> Vec<String> {
+    let mut evidence = Vec::new();
+
+    if filesystem.is_file(&package_dir.join("binding.gyp")) {
+        evidence.push("binding.gyp".to_string());
+    }
+
+    // prebuildify ships binaries in prebuilds/, prebuild and node-pre-gyp in build/Release
+    if filesystem.is_dir(&package_dir.join("prebuilds")) {
+        evidence.push("prebuilds/".to_string());
+    }
+    if filesystem.is_dir(&package_dir.join("build").join("Release")) {
+        evidence.push("build/Release/".to_string());
+    }
+
+    if v["gypfile"].as_bool() == Some(true) {
+        evidence.push("'gypfile' field".to_string());
+    }
+
+    for dependency in NATIVE_ADDON_DEPENDENCIES {
+        if !v["dependencies"][dependency].is_null() {
+            evidence.push(format!("depends on {}", dependency));
+        }
+    }
+
+    evidence
+}
+
+fn string_array(value: &Value) -> Vec<String> {
+    match value.as_array() {
+        Some(values) => values
+            .iter()
+            .filter_map(|value| value.as_str())
+            .map(|value| value.to_string())
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+fn dir_handler(path: &Path, metadata: &Metadata, options: &AnalysisOptions) -> PackageValidation {
+    let filesystem = options.filesystem;
+    let max_manifest_size = options.max_manifest_size;
+    let file_name = path.file_name().unwrap_or_default();
     let mut package_validation = PackageValidation {
         is_package: false,
-        package_details: PackageDetails {
-            name: "".to_string(),
-            version: "".to_string(),
-            module_support: ModuleSupport {
-                esm_main_mjs: false,
-                esm_type: false,
-                esm_exports: false,
-                esm_partial: false,
-                cjs_type: false,
-                cjs_exports: false,
-            },
-            size: 0,
-        },
+        package_details: PackageDetails::default(),
+        bytes_read: 0,
     };
     if file_name == "package.json" {
         package_validation.is_package = true;
-        let contents = fs::read_to_string(path).expect("Unable to read file {path}");
+        let package_dir = path.parent().unwrap().to_path_buf();
 
-        let v: Value = serde_json::from_str(&contents).expect("Unable to parse JSON");
+        // an oversized manifest is only skimmed for the fields classification needs
+        let manifest_size = metadata.len;
+        let oversized = manifest_size > max_manifest_size;
+        let manifest = match oversized {
+            false => filesystem
+                .read(path)
+                .context("reading package.json")
+                .and_then(|bytes| {
+                    let hash = sha256::sha256_hex(&bytes);
+                    if let Some(parsed) = options.manifests.borrow_mut().get(&hash) {
+                        return Ok((parsed, bytes.len() as u64, hash, None));
+                    }
+                    let parsing = Instant::now();
+                    let (contents, encoding) =
+                        manifest::decode(&bytes).context("decoding package.json")?;
+                    let v: Value =
+                        serde_json::from_str(&contents).context("parsing package.json")?;
+                    let parsed = Rc::new(ParsedManifest::new(v, encoding));
+                    options.manifests.borrow_mut().insert(
+                        hash.clone(),
+                        parsed.clone(),
+                        parsing.elapsed(),
+                    );
+                    Ok((parsed, bytes.len() as u64, hash, None))
+                }),
+            true => manifest::read_partial(
+                filesystem,
+                path,
+                &manifest::CLASSIFICATION_FIELDS,
+                max_manifest_size,
+            )
+            .context("reading package.json")
+            .map(|partial| {
+                (
+                    Rc::new(ParsedManifest::new(partial.fields, None)),
+                    partial.bytes_read,
+                    String::new(),
+                    partial.error,
+                )
+            }),
+        }
+        .context_at("analyze", &package_dir);
+        let (parsed, manifest_bytes, manifest_hash, partial_error) = match manifest {
+            Ok(manifest) => manifest,
+            // still reported, by its directory, so the broken manifest shows up
+            Err(error) => {
+                package_validation.package_details.name = package_dir.display().to_string();
+                package_validation.package_details.path = package_dir;
+                package_validation.package_details.errors.push(error);
+                return package_validation;
+            }
+        };
+        let v = &parsed.value;
+        let native_addon = detect_native_addon(filesystem, v, &package_dir);
+        let mut findings = Vec::new();
+        if oversized {
+            findings.push(Finding {
+                rule: "oversized-manifest".into(),
+                subject: String::new(),
+                message: format!(
+                    "package.json is {} bytes, over the {} byte limit, only {} were read",
+                    manifest_size,
+                    max_manifest_size,
+                    manifest::CLASSIFICATION_FIELDS.join(", ")
+                ),
+            });
+        }
+        if let Some(encoding) = parsed.encoding {
+            findings.push(Finding {
+                rule: "manifest-encoding".into(),
+                subject: String::new(),
+                message: format!(
+                    "package.json is {}, tools that expect plain UTF-8 fail to parse it",
+                    encoding
+                ),
+            });
+        }
+        findings.extend(files_field::check_files_field(filesystem, v, &package_dir));
+        findings.extend(parsed.exports_findings.iter().cloned());
 
         package_validation.package_details = parse_package(v);
+        let package_details = &package_validation.package_details;
+        findings.extend(package_checks::check_entrypoints(
+            filesystem,
+            v,
+            &package_dir,
+            &package_details.entrypoints,
+        ));
+        findings.extend(package_checks::check_missing_types(
+            filesystem,
+            v,
+            &package_dir,
+            package_details.has_types,
+        ));
+        findings.extend(package_checks::check_lockfiles(filesystem, &package_dir));
+        findings.extend(interop::default_export_hazards(
+            filesystem,
+            &package_dir,
+            &package_details.resolutions,
+        ));
+        // types listed after other conditions are already reported by the exports lint,
+        // but they still keep the package from resolving cleanly
+        if let Some(resolution) = options.ts_resolution {
+            if let Some(ts_findings) =
+                typescript::check_types(filesystem, v, &package_dir, resolution)
+            {
+                let compliant = ts_findings.is_empty()
+                    && !findings
+                        .iter()
+                        .any(|finding| finding.rule == "exports-types-order");
+                package_validation.package_details.typescript = Some((resolution, compliant));
+                findings.extend(ts_findings);
+            }
+        }
+        package_validation.package_details.findings = findings;
+        if !native_addon.is_empty() {
+            package_validation
+                .package_details
+                .tags
+                .push("native-addon".to_string());
+        }
+        package_validation.package_details.native_addon = native_addon;
+        if let Some(error) = partial_error {
+            let error = format!("package.json only partly read, {}", error);
+            package_validation
+                .package_details
+                .errors
+                .push(ContextError::from(error).at("analyze", &package_dir));
+        }
+        package_validation.bytes_read = manifest_bytes;
+        match lockfile::read_lockfile(filesystem, &package_dir, &mut package_validation.bytes_read)
+            .context_at("analyze", &package_dir)
+        {
+            Ok(lockfile) => package_validation.package_details.lockfile = lockfile,
+            Err(error) => package_validation.package_details.errors.push(error),
+        }
+        let pnpm_patterns =
+            workspaces::pnpm_patterns(filesystem, &package_dir, &mut package_validation.bytes_read);
+        package_validation
+            .package_details
+            .workspace_patterns
+            .extend(pnpm_patterns);
+        if !package_validation
+            .package_details
+            .workspace_patterns
+            .is_empty()
+        {
+            package_validation
+                .package_details
+                .unlinked_workspace_packages = workspaces::unlinked_workspace_packages(
+                filesystem,
+                &package_dir,
+                &mut package_validation.bytes_read,
+            );
+        }
+        if let Some(syntax) = sources::entrypoint_syntax(
+            filesystem,
+            &package_dir,
+            &package_validation.package_details.resolutions,
+            &mut package_validation.bytes_read,
+        ) {
+            package_validation.package_details.min_es_version = Some(syntax.version);
+            package_validation.package_details.es_features =
+                syntax.features.into_iter().map(String::from).collect();
+            for (entry, line) in syntax.top_level_await {
+                package_validation.package_details.findings.push(Finding {
+                    rule: "top-level-await".into(),
+                    subject: entry.clone(),
+                    message: format!(
+                        "{}:{} uses top-level await, require() of it fails and some bundlers can't handle it",
+                        entry, line
+                    ),
+                });
+            }
+        }
+        let format_mismatches = module_format::check_entry_formats(
+            filesystem,
+            v,
+            &package_dir,
+            &package_validation.package_details.entrypoints,
+            &mut package_validation.bytes_read,
+        );
+        for mismatch in &format_mismatches {
+            package_validation.package_details.findings.push(Finding {
+                rule: "module-format-mismatch".into(),
+                subject: mismatch.entry.clone(),
+                message: format!(
+                    "{} ({}) is loaded as {} but written as {}, it fails to load",
+                    mismatch.entry,
+                    mismatch.file,
+                    module_format::format_label(&mismatch.declared),
+                    module_format::format_label(&mismatch.syntax)
+                ),
+            });
+        }
+        package_validation.package_details.format_mismatches = format_mismatches;
+        if options.scan_sources {
+            let analysis = sources::analyze_sources(
+                filesystem,
+                &package_dir,
+                &mut package_validation.bytes_read,
+            );
+            package_validation.package_details.node_builtins =
+                analysis.builtins.into_iter().collect();
+            if !analysis.dynamic_loads.is_empty() {
+                package_validation.package_details.findings.push(Finding {
+                    rule: "dynamic-import".into(),
+                    subject: location_files(&analysis.dynamic_loads),
+                    message: format!(
+                        "import() or require() of a computed specifier at {}, bundlers can't follow it and its imports are approximate",
+                        list_locations(&analysis.dynamic_loads)
+                    ),
+                });
+            }
+            // scripts handed to workers and child processes are usually found relative to
+            // __dirname, which moves when the package is bundled or converted to ESM
+            if !analysis.workers.is_empty() {
+                package_validation.package_details.notes.push(Finding {
+                    rule: "starts-workers".into(),
+                    subject: location_files(&analysis.workers),
+                    message: format!(
+                        "starts worker threads at {}, check the script paths survive bundling and ESM",
+                        list_locations(&analysis.workers)
+                    ),
+                });
+            }
+            if !analysis.child_processes.is_empty() {
+                package_validation.package_details.notes.push(Finding {
+                    rule: "spawns-processes".into(),
+                    subject: location_files(&analysis.child_processes),
+                    message: format!(
+                        "spawns child processes at {}, check the script paths survive bundling and ESM",
+                        list_locations(&analysis.child_processes)
+                    ),
+                });
+            }
+            if !analysis.implicit_imports.is_empty() {
+                package_validation.package_details.findings.push(Finding {
+                    rule: "implicit-import".into(),
+                    subject: location_files(&analysis.implicit_imports),
+                    message: format!(
+                        "{} relative imports leave out the extension or import a directory, ESM requires the full file path: {}",
+                        analysis.implicit_imports.len(),
+                        list_locations(&analysis.implicit_imports)
+                    ),
+                });
+            }
+            package_validation.package_details.dynamic_imports = analysis.dynamic_loads;
+            package_validation.package_details.implicit_imports = analysis.implicit_imports;
+        }
+        let (edge_portability, reasons) =
+            portability::classify(&package_validation.package_details, options.scan_sources);
+        package_validation.package_details.edge_portability = edge_portability;
+        package_validation.package_details.edge_portability_reasons = reasons;
+        package_validation.package_details.path = package_dir;
+        package_validation.package_details.manifest_hash = manifest_hash;
         if package_validation.package_details.name == "" {
-            package_validation.package_details.name =
-                entry.path().parent().unwrap().display().to_string();
+            package_validation.package_details.name = path.parent().unwrap().display().to_string();
         }
-        package_validation.package_details.size = entry.metadata().unwrap().size()
+        package_validation.package_details.size = metadata.len
     }
     package_validation
 }
 
 fn main() {
+    let args = match args::parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}", Red.paint(message));
+            process::exit(1);
+        }
+    };
+
     let current_path = match env::current_dir() {
         Ok(path) => path,
         Err(_) => panic!(),
     };
 
-    let _ = walk_dirs(&current_path, &dir_handler);
+    let owner_rules = match &args.owners {
+        Some(owners_path) => match owners::load_owners(owners_path) {
+            Ok(rules) => rules,
+            Err(err) => {
+                eprintln!(
+                    "{}",
+                    Red.paint(format!(
+                        "Unable to load owners file {}: {}",
+                        owners_path.display(),
+                        err
+                    ))
+                );
+                exit_summary::exit(&args, 1, "invalid-owners", None);
+            }
+        },
+        None => Vec::new(),
+    };
+
+    // both files are JSON, a TOML one next to them would otherwise be silently ignored
+    for (toml_file, json_file) in [
+        (config::TOML_CONFIG_FILE, config::DEFAULT_CONFIG_FILE),
+        (acks::TOML_ACKS_FILE, acks::DEFAULT_ACKS_FILE),
+    ] {
+        if current_path.join(toml_file).is_file() {
+            eprintln!(
+                "{}",
+                Yellow.paint(format!(
+                    "Warning: {} is ignored, walker reads JSON from {}",
+                    toml_file, json_file
+                ))
+            );
+        }
+    }
+
+    let config_path = match &args.config {
+        Some(config_path) => Some(config_path.clone()),
+        None => Some(current_path.join(config::DEFAULT_CONFIG_FILE)).filter(|path| path.is_file()),
+    };
+    let config = match &config_path {
+        Some(config_path) => match config::load_config(config_path) {
+            Ok(config) => config,
+            Err(err) => {
+                eprintln!(
+                    "{}",
+                    Red.paint(format!(
+                        "Unable to load config file {}: {}",
+                        config_path.display(),
+                        err
+                    ))
+                );
+                exit_summary::exit(&args, 1, "invalid-config", None);
+            }
+        },
+        None => Config::default(),
+    };
+    let args = Args {
+        report_limits: args.report_limits.or(config.report_limits),
+        // the two trees of a diff live in different places, their packages are paired up
+        // by where they are within them
+        relative_paths: args.relative_paths.or(args.diff.as_ref().map(|_| true)),
+        ..args
+    };
+
+    // patterns given on the command line come after the config file's, so they win
+    let args = Args {
+        config: config_path,
+        config_sha256: config.sha256.clone(),
+        includes: config
+            .include_patterns
+            .iter()
+            .chain(&args.includes)
+            .cloned()
+            .collect(),
+        excludes: config
+            .exclude_patterns
+            .iter()
+            .chain(&args.excludes)
+            .cloned()
+            .collect(),
+        ..args
+    };
+
+    // queries run on saved results, nothing is scanned
+    if let Some(results_path) = &args.query {
+        let mut results = match AnalysisResults::load(results_path) {
+            Ok(results) => results,
+            Err(err) => {
+                eprintln!(
+                    "{}",
+                    Red.paint(format!(
+                        "Unable to load results {}: {}",
+                        results_path.display(),
+                        err
+                    ))
+                );
+                exit_summary::exit(&args, 1, "load-failed", None);
+            }
+        };
+        pipeline::select(&mut results, &args);
+        match query::query_results(&mut results, &args) {
+            true => exit_summary::write_exit_summary(&args, 0, "ok", Some(&results)),
+            false => exit_summary::exit(&args, 1, "invalid-query", Some(&results)),
+        }
+        return;
+    }
+
+    // built again for every scan, they keep track of what they matched
+    let filters = || Filters {
+        includes: args
+            .includes
+            .iter()
+            .map(|pattern| filters::parse_pattern(pattern))
+            .collect(),
+        excludes: args
+            .excludes
+            .iter()
+            .map(|pattern| filters::parse_pattern(pattern))
+            .collect(),
+    };
+
+    if let Some(explain_path) = &args.explain_excludes {
+        let relative_path = explain_path
+            .strip_prefix(&current_path)
+            .unwrap_or(explain_path);
+        let is_dir = current_path.join(relative_path).is_dir();
+        print!(
+            "{}",
+            filters::explain(&filters(), &relative_path.to_string_lossy(), is_dir)
+        );
+        return;
+    }
+
+    let acks_path = match &args.acks {
+        Some(acks_path) => Some(acks_path.clone()),
+        None => Some(current_path.join(acks::DEFAULT_ACKS_FILE)).filter(|path| path.is_file()),
+    };
+    let mut acks = match &acks_path {
+        Some(acks_path) => match acks::load_acks(acks_path) {
+            Ok(acks) => acks,
+            Err(err) => {
+                eprintln!(
+                    "{}",
+                    Red.paint(format!(
+                        "Unable to load acknowledgements {}: {}",
+                        acks_path.display(),
+                        err
+                    ))
+                );
+                exit_summary::exit(&args, 1, "invalid-acks", None);
+            }
+        },
+        None => Vec::new(),
+    };
+
+    // a remote repository is cloned for the scan and removed again once it's analyzed
+    let checkout = match &args.git {
+        Some(remote) => match vcs::checkout(remote, args.git_cache.as_deref()) {
+            Ok(checkout) => Some(checkout),
+            Err(err) => {
+                eprintln!(
+                    "{}",
+                    Red.paint(format!("Unable to clone {}: {}", remote.url, err))
+                );
+                exit_summary::exit(&args, 1, "clone-failed", None);
+            }
+        },
+        None => None,
+    };
+    let scan_root = checkout
+        .as_ref()
+        .map_or_else(|| current_path.clone(), |checkout| checkout.path.clone());
+
+    let options = AnalysisOptions {
+        filesystem: &StdFileSystem,
+        max_manifest_size: args
+            .max_manifest_size
+            .or(config.max_manifest_size)
+            .unwrap_or(manifest::DEFAULT_MAX_MANIFEST_SIZE),
+        scan_sources: args.scan_sources,
+        ts_resolution: args.ts_resolution,
+        manifests: RefCell::new(ManifestCache::default()),
+    };
+    // with --output ndjson, packages are written out as the walk analyzes them
+    let stream = pipeline::ndjson_stream(&args);
+    let stream_package = stream
+        .as_ref()
+        .map(|stream| pipeline::package_streamer(stream, &scan_root, &args));
+    let on_package = stream_package
+        .as_ref()
+        .map(|streamer| streamer as &dyn Fn(&PackageDetails));
+    let scan_tree = |root: &Path, checkpoint| {
+        let (mut scan, walk_result) =
+            pipeline::discover(root, &args, filters(), &options, checkpoint, on_package);
+        pipeline::analyze(
+            &mut scan,
+            root,
+            &owner_rules,
+            &config.layers,
+            &config.tags,
+            &config.rules,
+            &args,
+        );
+        let results = pipeline::aggregate(&mut scan, &walk_result, &args);
+        (results, scan)
+    };
+    let run = |checkpoint| scan_tree(&scan_root, checkpoint);
+
+    if let Some((before, after)) = &args.diff {
+        // a side that isn't a directory is checked out from the repository first. Exiting
+        // skips Drop, so a side that fails is only reported once the collect has dropped
+        // the checkouts made before it
+        let checkouts = [before, after]
+            .into_iter()
+            .map(|side| match current_path.join(side).is_dir() {
+                true => Ok(None),
+                false => vcs::checkout_ref(&current_path, side)
+                    .map(Some)
+                    .map_err(|err| (side, err)),
+            })
+            .collect::<Result<Vec<Option<vcs::Checkout>>, _>>();
+        let checkouts = match checkouts {
+            Ok(checkouts) => checkouts,
+            Err((side, err)) => {
+                eprintln!(
+                    "{}",
+                    Red.paint(format!("Unable to check out {}: {}", side, err))
+                );
+                exit_summary::exit(&args, 1, "checkout-failed", None);
+            }
+        };
+        let trees: Vec<AnalysisResults> = [before, after]
+            .iter()
+            .zip(&checkouts)
+            .map(|(side, checkout)| {
+                let root = match checkout {
+                    Some(checkout) => checkout.path.clone(),
+                    None => current_path.join(side),
+                };
+                let (mut results, _) = scan_tree(&root, None);
+                pipeline::select(&mut results, &args);
+                results
+            })
+            .collect();
+        drop(checkouts);
+
+        let diff = tree_diff::diff(&trees[0], &trees[1], &args.dependency_classes);
+        let labels = (before.as_str(), after.as_str());
+        let formatted = match args.output {
+            OutputFormat::Json => tree_diff::render_json(labels, &diff),
+            _ => tree_diff::render_text(labels, &diff),
+        };
+        pipeline::write(&trees[1], Output::Text(formatted), &scan_root, &args);
+        exit_summary::write_exit_summary(&args, 0, "ok", Some(&trees[1]));
+        return;
+    }
+
+    // results loaded from a file skip the scan entirely
+    let (mut results, scan) = match &args.load_results {
+        Some(results_path) => match AnalysisResults::load(results_path) {
+            Ok(results) => (results, None),
+            Err(err) => {
+                eprintln!(
+                    "{}",
+                    Red.paint(format!(
+                        "Unable to load results {}: {}",
+                        results_path.display(),
+                        err
+                    ))
+                );
+                drop(checkout);
+                exit_summary::exit(&args, 1, "load-failed", None);
+            }
+        },
+        None => {
+            let checkpoint = match &args.checkpoint {
+                Some(checkpoint_path) => {
+                    match checkpoint::load(checkpoint_path, &scan_root, args.redact) {
+                        Ok(checkpoint) => Some(checkpoint),
+                        Err(err) => {
+                            eprintln!(
+                                "{}",
+                                Red.paint(format!(
+                                    "Unable to resume from checkpoint {}: {}",
+                                    checkpoint_path.display(),
+                                    err
+                                ))
+                            );
+                            drop(checkout);
+                            exit_summary::exit(&args, 1, "invalid-checkpoint", None);
+                        }
+                    }
+                }
+                None => None,
+            };
+            let (results, scan) = run(checkpoint);
+            (results, Some(scan))
+        }
+    };
+
+    drop(checkout);
+
+    if args.self_check {
+        if let Err(violations) = results.assert_invariants(&args.dependency_classes) {
+            eprintln!(
+                "{}",
+                Red.paint(format!(
+                    "Error: the analysis is inconsistent, this is a bug in walker:\n  {}",
+                    violations.join("\n  ")
+                ))
+            );
+            exit_summary::exit(&args, 1, "self-check-failed", Some(&results));
+        }
+    }
+
+    let previous = match args.compare_with.as_ref().or(args.baseline.as_ref()) {
+        Some(previous_path) => match AnalysisResults::load(previous_path) {
+            Ok(previous) => Some((previous, previous_path)),
+            Err(err) => {
+                eprintln!(
+                    "{}",
+                    Red.paint(format!(
+                        "Unable to load results {}: {}",
+                        previous_path.display(),
+                        err
+                    ))
+                );
+                exit_summary::exit(&args, 1, "load-failed", None);
+            }
+        },
+        None => None,
+    };
+
+    let report = |results: &mut AnalysisResults, acks: &mut [Ack]| {
+        if let Some((previous, previous_path)) = &previous {
+            pipeline::compare(results, previous, previous_path, &args);
+        }
+        pipeline::acknowledge(results, acks);
+        pipeline::select(results, &args);
+
+        let output = match &args.show {
+            None if args.doctor => Output::Text(doctor::diagnose(results)),
+            Some(target) => match pipeline::show(results, &scan_root, target, &args) {
+                Ok(formatted) => Output::Text(formatted),
+                Err(message) => {
+                    eprintln!("{}", Red.paint(message));
+                    exit_summary::exit(&args, 1, "package-not-found", Some(results));
+                }
+            },
+            None => match args.graph {
+                Some(format) => {
+                    let drawn = match format {
+                        graph_export::GraphFormat::Html => {
+                            graph_export::html_graph(&results.packages).0.len()
+                        }
+                        _ => 0,
+                    };
+                    if drawn > graph_export::MAX_HTML_NODES {
+                        eprintln!(
+                            "{}",
+                            Yellow.paint(format!(
+                                "Warning: the graph has {} packages, the page only draws the first {}, use `walker graph json` or `dot` for all of them",
+                                drawn,
+                                graph_export::MAX_HTML_NODES
+                            ))
+                        );
+                    }
+                    Output::Text(graph_export::render_graph(&results.packages, format))
+                }
+                None => match &stream {
+                    Some(stream) => Output::Streamed(stream),
+                    None => Output::Report,
+                },
+            },
+        };
+        pipeline::write(results, output, &scan_root, &args);
+    };
+    report(&mut results, &mut acks);
+
+    // an ack that no longer matches anything was fixed, or the finding changed
+    let stale_acks: Vec<String> = acks
+        .iter()
+        .filter(|ack| !ack.matched)
+        .map(|ack| format!("{} {}", ack.package, ack.rule))
+        .collect();
+    if !stale_acks.is_empty() && !results.partial() {
+        eprintln!(
+            "{}",
+            Yellow.paint(format!(
+                "Warning: acknowledgements matched nothing: {}",
+                stale_acks.join(", ")
+            ))
+        );
+    }
+
+    let regressions = results
+        .comparison
+        .as_ref()
+        .map_or(0, |comparison| comparison.regressions.len());
+    if args.baseline.is_some() && regressions > 0 {
+        eprintln!(
+            "{}",
+            Red.paint(format!(
+                "Error: {} regressions since the baseline",
+                regressions
+            ))
+        );
+        exit_summary::exit(&args, 1, "regressions", Some(&results));
+    }
+
+    let mut scan = match scan {
+        Some(scan) => scan,
+        None => {
+            exit_summary::write_exit_summary(&args, 0, "ok", Some(&results));
+            return;
+        }
+    };
+
+    // a walk that stopped early may not have reached the paths a pattern was meant for
+    let unmatched_excludes = filters::unmatched_excludes(&scan.filters);
+    let stopped_early = results.timed_out || results.error_limit_reached;
+    if config.warn_unmatched_excludes && !stopped_early && !unmatched_excludes.is_empty() {
+        eprintln!(
+            "{}",
+            Yellow.paint(format!(
+                "Warning: exclude patterns matched nothing: {}",
+                unmatched_excludes.join(", ")
+            ))
+        );
+    }
+
+    if args.metrics {
+        print_metrics(&mut scan.metrics);
+    }
+
+    // every change is reported with a fresh scan, the same stages run again
+    if args.watch {
+        // on a terminal only what changed is printed after the first report, so it stays
+        // readable while the tree is being worked on. Files and other formats still get
+        // the whole report every time
+        let print_deltas = matches!(args.output, OutputFormat::Text)
+            && args.output_file.is_none()
+            && args.show.is_none()
+            && args.graph.is_none()
+            && !args.doctor;
+        let mut snapshot = watcher::snapshot(&scan);
+        let mut last_results = results;
+        loop {
+            let changed: Vec<String> = watcher::wait_for_changes(options.filesystem, &snapshot)
+                .iter()
+                .map(|dir| match dir.strip_prefix(&scan_root) {
+                    Ok(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+                    Ok(relative) => relative.display().to_string(),
+                    Err(_) => dir.display().to_string(),
+                })
+                .collect();
+            eprintln!(
+                "{}",
+                Yellow.paint(format!(
+                    "Changed: {}, analyzing again",
+                    list_locations(&changed)
+                ))
+            );
+            for ack in acks.iter_mut() {
+                ack.matched = false;
+            }
+            let (mut results, scan) = run(None);
+            match print_deltas {
+                true => {
+                    pipeline::acknowledge(&mut results, &mut acks);
+                    pipeline::select(&mut results, &args);
+                    let deltas = watcher::deltas(&last_results, &results);
+                    match deltas.is_empty() {
+                        true => println!("No changes since the last analysis"),
+                        false => println!("Changes since the last analysis:"),
+                    }
+                    for delta in deltas {
+                        println!("  {}", delta);
+                    }
+                    println!("{}", watcher::totals(&last_results, &results));
+                }
+                false => report(&mut results, &mut acks),
+            }
+            last_results = results;
+            snapshot = watcher::snapshot(&scan);
+        }
+    }
+
+    let layer_violations = results.layer_violations().len();
+    if args.fail_on_layer_violations && layer_violations > 0 {
+        eprintln!(
+            "{}",
+            Red.paint(format!(
+                "Error: {} dependencies violate the configured layers",
+                layer_violations
+            ))
+        );
+        exit_summary::exit(&args, 1, "layer-violations", Some(&results));
+    }
+    let rule_errors = rules::rule_errors(&results.packages, &config.rules);
+    if !rule_errors.is_empty() {
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for (_, finding) in &rule_errors {
+            *counts.entry(&finding.rule).or_insert(0) += 1;
+        }
+        let counts: Vec<String> = counts
+            .iter()
+            .map(|(rule, count)| format!("{} ({})", rule, count))
+            .collect();
+        eprintln!(
+            "{}",
+            Red.paint(format!(
+                "Error: {} findings of rules set to error: {}",
+                rule_errors.len(),
+                counts.join(", ")
+            ))
+        );
+        exit_summary::exit(&args, 1, "rule-errors", Some(&results));
+    }
+    exit_summary::write_exit_summary(&args, 0, "ok", Some(&results));
 }
@@ -0,0 +1,236 @@
+use crate::filesystem::FileSystem;
+use serde_json::{Map, Value};
+use std::io::{self, BufReader, Bytes, Read};
+use std::path::Path;
+
+// manifests bigger than this aren't parsed whole, a corrupted one can run to gigabytes
+pub const DEFAULT_MAX_MANIFEST_SIZE: u64 = 16 * 1024 * 1024;
+
+// the top-level fields classification needs, everything else in an oversized manifest
+// is skipped over without being kept
+pub const CLASSIFICATION_FIELDS: [&str; 8] = [
+    "name", "version", "type", "main", "module", "exports", "types", "typings",
+];
+
+// the text of a manifest, with the encoding it was stored in when that isn't plain UTF-8.
+// A JSON document starts with ASCII, so UTF-16 without a byte order mark still shows in
+// the zero bytes of its first character
+pub fn decode(bytes: &[u8]) -> Result<(String, Option<&'static str>), String> {
+    let utf16 = |bytes: &[u8], from_bytes: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|pair| from_bytes([pair[0], pair[1]]))
+            .collect();
+        String::from_utf16(&units).map_err(|_| "invalid UTF-16".to_string())
+    };
+    match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8(rest.to_vec())
+            .map(|contents| (contents, Some("UTF-8 with a byte order mark")))
+            .map_err(|_| "invalid UTF-8".to_string()),
+        [0xFF, 0xFE, rest @ ..] => Ok((utf16(rest, u16::from_le_bytes)?, Some("UTF-16LE"))),
+        [0xFE, 0xFF, rest @ ..] => Ok((utf16(rest, u16::from_be_bytes)?, Some("UTF-16BE"))),
+        [first, 0, ..] if *first != 0 => Ok((utf16(bytes, u16::from_le_bytes)?, Some("UTF-16LE"))),
+        [0, second, ..] if *second != 0 => {
+            Ok((utf16(bytes, u16::from_be_bytes)?, Some("UTF-16BE")))
+        }
+        _ => String::from_utf8(bytes.to_vec())
+            .map(|contents| (contents, None))
+            .map_err(|_| "neither UTF-8 nor UTF-16".to_string()),
+    }
+}
+
+pub struct PartialManifest {
+    pub fields: Value,
+    pub bytes_read: u64,
+    // where reading stopped when the file is cut short or isn't JSON past some point,
+    // the fields found before it are still kept
+    pub error: Option<String>,
+}
+
+struct Scanner<R: Read> {
+    bytes: Bytes<BufReader<R>>,
+    peeked: Option<u8>,
+    position: u64,
+}
+
+impl<R: Read> Scanner<R> {
+    fn next(&mut self) -> io::Result<u8> {
+        if let Some(byte) = self.peeked.take() {
+            return Ok(byte);
+        }
+        match self.bytes.next() {
+            Some(byte) => {
+                self.position += 1;
+                byte
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "unexpected end of file",
+            )),
+        }
+    }
+
+    fn next_token(&mut self) -> io::Result<u8> {
+        loop {
+            let byte = self.next()?;
+            if !byte.is_ascii_whitespace() {
+                return Ok(byte);
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: u8) -> io::Result<()> {
+        match self.next_token()? {
+            byte if byte == expected => Ok(()),
+            byte => Err(self.unexpected(byte)),
+        }
+    }
+
+    fn unexpected(&self, byte: u8) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "unexpected '{}' at byte {}",
+                byte.escape_ascii(),
+                self.position
+            ),
+        )
+    }
+
+    // the rest of a string whose opening quote was already read
+    fn string(&mut self, capture: &mut Capture) -> io::Result<()> {
+        loop {
+            let byte = self.next()?;
+            capture.push(byte);
+            match byte {
+                b'"' => return Ok(()),
+                b'\\' => capture.push(self.next()?),
+                _ => {}
+            }
+        }
+    }
+
+    // one complete value starting at `first`, copied into `capture` as it goes
+    fn value(&mut self, first: u8, capture: &mut Capture) -> io::Result<()> {
+        capture.push(first);
+        match first {
+            b'"' => self.string(capture),
+            b'{' | b'[' => {
+                let mut depth = 1;
+                while depth > 0 {
+                    let byte = self.next()?;
+                    capture.push(byte);
+                    match byte {
+                        b'"' => self.string(capture)?,
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => depth -= 1,
+                        _ => {}
+                    }
+                }
+                Ok(())
+            }
+            // numbers, true, false and null run up to whatever follows them
+            _ => loop {
+                let byte = self.next()?;
+                match byte {
+                    b',' | b'}' | b']' => {
+                        self.peeked = Some(byte);
+                        return Ok(());
+                    }
+                    byte if byte.is_ascii_whitespace() => return Ok(()),
+                    byte => capture.push(byte),
+                }
+            },
+        }
+    }
+}
+
+// the raw bytes of a value being kept, dropped once they go over the limit
+struct Capture {
+    bytes: Option<Vec<u8>>,
+    limit: usize,
+}
+
+impl Capture {
+    fn push(&mut self, byte: u8) {
+        if let Some(bytes) = &mut self.bytes {
+            match bytes.len() < self.limit {
+                true => bytes.push(byte),
+                false => self.bytes = None,
+            }
+        }
+    }
+}
+
+fn read_fields<R: Read>(
+    scanner: &mut Scanner<R>,
+    fields: &[&str],
+    limit: usize,
+    found: &mut Map<String, Value>,
+) -> io::Result<()> {
+    // a UTF-8 byte order mark is passed over, as it is for smaller manifests
+    match scanner.next_token()? {
+        0xEF if scanner.next()? == 0xBB && scanner.next()? == 0xBF => scanner.expect(b'{')?,
+        b'{' => {}
+        byte => return Err(scanner.unexpected(byte)),
+    }
+    let mut first = true;
+    loop {
+        match (scanner.next_token()?, first) {
+            (b'}', _) => return Ok(()),
+            (b'"', true) => {}
+            (b',', false) => scanner.expect(b'"')?,
+            (byte, _) => return Err(scanner.unexpected(byte)),
+        }
+        first = false;
+
+        let mut key = Capture {
+            bytes: Some(vec![b'"']),
+            limit: 1024,
+        };
+        scanner.string(&mut key)?;
+        let key = key
+            .bytes
+            .and_then(|key| match serde_json::from_slice(&key) {
+                Ok(Value::String(key)) => Some(key),
+                _ => None,
+            });
+        scanner.expect(b':')?;
+
+        let wanted = key.filter(|key| fields.contains(&key.as_str()));
+        let mut value = Capture {
+            bytes: wanted.as_ref().map(|_| Vec::new()),
+            limit,
+        };
+        let start = scanner.next_token()?;
+        scanner.value(start, &mut value)?;
+        if let (Some(key), Some(value)) = (wanted, value.bytes) {
+            if let Ok(value) = serde_json::from_slice(&value) {
+                found.insert(key, value);
+            }
+        }
+    }
+}
+
+// reads `fields` from the top level of a manifest too big to parse whole. The file is
+// streamed through once and only the values of those fields are kept, each up to
+// `limit` bytes
+pub fn read_partial(
+    filesystem: &dyn FileSystem,
+    path: &Path,
+    fields: &[&str],
+    limit: u64,
+) -> io::Result<PartialManifest> {
+    let mut scanner = Scanner {
+        bytes: BufReader::new(filesystem.open(path)?).bytes(),
+        peeked: None,
+        position: 0,
+    };
+    let mut found = Map::new();
+    let result = read_fields(&mut scanner, fields, limit as usize, &mut found);
+    Ok(PartialManifest {
+        fields: Value::Object(found),
+        bytes_read: scanner.position,
+        error: result.err().map(|err| err.to_string()),
+    })
+}
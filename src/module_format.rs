@@ -0,0 +1,154 @@
+use crate::filesystem::FileSystem;
+use crate::sources::{read_source, tokenize, Token};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+// an entry file whose syntax isn't the module system it's loaded as
+#[derive(Clone)]
+pub struct FormatMismatch {
+    // where the package points at it, e.g. `main` or `exports["."].import`
+    pub entry: String,
+    pub file: String,
+    pub declared: Cow<'static, str>,
+    pub syntax: Cow<'static, str>,
+}
+
+fn is_ident(token: Option<&Token>, name: &str) -> bool {
+    matches!(token, Some(Token::Ident(ident)) if ident == name)
+}
+
+// "esm" for import and export statements or import.meta, "cjs" for require() calls and
+// assignments to module.exports or exports. None when the file has neither, and for UMD
+// wrappers, which check `typeof module` before touching it and load either way
+fn module_syntax(tokens: &[Token]) -> Option<&'static str> {
+    let mut cjs = false;
+    for (index, token) in tokens.iter().enumerate() {
+        let previous = index.checked_sub(1).and_then(|index| tokens.get(index));
+        let next = tokens.get(index + 1);
+        // a property that happens to share the name
+        if previous == Some(&Token::Punct('.')) {
+            continue;
+        }
+        let name = match token {
+            Token::Ident(name) => name.as_str(),
+            _ => continue,
+        };
+        match name {
+            "import" => match next {
+                Some(Token::Str(_)) | Some(Token::Ident(_)) => return Some("esm"),
+                Some(Token::Punct('{')) | Some(Token::Punct('*')) => return Some("esm"),
+                Some(Token::Punct('.')) if is_ident(tokens.get(index + 2), "meta") => {
+                    return Some("esm")
+                }
+                _ => {}
+            },
+            "export" => match next {
+                Some(Token::Ident(_)) | Some(Token::Punct('{')) | Some(Token::Punct('*')) => {
+                    return Some("esm")
+                }
+                _ => {}
+            },
+            "module" | "exports" | "define" if is_ident(previous, "typeof") => return None,
+            "module" => {
+                cjs |=
+                    next == Some(&Token::Punct('.')) && is_ident(tokens.get(index + 2), "exports")
+            }
+            "exports" => cjs |= matches!(next, Some(Token::Punct('.')) | Some(Token::Punct('['))),
+            "require" => cjs |= next == Some(&Token::Punct('(')),
+            _ => {}
+        }
+    }
+    cjs.then_some("cjs")
+}
+
+// what node loads a file as: by extension, and for .js by the package's "type". The
+// "module" field and condition are only read by bundlers, which expect ESM there. None
+// for files that aren't JavaScript
+fn declared_format(file: &str, esm_type: bool, bundler_module: bool) -> Option<&'static str> {
+    let extension = Path::new(file).extension().and_then(|e| e.to_str());
+    match extension {
+        Some("mjs") => Some("esm"),
+        Some("cjs") => Some("cjs"),
+        Some("js") | None if bundler_module || esm_type => Some("esm"),
+        Some("js") | None => Some("cjs"),
+        Some(_) => None,
+    }
+}
+
+// opens the files "main", "module" and "exports" point at and checks each uses the module
+// system it's declared as, adding what it reads to `bytes_read`. Missing files are left to
+// the broken entrypoint check, and so are wildcard subpaths
+pub fn check_entry_formats(
+    filesystem: &dyn FileSystem,
+    v: &Value,
+    package_dir: &Path,
+    entrypoints: &BTreeMap<String, BTreeMap<String, String>>,
+    bytes_read: &mut u64,
+) -> Vec<FormatMismatch> {
+    let esm_type = v["type"].as_str() == Some("module");
+    let mut entries: Vec<(String, &str, bool)> = Vec::new();
+    if let Some(main) = v["main"].as_str() {
+        entries.push(("main".to_string(), main, false));
+    }
+    if let Some(module) = v["module"].as_str() {
+        entries.push(("module".to_string(), module, true));
+    }
+    for (subpath, conditions) in entrypoints {
+        for (condition, target) in conditions {
+            if target.contains('*') || condition.split('+').any(|c| c == "types") {
+                continue;
+            }
+            let bundler_module = condition.split('+').any(|c| c == "module");
+            entries.push((
+                format!("exports[\"{}\"].{}", subpath, condition),
+                target,
+                bundler_module,
+            ));
+        }
+    }
+
+    let mut mismatches = Vec::new();
+    let mut checked: Vec<(String, &'static str)> = Vec::new();
+    for (entry, target, bundler_module) in entries {
+        let file = target.trim_start_matches("./");
+        let declared = match declared_format(file, esm_type, bundler_module) {
+            Some(declared) => declared,
+            None => continue,
+        };
+        if checked.iter().any(|(f, d)| f == file && *d == declared) {
+            continue;
+        }
+        checked.push((file.to_string(), declared));
+        // node finds a "main" without its extension
+        let path = package_dir.join(file);
+        let path = match filesystem.is_file(&path) {
+            true => path,
+            false => package_dir.join(format!("{}.js", file)),
+        };
+        let source = match read_source(filesystem, &path, bytes_read) {
+            Some(source) => source,
+            None => continue,
+        };
+        let (tokens, _) = tokenize(&source);
+        if let Some(syntax) = module_syntax(&tokens) {
+            if syntax != declared {
+                mismatches.push(FormatMismatch {
+                    entry,
+                    file: file.to_string(),
+                    declared: declared.into(),
+                    syntax: syntax.into(),
+                });
+            }
+        }
+    }
+    mismatches
+}
+
+pub fn format_label(format: &str) -> &'static str {
+    match format {
+        "esm" => "ESM",
+        _ => "CommonJS",
+    }
+}
@@ -0,0 +1,99 @@
+use super::writer::write_atomic;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+
+// who an encrypted report is for. age recipients are public keys, "age1..." or an ssh
+// key; anything else is a GPG key id, fingerprint or email in the local keyring
+#[derive(Clone, PartialEq)]
+pub enum Recipient {
+    Age(String),
+    Gpg(String),
+}
+
+impl Recipient {
+    pub fn parse(value: &str) -> Recipient {
+        match value.starts_with("age1") || value.starts_with("ssh-") {
+            true => Recipient::Age(value.to_string()),
+            false => Recipient::Gpg(value.to_string()),
+        }
+    }
+
+    fn is_age(&self) -> bool {
+        matches!(self, Recipient::Age(_))
+    }
+}
+
+// one tool does the whole file, so all recipients have to be of the same kind
+pub fn check_recipients(recipients: &[Recipient]) -> Result<(), String> {
+    match recipients
+        .iter()
+        .map(Recipient::is_age)
+        .collect::<Vec<bool>>()[..]
+    {
+        [first, ref rest @ ..] if rest.iter().any(|&is_age| is_age != first) => {
+            Err("--encrypt-to can't mix age and GPG recipients".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+// ASCII armored, so an encrypted report is still text and can go anywhere a plain one
+// can, stdout included
+pub fn encrypt(plaintext: &str, recipients: &[Recipient]) -> io::Result<String> {
+    let mut command = match recipients.first() {
+        Some(Recipient::Age(_)) => {
+            let mut command = Command::new("age");
+            command.arg("--armor");
+            command
+        }
+        // the recipient was named on the command line, that's all the trust asked for
+        Some(Recipient::Gpg(_)) => {
+            let mut command = Command::new("gpg");
+            command.args(["--batch", "--armor", "--trust-model", "always", "--encrypt"]);
+            command
+        }
+        None => return Ok(plaintext.to_string()),
+    };
+    for recipient in recipients {
+        match recipient {
+            Recipient::Age(key) | Recipient::Gpg(key) => command.arg("--recipient").arg(key),
+        };
+    }
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| io::Error::new(err.kind(), format!("can't run {}: {}", program, err)))?;
+
+    // written from another thread, a large report would otherwise fill the pipe back
+    // from the tool before all of it was handed over
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let (written, output) = thread::scope(|scope| {
+        let writer = scope.spawn(move || stdin.write_all(plaintext.as_bytes()));
+        let output = child.wait_with_output();
+        (
+            writer.join().expect("the writer thread doesn't panic"),
+            output,
+        )
+    });
+    let output = output?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(io::Error::other(format!("{} failed: {}", program, message)));
+    }
+    written?;
+    String::from_utf8(output.stdout)
+        .map_err(|_| io::Error::other(format!("{} didn't produce armored output", program)))
+}
+
+// `write_atomic`, encrypted first when there are recipients
+pub fn write_encrypted(path: &Path, contents: &str, recipients: &[Recipient]) -> io::Result<()> {
+    match recipients.is_empty() {
+        true => write_atomic(path, contents),
+        false => write_atomic(path, &encrypt(contents, recipients)?),
+    }
+}
@@ -0,0 +1,2644 @@
+use super::limits::{capped, omitted, ReportLimits};
+use super::width::{display_width, pad, truncate};
+use super::writer::date;
+use super::{buffered, Formatter};
+use crate::args::{Breakdown, DependencyClass, GroupBy};
+use crate::dependency_usage;
+use crate::error_context::errors_to_json;
+use crate::field_census::{self, FieldCensus};
+use crate::fingerprint::{error_fingerprint, finding_fingerprint};
+use crate::identity::{ChangeKind, PackageChange};
+use crate::portability::{self, EdgePortability};
+use crate::protocols;
+use crate::sampling::Estimate;
+use crate::syntax;
+use crate::typescript;
+use crate::vcs::PackageHistory;
+use crate::{
+    dependency_count, dependency_counts, dependency_statistics, group_by_owner, group_by_tag,
+    scope_statistics, supports_cjs, supports_esm, tag_statistics, workspace_statistics,
+    AnalysisResults, Finding, PackageDetails, ScopeStatistics,
+};
+use ansi_term::Colour::{Green, Red, Yellow};
+use serde_json::{json, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+pub struct TextFormatter {
+    pub group_by: Option<GroupBy>,
+    // package names longer than this many columns are truncated
+    pub max_name_len: Option<usize>,
+    pub list_skipped: bool,
+    pub dependency_usage: bool,
+    pub field_census: bool,
+    pub dependency_classes: Vec<DependencyClass>,
+    pub limits: ReportLimits,
+}
+
+fn format_counts<K: std::fmt::Display, V: std::fmt::Display>(
+    counts: impl IntoIterator<Item = (K, V)>,
+) -> String {
+    counts
+        .into_iter()
+        .map(|(key, count)| format!("{}: {}", key, count))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn es_version_counts(packages: &[PackageDetails]) -> Vec<(String, usize)> {
+    syntax::version_counts(packages.iter().filter_map(|p| p.min_es_version))
+}
+
+// packages importing each node builtin, most used first
+fn builtin_counts(packages: &[PackageDetails]) -> Vec<(&str, usize)> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for builtin in packages.iter().flat_map(|p| &p.node_builtins) {
+        *counts.entry(builtin).or_insert(0) += 1;
+    }
+    let mut counts: Vec<(&str, usize)> = counts.into_iter().collect();
+    counts.sort_by(|(_, a), (_, b)| b.cmp(a));
+    counts
+}
+
+// packages relying on extensionless or directory imports, and how many import sites
+fn implicit_import_counts(packages: &[PackageDetails]) -> (usize, usize) {
+    let affected = packages
+        .iter()
+        .filter(|p| !p.implicit_imports.is_empty())
+        .count();
+    let sites = packages.iter().map(|p| p.implicit_imports.len()).sum();
+    (affected, sites)
+}
+
+// packages with each kind of informational finding
+fn note_counts(packages: &[PackageDetails]) -> BTreeMap<&str, usize> {
+    let mut counts = BTreeMap::new();
+    for note in packages.iter().flat_map(|p| &p.notes) {
+        *counts.entry(note.rule.as_ref()).or_insert(0) += 1;
+    }
+    counts
+}
+
+// CommonJS-only packages nobody committed to within the churn window, longest
+// untouched first: the ones to look at first for a migration
+fn stale_cjs_only(packages: &[PackageDetails]) -> Vec<(&PackageDetails, &PackageHistory)> {
+    let mut stale: Vec<(&PackageDetails, &PackageHistory)> = packages
+        .iter()
+        .filter(|p| !supports_esm(&p.module_support))
+        .filter_map(|p| Some((p, p.history.as_ref()?)))
+        .filter(|(_, history)| history.commits == 0)
+        .collect();
+    stale.sort_by_key(|(_, history)| history.last_changed);
+    stale
+}
+
+// dependencies listed in the text report, JSON has all of them
+const TOP_DEPENDENCIES: usize = 20;
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+fn display_name(name: &str, max_name_len: Option<usize>) -> String {
+    match max_name_len {
+        Some(max_name_len) => truncate(name, max_name_len),
+        None => name.to_string(),
+    }
+}
+
+fn format_package(
+    out: &mut dyn Write,
+    package_details: &PackageDetails,
+    max_name_len: Option<usize>,
+) -> io::Result<()> {
+    let PackageDetails {
+        module_support,
+        name,
+        version,
+        size,
+        tags,
+        partial,
+        findings,
+        notes,
+        errors,
+        asset_exports,
+        min_es_version,
+        es_features,
+        node_builtins,
+        dynamic_imports,
+        history,
+        edge_portability,
+        edge_portability_reasons,
+        typescript,
+        aliases,
+        ..
+    } = package_details;
+
+    let esm = supports_esm(module_support);
+
+    let cjs = supports_cjs(module_support);
+
+    let print_esm = match esm {
+        true => Green.paint("true"),
+        false => Red.paint("false"),
+    };
+
+    let print_cjs = match cjs {
+        true => Green.paint("true"),
+        false => Red.paint("false"),
+    };
+
+    let print_tags = match tags.is_empty() {
+        true => "".to_string(),
+        false => format!(" [{}]", tags.join(", ")),
+    };
+
+    writeln!(
+        out,
+        "Package: {}@{}{}, size: {}{} - ESM Support: {}, CommonJS: {}",
+        Green.paint(display_name(name, max_name_len)),
+        Green.paint(version),
+        print_tags,
+        size,
+        if *partial { " (partial)" } else { "" },
+        print_esm,
+        print_cjs
+    )?;
+
+    for finding in findings {
+        writeln!(
+            out,
+            "  {} {}",
+            Yellow.paint(format!("Warning [{}]:", finding.rule)),
+            finding.message
+        )?;
+    }
+
+    for note in notes {
+        writeln!(out, "  Info [{}]: {}", note.rule, note.message)?;
+    }
+
+    if !asset_exports.is_empty() {
+        writeln!(out, "  Asset entrypoints: {}", format_counts(asset_exports))?;
+    }
+
+    if let Some(version) = min_es_version {
+        match es_features.is_empty() {
+            true => writeln!(out, "  Syntax: {}", syntax::es_label(*version))?,
+            false => writeln!(
+                out,
+                "  Syntax: {} ({})",
+                syntax::es_label(*version),
+                es_features.join(", ")
+            )?,
+        }
+    }
+
+    if !node_builtins.is_empty() {
+        writeln!(
+            out,
+            "  Node builtins: {}{}",
+            node_builtins.join(", "),
+            match dynamic_imports.is_empty() {
+                true => "",
+                false => " (approximate, some imports are computed)",
+            }
+        )?;
+    }
+
+    if let Some(history) = history {
+        writeln!(
+            out,
+            "  History: created {}, last changed {}, {} commits since {}",
+            date(history.created),
+            date(history.last_changed),
+            history.commits,
+            date(history.since)
+        )?;
+    }
+
+    if let Some((resolution, compliant)) = typescript {
+        writeln!(
+            out,
+            "  TypeScript {}: {}",
+            resolution.label(),
+            match compliant {
+                true => Green.paint("types resolve"),
+                false => Red.paint("types don't resolve cleanly"),
+            }
+        )?;
+    }
+
+    if *edge_portability != EdgePortability::Portable {
+        writeln!(
+            out,
+            "  Edge portability: {} - {}",
+            edge_portability.label(),
+            edge_portability_reasons.join("; ")
+        )?;
+    }
+
+    if !aliases.is_empty() {
+        let aliases: Vec<String> = aliases.iter().map(|a| a.display().to_string()).collect();
+        writeln!(out, "  Also found at: {}", aliases.join(", "))?;
+    }
+
+    for error in errors {
+        writeln!(out, "  {} {}", Red.paint("Error:"), error)?;
+    }
+
+    //if esm {
+    //    let print_esm_type = match module_support.esm_type {
+    //        true => Green.paint("true"),
+    //        false => Red.paint("false"),
+    //    };
+
+    //    let print_esm_exports = match module_support.esm_exports {
+    //        true => Green.paint("true"),
+    //        false => Red.paint("false"),
+    //    };
+
+    //    let print_esm_partial = match module_support.esm_partial {
+    //        true => Green.paint("true"),
+    //        false => Red.paint("false"),
+    //    };
+
+    //    let print_esm_main = match module_support.esm_main_mjs {
+    //        true => Green.paint("true"),
+    //        false => Red.paint("false"),
+    //    };
+
+    //    println!(
+    //         "'type' set to 'module': {}\n'exports' field defined with 'import' prop: {}\n'module' field set: {}\n'main' field references an '.mjs' file: {}",
+    //         print_esm_type, print_esm_exports, print_esm_partial, print_esm_main
+    //     );
+    //}
+
+    Ok(())
+}
+
+const CHANGE_KINDS: [ChangeKind; 4] = [
+    ChangeKind::Added,
+    ChangeKind::Removed,
+    ChangeKind::Moved,
+    ChangeKind::Updated,
+];
+
+pub fn describe_change(change: &PackageChange, max_name_len: Option<usize>) -> String {
+    let name = display_name(&change.name, max_name_len);
+    let versioned = |version: &str| match version.is_empty() {
+        true => name.clone(),
+        false => format!("{}@{}", name, version),
+    };
+    match (change.kind, &change.before, &change.after) {
+        (ChangeKind::Moved, Some((_, from)), Some((version, to))) => format!(
+            "{} {} from {} to {}",
+            Yellow.paint("moved"),
+            versioned(version),
+            from.display(),
+            to.display()
+        ),
+        (ChangeKind::Updated, Some((from, _)), Some((to, path))) => format!(
+            "{} {} from {} to {} at {}",
+            Yellow.paint("updated"),
+            name,
+            from,
+            to,
+            path.display()
+        ),
+        (ChangeKind::Removed, Some((version, path)), _) => format!(
+            "{} {} at {}",
+            Red.paint("removed"),
+            versioned(version),
+            path.display()
+        ),
+        (_, _, Some((version, path))) => format!(
+            "{} {} at {}",
+            Green.paint("added"),
+            versioned(version),
+            path.display()
+        ),
+        _ => name.clone(),
+    }
+}
+
+// the marker for the rest of a list cut short by --report-max-items
+fn write_omitted(out: &mut dyn Write, count: usize, what: &str) -> io::Result<()> {
+    match count {
+        0 => Ok(()),
+        count => writeln!(out, "  {}", omitted(count, what, "--report-max-items")),
+    }
+}
+
+// a summary split in two, like published and private packages, only worth showing once
+// there's something on both sides
+fn write_split_summary(
+    out: &mut dyn Write,
+    title: &str,
+    splits: Vec<(&str, ScopeStatistics)>,
+) -> io::Result<()> {
+    if splits.len() < 2 {
+        return Ok(());
+    }
+    writeln!(out, "{}", title)?;
+    let label_width = splits
+        .iter()
+        .map(|(label, _)| label.len() + 1)
+        .max()
+        .unwrap_or(0);
+    for (scope, statistics) in splits {
+        let dependencies = match &statistics.dependencies {
+            Some(dependencies) => format!(
+                "{} total, median {}, p90 {}",
+                dependencies.total, dependencies.median, dependencies.p90
+            ),
+            None => "none".to_string(),
+        };
+        writeln!(
+            out,
+            "  {} {} packages, ESM: {:.1}%, CommonJS only: {}, size: {} ({} average), dependencies: {}, findings: {}, errors: {}",
+            Green.paint(pad(&format!("{}:", scope), label_width)),
+            statistics.packages,
+            statistics.esm_percentage,
+            statistics.cjs_only,
+            format_size(statistics.total_size),
+            format_size(statistics.average_size),
+            dependencies,
+            statistics.findings,
+            statistics.errors
+        )?;
+    }
+    Ok(())
+}
+
+impl Formatter for TextFormatter {
+    fn format_to(&self, results: &AnalysisResults, out: &mut dyn Write) -> io::Result<()> {
+        let groups = match self.group_by {
+            Some(GroupBy::Tag) => Some(group_by_tag(&results.packages)),
+            Some(GroupBy::Owner) => Some(group_by_owner(&results.packages)),
+            None => None,
+        };
+
+        match groups {
+            Some(groups) => {
+                // the package cap counts across groups
+                let mut remaining = self.limits.packages.unwrap_or(usize::MAX);
+                for (group, packages) in groups {
+                    if remaining == 0 {
+                        break;
+                    }
+                    writeln!(out, "{}:", Green.paint(group))?;
+                    for package_details in packages.into_iter().take(remaining) {
+                        format_package(out, package_details, self.max_name_len)?;
+                        remaining -= 1;
+                    }
+                }
+            }
+            None => {
+                let (listed, _) = capped(&results.packages, self.limits.packages);
+                for package_details in listed {
+                    format_package(out, package_details, self.max_name_len)?;
+                }
+            }
+        }
+        let (_, omitted_packages) = capped(&results.packages, self.limits.packages);
+        if omitted_packages > 0 {
+            writeln!(
+                out,
+                "{}",
+                omitted(omitted_packages, "packages", "--report-max-packages")
+            )?;
+        }
+
+        if results.timed_out {
+            writeln!(
+                out,
+                "{}",
+                Red.paint("Scan aborted after timeout, results are partial")
+            )?;
+        }
+
+        if results.error_limit_reached {
+            writeln!(
+                out,
+                "{}",
+                Red.paint("Scan aborted after reaching --max-errors, results are partial")
+            )?;
+        }
+
+        let (errors, omitted_errors) = capped(&results.errors, self.limits.errors);
+        for error in errors {
+            writeln!(out, "{} {}", Red.paint("Error:"), error)?;
+        }
+        if omitted_errors > 0 {
+            writeln!(
+                out,
+                "{}",
+                omitted(omitted_errors, "errors", "--report-max-errors")
+            )?;
+        }
+
+        let walk = &results.walk;
+        let skipped = match walk.skipped.is_empty() {
+            true => "".to_string(),
+            false => format!(
+                ", skipped {} directories ({})",
+                walk.skipped.len(),
+                format_counts(walk.skipped_counts())
+            ),
+        };
+        writeln!(
+            out,
+            "Scanned {} directories and {} files, read {}{}",
+            walk.directories_scanned,
+            walk.files_processed,
+            format_size(walk.bytes_read),
+            skipped
+        )?;
+        if self.list_skipped {
+            for (path, reason) in &walk.skipped {
+                writeln!(out, "  Skipped {} ({})", path.display(), reason.label())?;
+            }
+        }
+        // only worth a breakdown once the walk crossed a mount point
+        if walk.filesystems.len() > 1 {
+            writeln!(out, "Filesystems:")?;
+            for (device, filesystem) in &walk.filesystems {
+                writeln!(
+                    out,
+                    "  device {}: {} directories, {:?}",
+                    device, filesystem.directories, filesystem.time
+                )?;
+            }
+        }
+
+        let (findings, suppressed_findings) = results.finding_counts();
+        let acknowledged_findings = results.acknowledged_count();
+        if findings > 0 || suppressed_findings > 0 || acknowledged_findings > 0 {
+            writeln!(
+                out,
+                "Findings: {}, suppressed: {}, acknowledged: {}",
+                findings, suppressed_findings, acknowledged_findings
+            )?;
+        }
+        if acknowledged_findings > 0 {
+            writeln!(out, "Acknowledged findings:")?;
+            let acknowledged: Vec<(&PackageDetails, &Finding)> = results
+                .packages
+                .iter()
+                .flat_map(|p| {
+                    p.acknowledged_findings
+                        .iter()
+                        .map(move |finding| (p, finding))
+                })
+                .collect();
+            let (acknowledged, omitted_findings) = capped(&acknowledged, self.limits.list_items);
+            for (package_details, finding) in acknowledged {
+                writeln!(
+                    out,
+                    "  {} [{}] {} ({})",
+                    Green.paint(display_name(&package_details.name, self.max_name_len)),
+                    finding.rule,
+                    finding.message,
+                    finding_fingerprint(&package_details.name, finding)
+                )?;
+            }
+            write_omitted(out, omitted_findings, "acknowledged findings")?;
+        }
+
+        let stale = stale_cjs_only(&results.packages);
+        if let Some((_, history)) = stale.first() {
+            writeln!(
+                out,
+                "Stale CommonJS-only packages, unchanged since {}: {}",
+                date(history.since),
+                stale.len()
+            )?;
+            let (listed, omitted_stale) = capped(&stale, self.limits.list_items);
+            for (package_details, history) in listed {
+                writeln!(
+                    out,
+                    "  {} last changed {}, created {}",
+                    Yellow.paint(display_name(&package_details.name, self.max_name_len)),
+                    date(history.last_changed),
+                    date(history.created)
+                )?;
+            }
+            write_omitted(out, omitted_stale, "stale packages")?;
+        }
+
+        let layer_violations = results.layer_violations();
+        if !layer_violations.is_empty() {
+            writeln!(out, "Layer violations: {}", layer_violations.len())?;
+            let (listed, omitted_violations) = capped(&layer_violations, self.limits.list_items);
+            for (package_details, finding) in listed {
+                writeln!(
+                    out,
+                    "  {} {}",
+                    Red.paint(display_name(&package_details.name, self.max_name_len)),
+                    finding.message
+                )?;
+            }
+            write_omitted(out, omitted_violations, "layer violations")?;
+        }
+
+        if let Some(comparison) = &results.comparison {
+            let counts: Vec<String> = CHANGE_KINDS
+                .iter()
+                .map(|&kind| format!("{} {}", comparison.count(kind), kind.label()))
+                .collect();
+            writeln!(
+                out,
+                "Changes since {}: {}",
+                comparison.compared_with.display(),
+                counts.join(", ")
+            )?;
+            let (listed, omitted_changes) = capped(&comparison.changes, self.limits.list_items);
+            for change in listed {
+                writeln!(out, "  {}", describe_change(change, self.max_name_len))?;
+            }
+            write_omitted(out, omitted_changes, "changes")?;
+            if !comparison.regressions.is_empty() {
+                writeln!(out, "Regressions: {}", comparison.regressions.len())?;
+                let (listed, omitted_regressions) =
+                    capped(&comparison.regressions, self.limits.list_items);
+                for regression in listed {
+                    writeln!(
+                        out,
+                        "  {} {} at {}: {}",
+                        Red.paint(regression.kind),
+                        display_name(&regression.name, self.max_name_len),
+                        regression.path.display(),
+                        regression.detail
+                    )?;
+                }
+                write_omitted(out, omitted_regressions, "regressions")?;
+            }
+        }
+
+        let (asset_bearing, asset_counts) = results.asset_counts();
+        if asset_bearing > 0 {
+            writeln!(
+                out,
+                "Asset-bearing packages: {} ({})",
+                asset_bearing,
+                format_counts(asset_counts)
+            )?;
+        }
+
+        if !results.packages.is_empty() {
+            writeln!(
+                out,
+                "Platforms: {}",
+                format_counts(results.platform_counts())
+            )?;
+        }
+
+        let native_addons: Vec<&PackageDetails> = results
+            .packages
+            .iter()
+            .filter(|p| !p.native_addon.is_empty())
+            .collect();
+        if !native_addons.is_empty() {
+            writeln!(out, "Native addons:")?;
+            for package_details in native_addons {
+                writeln!(
+                    out,
+                    "  {}@{} - {}",
+                    Yellow.paint(display_name(&package_details.name, self.max_name_len)),
+                    package_details.version,
+                    package_details.native_addon.join(", ")
+                )?;
+            }
+        }
+
+        let esm_with_cjs_only_dependencies = results
+            .packages
+            .iter()
+            .filter(|p| !p.cjs_only_dependencies.is_empty())
+            .count();
+        if let Some(statistics) = dependency_statistics(&results.packages, &self.dependency_classes)
+        {
+            writeln!(
+                out,
+                "Dependencies ({}): {} total, median {}, p90 {} per package",
+                class_labels(&self.dependency_classes).join(", "),
+                statistics.total,
+                statistics.median,
+                statistics.p90
+            )?;
+        }
+        if self.dependency_usage {
+            let usage =
+                dependency_usage::external_usage(&results.packages, &self.dependency_classes);
+            if !usage.is_empty() {
+                writeln!(out, "Most used external dependencies:")?;
+            }
+            for dependency in usage.iter().take(TOP_DEPENDENCIES) {
+                writeln!(
+                    out,
+                    "  {}: {} declarations, {} specs",
+                    dependency.name,
+                    dependency.declarations,
+                    dependency.specs.len()
+                )?;
+                for (spec, packages) in dependency.odd_specs() {
+                    let (listed, omitted_packages) = capped(packages, self.limits.list_items);
+                    let more = match omitted_packages {
+                        0 => String::new(),
+                        count => format!(" and {} more", count),
+                    };
+                    writeln!(
+                        out,
+                        "    {} {} in {}{}",
+                        Yellow.paint("odd version"),
+                        spec,
+                        listed.join(", "),
+                        more
+                    )?;
+                }
+            }
+        }
+        if self.field_census {
+            let census = field_census::field_census(&results.packages);
+            writeln!(out, "package.json fields ({} packages):", census.packages)?;
+            let (listed, omitted_fields) = capped(&census.fields, self.limits.list_items);
+            let width = listed
+                .iter()
+                .map(|usage| display_width(usage.field) + 1)
+                .max()
+                .unwrap_or(0);
+            for usage in listed {
+                writeln!(
+                    out,
+                    "  {} {} packages ({:.1}%)",
+                    Green.paint(pad(&format!("{}:", usage.field), width)),
+                    usage.packages,
+                    usage.percentage
+                )?;
+            }
+            write_omitted(out, omitted_fields, "fields")?;
+        }
+        let es_version_counts = es_version_counts(&results.packages);
+        if !es_version_counts.is_empty() {
+            writeln!(
+                out,
+                "Minimum ES versions: {}",
+                format_counts(es_version_counts)
+            )?;
+        }
+        for (resolution, (compliant, non_compliant)) in
+            typescript::compliance_counts(&results.packages)
+        {
+            writeln!(
+                out,
+                "TypeScript {} resolution: {} packages with types resolve cleanly, {} don't",
+                resolution, compliant, non_compliant
+            )?;
+        }
+        let portability_counts = portability::portability_counts(&results.packages);
+        if !portability_counts.is_empty() {
+            writeln!(
+                out,
+                "Edge portability: {}",
+                format_counts(portability_counts)
+            )?;
+        }
+        let builtin_counts = builtin_counts(&results.packages);
+        if !builtin_counts.is_empty() {
+            writeln!(
+                out,
+                "Node builtins imported: {}",
+                format_counts(builtin_counts)
+            )?;
+        }
+        let (implicit_import_packages, implicit_import_sites) =
+            implicit_import_counts(&results.packages);
+        if implicit_import_sites > 0 {
+            writeln!(
+                out,
+                "Imports ESM can't resolve as written: {} in {} packages",
+                implicit_import_sites, implicit_import_packages
+            )?;
+        }
+        let note_counts = note_counts(&results.packages);
+        if !note_counts.is_empty() {
+            writeln!(out, "Informational: {}", format_counts(note_counts))?;
+        }
+        let protocol_counts = protocols::protocol_counts(&results.packages);
+        if !protocol_counts.is_empty() {
+            writeln!(
+                out,
+                "Local dependencies: {}",
+                format_counts(protocol_counts)
+            )?;
+        }
+        if esm_with_cjs_only_dependencies > 0 {
+            writeln!(
+                out,
+                "ESM packages with CommonJS-only dependencies: {}",
+                esm_with_cjs_only_dependencies
+            )?;
+        }
+        if let Some(graph) = &results.graph {
+            writeln!(
+                out,
+                "Dependency graph: {} edges ({} approximate), findings by analysis: {}",
+                graph.edges,
+                graph.approximate_edges,
+                format_counts(
+                    graph
+                        .findings
+                        .iter()
+                        .map(|(analysis, count)| (analysis, count))
+                )
+            )?;
+        }
+
+        let lockfiles: Vec<&PackageDetails> = results
+            .packages
+            .iter()
+            .filter(|p| p.lockfile.is_some())
+            .collect();
+        if !lockfiles.is_empty() {
+            writeln!(out, "Install weight:")?;
+            for package_details in lockfiles {
+                let lockfile = package_details.lockfile.as_ref().unwrap();
+                writeln!(
+                    out,
+                    "  {} - {} locked, {} unique versions, {} duplicated, ~{} estimated",
+                    Green.paint(&package_details.name),
+                    lockfile.locked,
+                    lockfile.unique_versions,
+                    lockfile.duplicated,
+                    format_size(lockfile.estimated_size)
+                )?;
+            }
+        }
+
+        if let Some((breakdown, statistics)) = &results.breakdown {
+            let label = match breakdown {
+                Breakdown::Depth => "depth",
+                Breakdown::Folder => "top-level folder",
+            };
+            writeln!(out, "Summary by {}:", label)?;
+            let width = statistics
+                .iter()
+                .map(|(location, _)| display_width(location) + 1)
+                .max()
+                .unwrap_or(0);
+            for (location, statistics) in statistics {
+                writeln!(
+                    out,
+                    "  {} {} packages, ESM: {:.1}%, CommonJS only: {}",
+                    Green.paint(pad(&format!("{}:", location), width)),
+                    statistics.packages,
+                    statistics.esm_percentage,
+                    statistics.cjs_only
+                )?;
+            }
+        }
+
+        if let Some(sample) = &results.sample {
+            writeln!(
+                out,
+                "Sample: analyzed {} of {} packages (seed {}), estimates at 95% confidence:",
+                sample.analyzed, sample.discovered, sample.seed
+            )?;
+            for (label, estimate) in [
+                ("ESM", &sample.esm_share),
+                ("CommonJS only", &sample.cjs_only_share),
+                ("With findings", &sample.with_findings_share),
+            ] {
+                writeln!(
+                    out,
+                    "  {}: {:.1}% ± {:.1}% (~{:.0} packages)",
+                    label,
+                    estimate.value * 100.0,
+                    estimate.margin * 100.0,
+                    estimate.value * sample.discovered as f64
+                )?;
+            }
+            writeln!(
+                out,
+                "  Average size: {:.0} ± {:.0}",
+                sample.average_size.value, sample.average_size.margin
+            )?;
+        }
+
+        if results.packages.iter().any(|p| !p.tags.is_empty()) {
+            writeln!(out, "Summary by tag:")?;
+            let statistics = tag_statistics(&results.packages);
+            let width = statistics
+                .keys()
+                .map(|tag| display_width(tag) + 1)
+                .max()
+                .unwrap_or(0);
+            for (tag, statistics) in statistics {
+                writeln!(
+                    out,
+                    "  {} {} packages, ESM: {:.1}%, average size: {}, errors: {}",
+                    Green.paint(pad(&format!("{}:", tag), width)),
+                    statistics.packages,
+                    statistics.esm_percentage,
+                    statistics.average_size,
+                    statistics.errors
+                )?;
+            }
+        }
+
+        // only worth splitting once there's something on both sides
+        write_split_summary(
+            out,
+            "Summary by scope:",
+            scope_statistics(&results.packages, &self.dependency_classes),
+        )?;
+        write_split_summary(
+            out,
+            "Summary by workspace:",
+            workspace_statistics(&results.packages, &self.dependency_classes),
+        )?;
+
+        Ok(())
+    }
+
+    fn format_package(&self, package_details: &PackageDetails) -> String {
+        buffered(|out| format_package(out, package_details, self.max_name_len))
+    }
+}
+
+pub struct JsonFormatter {
+    pub list_skipped: bool,
+    pub dependency_usage: bool,
+    pub field_census: bool,
+    pub pretty: bool,
+    pub dependency_classes: Vec<DependencyClass>,
+    pub limits: ReportLimits,
+}
+
+// percentages to two decimals and shares to four, so the same tree reports the same digits
+// instead of whatever the division left over
+fn fixed(value: f64, decimals: i32) -> f64 {
+    let scale = 10f64.powi(decimals);
+    (value * scale).round() / scale
+}
+
+fn class_labels(classes: &[DependencyClass]) -> Vec<&'static str> {
+    classes.iter().map(|class| class.label()).collect()
+}
+
+// `list_items` caps the lists that grow with the tree, what's left out is counted under
+// "truncated"
+fn package_json(
+    package_details: &PackageDetails,
+    classes: &[DependencyClass],
+    list_items: Option<usize>,
+) -> Value {
+    let module_support = &package_details.module_support;
+    let mut truncated = serde_json::Map::new();
+    let mut cap = |field: &str, items: &[String]| {
+        let (listed, omitted) = capped(items, list_items);
+        if omitted > 0 {
+            truncated.insert(field.to_string(), json!(omitted));
+        }
+        listed.to_vec()
+    };
+    let cjs_only_dependencies = cap(
+        "cjs_only_dependencies",
+        &package_details.cjs_only_dependencies,
+    );
+    let dynamic_imports = cap("dynamic_imports", &package_details.dynamic_imports);
+    let implicit_imports = cap("implicit_imports", &package_details.implicit_imports);
+
+    // the fingerprint is what an acknowledgement pins
+    let findings_json = |findings: &[Finding]| -> Vec<Value> {
+        findings
+            .iter()
+            .map(|finding| {
+                json!({
+                    "rule": finding.rule,
+                    "message": finding.message,
+                    "fingerprint": finding_fingerprint(&package_details.name, finding),
+                })
+            })
+            .collect()
+    };
+    let findings = findings_json(&package_details.findings);
+    let suppressed_findings = findings_json(&package_details.suppressed_findings);
+    let acknowledged_findings = findings_json(&package_details.acknowledged_findings);
+    let notes = findings_json(&package_details.notes);
+
+    let mut resolutions = serde_json::Map::new();
+    for resolution in &package_details.resolutions {
+        resolutions.insert(
+            resolution.resolver.to_string(),
+            json!({ "import": resolution.import, "require": resolution.require }),
+        );
+    }
+
+    let lockfile = package_details.lockfile.as_ref().map(|lockfile| {
+        json!({
+            "locked": lockfile.locked,
+            "unique_versions": lockfile.unique_versions,
+            "duplicated": lockfile.duplicated,
+            "estimated_size": lockfile.estimated_size,
+        })
+    });
+
+    let mut package = json!({
+        "name": package_details.name,
+        "version": package_details.version,
+        "path": package_details.path.display().to_string(),
+        "duplicate_of": package_details
+            .duplicate_of
+            .as_ref()
+            .map(|path| path.display().to_string()),
+        "workspace_member": package_details.workspace_member,
+        "workspace_root": package_details
+            .workspace_root
+            .as_ref()
+            .map(|path| path.display().to_string()),
+        "owner": package_details.owner,
+        "layer": package_details.layer,
+        "history": package_details.history.as_ref().map(|history| {
+            json!({
+                "created": date(history.created),
+                "last_changed": date(history.last_changed),
+                "commits": history.commits,
+                "commits_since": date(history.since),
+            })
+        }),
+        "aliases": package_details
+            .aliases
+            .iter()
+            .map(|alias| alias.display().to_string())
+            .collect::<Vec<String>>(),
+        "size": package_details.size,
+        "partial": package_details.partial,
+        "module_support": {
+            "esm": supports_esm(module_support),
+            "cjs": supports_cjs(module_support),
+            "esm_main_mjs": module_support.esm_main_mjs,
+            "esm_type": module_support.esm_type,
+            "esm_exports": module_support.esm_exports,
+            "esm_partial": module_support.esm_partial,
+            "cjs_type": module_support.cjs_type,
+            "cjs_exports": module_support.cjs_exports,
+            // what the entry files are written as, against what they're loaded as
+            "consistency": {
+                "consistent": package_details.format_mismatches.is_empty(),
+                "mismatches": package_details
+                    .format_mismatches
+                    .iter()
+                    .map(|mismatch| {
+                        json!({
+                            "entry": mismatch.entry,
+                            "file": mismatch.file,
+                            "declared": mismatch.declared,
+                            "syntax": mismatch.syntax,
+                        })
+                    })
+                    .collect::<Vec<Value>>(),
+            },
+        },
+        "has_types": package_details.has_types,
+        "dependency_count": dependency_count(package_details, classes),
+        "dependency_counts": dependency_counts(package_details),
+        "cjs_only_dependencies": cjs_only_dependencies,
+        "local_dependencies": package_details.local_dependencies,
+        "min_es_version": package_details.min_es_version.map(syntax::es_label),
+        "es_features": package_details.es_features,
+        "node_builtins": package_details.node_builtins,
+        "dynamic_imports": dynamic_imports,
+        "imports_approximate": !package_details.dynamic_imports.is_empty(),
+        "implicit_import_count": package_details.implicit_imports.len(),
+        "implicit_imports": implicit_imports,
+        "typescript": package_details.typescript.map(|(resolution, compliant)| {
+            json!({ "resolution": resolution.label(), "compliant": compliant })
+        }),
+        "edge_portability": {
+            "class": package_details.edge_portability.label(),
+            "reasons": package_details.edge_portability_reasons,
+        },
+        "entrypoints": package_details.entrypoints,
+        "resolutions": resolutions,
+        "asset_bearing": !package_details.asset_exports.is_empty(),
+        "asset_exports": package_details.asset_exports,
+        "native_addon": package_details.native_addon,
+        "lockfile": lockfile,
+        "platforms": package_details.platforms,
+        "tags": package_details.tags,
+        "findings": findings,
+        "suppressed_findings": suppressed_findings,
+        "acknowledged_findings": acknowledged_findings,
+        "notes": notes,
+        "errors": errors_to_json(&package_details.errors),
+        // one per error, in the same order
+        "error_fingerprints": package_details
+            .errors
+            .iter()
+            .map(|error| error_fingerprint(&package_details.name, error))
+            .collect::<Vec<String>>(),
+    });
+    if !truncated.is_empty() {
+        package["truncated"] = Value::Object(truncated);
+    }
+    package
+}
+
+// scope -> its statistics, for each side of a split
+fn split_json(splits: Vec<(&str, ScopeStatistics)>) -> serde_json::Map<String, Value> {
+    splits
+        .into_iter()
+        .map(|(scope, statistics)| {
+            let statistics = json!({
+                "packages": statistics.packages,
+                "esm_percentage": fixed(statistics.esm_percentage, 2),
+                "cjs_only": statistics.cjs_only,
+                "total_size": statistics.total_size,
+                "average_size": statistics.average_size,
+                "dependencies": statistics.dependencies.map(|dependencies| {
+                    json!({
+                        "total": dependencies.total,
+                        "median": dependencies.median,
+                        "p90": dependencies.p90,
+                    })
+                }),
+                "findings": statistics.findings,
+                "errors": statistics.errors,
+            });
+            (scope.to_string(), statistics)
+        })
+        .collect()
+}
+
+impl JsonFormatter {
+    // everything in the report but the packages, which are written out one at a time
+    fn report(&self, results: &AnalysisResults) -> Value {
+        let (_, omitted_packages) = capped(&results.packages, self.limits.packages);
+        let (errors, omitted_errors) = capped(&results.errors, self.limits.errors);
+        let (implicit_import_packages, implicit_import_sites) =
+            implicit_import_counts(&results.packages);
+
+        let mut tags = serde_json::Map::new();
+        for (tag, statistics) in tag_statistics(&results.packages) {
+            tags.insert(
+                tag.to_string(),
+                json!({
+                    "packages": statistics.packages,
+                    "esm_percentage": fixed(statistics.esm_percentage, 2),
+                    "average_size": statistics.average_size,
+                    "errors": statistics.errors,
+                }),
+            );
+        }
+
+        let scopes = split_json(scope_statistics(
+            &results.packages,
+            &self.dependency_classes,
+        ));
+        let workspaces = split_json(workspace_statistics(
+            &results.packages,
+            &self.dependency_classes,
+        ));
+
+        let (findings, suppressed_findings) = results.finding_counts();
+        let (asset_bearing, asset_counts) = results.asset_counts();
+        let native_addons: Vec<&str> = results
+            .packages
+            .iter()
+            .filter(|p| !p.native_addon.is_empty())
+            .map(|p| p.name.as_str())
+            .collect();
+        let esm_with_cjs_only_dependencies = results
+            .packages
+            .iter()
+            .filter(|p| !p.cjs_only_dependencies.is_empty())
+            .count();
+        let lockfiles = results.packages.iter().filter_map(|p| p.lockfile.as_ref());
+        let locked_dependencies: usize = lockfiles.clone().map(|lockfile| lockfile.locked).sum();
+        let estimated_install_size: u64 = lockfiles.map(|lockfile| lockfile.estimated_size).sum();
+
+        let sample = results.sample.as_ref().map(|sample| {
+            let estimate = |estimate: &Estimate| {
+                json!({ "value": fixed(estimate.value, 4), "margin": fixed(estimate.margin, 4) })
+            };
+            json!({
+                "seed": sample.seed,
+                "discovered": sample.discovered,
+                "analyzed": sample.analyzed,
+                "esm_share": estimate(&sample.esm_share),
+                "cjs_only_share": estimate(&sample.cjs_only_share),
+                "with_findings_share": estimate(&sample.with_findings_share),
+                "average_size": estimate(&sample.average_size),
+            })
+        });
+
+        let breakdown = results.breakdown.as_ref().map(|(breakdown, statistics)| {
+            let mut locations = serde_json::Map::new();
+            for (location, statistics) in statistics {
+                locations.insert(
+                    location.clone(),
+                    json!({
+                        "packages": statistics.packages,
+                        "esm_percentage": fixed(statistics.esm_percentage, 2),
+                        "cjs_only": statistics.cjs_only,
+                    }),
+                );
+            }
+            let by = match breakdown {
+                Breakdown::Depth => "depth",
+                Breakdown::Folder => "folder",
+            };
+            json!({ "by": by, "locations": locations })
+        });
+
+        let dependency_usage: Option<Vec<Value>> = self.dependency_usage.then(|| {
+            let usage =
+                dependency_usage::external_usage(&results.packages, &self.dependency_classes);
+            let (usage, _) = capped(&usage, self.limits.list_items);
+            usage
+                .iter()
+                .map(|dependency| {
+                    let specs: BTreeMap<&str, usize> = dependency
+                        .specs
+                        .iter()
+                        .map(|(spec, packages)| (*spec, packages.len()))
+                        .collect();
+                    let odd_specs: Vec<Value> = dependency
+                        .odd_specs()
+                        .into_iter()
+                        .map(|(spec, packages)| {
+                            let (listed, omitted) = capped(packages, self.limits.list_items);
+                            json!({ "spec": spec, "packages": listed, "packages_omitted": omitted })
+                        })
+                        .collect();
+                    json!({
+                        "name": dependency.name,
+                        "declarations": dependency.declarations,
+                        "specs": specs,
+                        "odd_specs": odd_specs,
+                    })
+                })
+                .collect()
+        });
+
+        let field_census = self.field_census.then(|| {
+            let census = field_census::field_census(&results.packages);
+            let (listed, omitted) = capped(&census.fields, self.limits.list_items);
+            let fields: Vec<Value> = listed
+                .iter()
+                .map(|usage| {
+                    json!({
+                        "field": usage.field,
+                        "packages": usage.packages,
+                        "percentage": fixed(usage.percentage, 2),
+                    })
+                })
+                .collect();
+            json!({
+                "packages": census.packages,
+                "fields": fields,
+                "fields_omitted": omitted,
+            })
+        });
+
+        let walk = &results.walk;
+        let skipped: Option<Vec<Value>> = self.list_skipped.then(|| {
+            walk.skipped
+                .iter()
+                .map(|(path, reason)| {
+                    json!({ "path": path.display().to_string(), "reason": reason.label() })
+                })
+                .collect()
+        });
+
+        let filesystems: Vec<Value> = walk
+            .filesystems
+            .iter()
+            .map(|(device, filesystem)| {
+                json!({
+                    "device": device,
+                    "directories": filesystem.directories,
+                    "time_ms": filesystem.time.as_millis() as u64,
+                })
+            })
+            .collect();
+
+        let summary = json!({
+            "total_packages": results.packages.len(),
+            "directories_scanned": walk.directories_scanned,
+            "files_processed": walk.files_processed,
+            "bytes_read": walk.bytes_read,
+            "skipped_directories": walk.skipped_counts(),
+            "skipped": skipped,
+            "filesystems": filesystems,
+            "findings": findings,
+            "suppressed_findings": suppressed_findings,
+            "acknowledged_findings": results.acknowledged_count(),
+            "layer_violations": results.layer_violations().len(),
+            "stale_cjs_only": stale_cjs_only(&results.packages).len(),
+            "asset_bearing_packages": asset_bearing,
+            "asset_exports": asset_counts,
+            "native_addons": native_addons,
+            "esm_with_cjs_only_dependencies": esm_with_cjs_only_dependencies,
+            "dependency_protocols": protocols::protocol_counts(&results.packages),
+            "graph": results.graph.as_ref().map(|graph| {
+                json!({
+                    "edges": graph.edges,
+                    "approximate_edges": graph.approximate_edges,
+                    "findings": graph
+                        .findings
+                        .iter()
+                        .map(|(analysis, count)| (analysis.to_string(), json!(count)))
+                        .collect::<serde_json::Map<String, Value>>(),
+                })
+            }),
+            "dependency_usage": dependency_usage,
+            "field_census": field_census,
+            "edge_portability": portability::portability_counts(&results.packages),
+            "typescript": typescript::compliance_counts(&results.packages)
+                .into_iter()
+                .map(|(resolution, (compliant, non_compliant))| {
+                    (
+                        resolution.to_string(),
+                        json!({ "compliant": compliant, "non_compliant": non_compliant }),
+                    )
+                })
+                .collect::<serde_json::Map<String, Value>>(),
+            "min_es_versions": es_version_counts(&results.packages)
+                .into_iter()
+                .map(|(version, count)| (version, json!(count)))
+                .collect::<serde_json::Map<String, Value>>(),
+            "node_builtins": builtin_counts(&results.packages)
+                .into_iter()
+                .collect::<BTreeMap<&str, usize>>(),
+            "implicit_imports": {
+                "packages": implicit_import_packages,
+                "sites": implicit_import_sites,
+            },
+            "notes": note_counts(&results.packages),
+            "dependencies": dependency_statistics(&results.packages, &self.dependency_classes)
+                .map(|statistics| {
+                    json!({
+                        "classes": class_labels(&self.dependency_classes),
+                        "total": statistics.total,
+                        "median": statistics.median,
+                        "p90": statistics.p90,
+                    })
+                }),
+            "locked_dependencies": locked_dependencies,
+            "estimated_install_size": estimated_install_size,
+            "platforms": results.platform_counts(),
+            "tags": tags,
+            "scopes": scopes,
+            "workspaces": workspaces,
+            "sample": sample,
+            "breakdown": breakdown,
+        });
+
+        json!({
+            "errors": errors_to_json(errors),
+            "error_fingerprints": errors
+                .iter()
+                .map(|error| error_fingerprint("", error))
+                .collect::<Vec<String>>(),
+            "partial": results.partial(),
+            // what the report caps left out, zero when nothing was
+            "truncated": {
+                "packages": omitted_packages,
+                "errors": omitted_errors,
+            },
+            "changes": results.comparison.as_ref().map(|comparison| {
+                let (listed, omitted) = capped(&comparison.changes, self.limits.list_items);
+                let version_and_path = |side: &Option<(String, PathBuf)>| {
+                    side.as_ref().map(|(version, path)| {
+                        json!({ "version": version, "path": path.display().to_string() })
+                    })
+                };
+                json!({
+                    "compared_with": comparison.compared_with.display().to_string(),
+                    "counts": CHANGE_KINDS
+                        .iter()
+                        .map(|&kind| (kind.label().to_string(), json!(comparison.count(kind))))
+                        .collect::<serde_json::Map<String, Value>>(),
+                    "packages": listed
+                        .iter()
+                        .map(|change| {
+                            json!({
+                                "change": change.kind.label(),
+                                "name": change.name,
+                                "before": version_and_path(&change.before),
+                                "after": version_and_path(&change.after),
+                            })
+                        })
+                        .collect::<Vec<Value>>(),
+                    "packages_omitted": omitted,
+                    "regressions": comparison
+                        .regressions
+                        .iter()
+                        .map(|regression| {
+                            json!({
+                                "kind": regression.kind,
+                                "name": regression.name,
+                                "path": regression.path.display().to_string(),
+                                "detail": regression.detail,
+                            })
+                        })
+                        .collect::<Vec<Value>>(),
+                })
+            }),
+            "summary": summary,
+        })
+    }
+}
+
+impl Formatter for JsonFormatter {
+    // laid out the way serde_json writes a whole document, but only one package is held
+    // as JSON at a time: "packages" first, then the rest of the report
+    fn format_to(&self, results: &AnalysisResults, out: &mut dyn Write) -> io::Result<()> {
+        let (listed, _) = capped(&results.packages, self.limits.packages);
+        let package = |p| package_json(p, &self.dependency_classes, self.limits.list_items);
+        let rest = self.report(results);
+        match self.pretty {
+            true => {
+                write!(out, "{{\n  \"packages\": [")?;
+                for (index, p) in listed.iter().enumerate() {
+                    let separator = if index > 0 { "," } else { "" };
+                    let package = serde_json::to_string_pretty(&package(p))?;
+                    write!(
+                        out,
+                        "{}\n    {}",
+                        separator,
+                        package.replace('\n', "\n    ")
+                    )?;
+                }
+                if !listed.is_empty() {
+                    write!(out, "\n  ")?;
+                }
+                // the rest, without the opening brace it starts with
+                let rest = serde_json::to_string_pretty(&rest)?;
+                write!(out, "],\n{}", &rest[2..])
+            }
+            false => {
+                write!(out, "{{\"packages\":[")?;
+                for (index, p) in listed.iter().enumerate() {
+                    if index > 0 {
+                        write!(out, ",")?;
+                    }
+                    serde_json::to_writer(&mut *out, &package(p))?;
+                }
+                let rest = serde_json::to_string(&rest)?;
+                write!(out, "],{}", &rest[1..])
+            }
+        }
+    }
+
+    fn format_package(&self, package_details: &PackageDetails) -> String {
+        let package = package_json(
+            package_details,
+            &self.dependency_classes,
+            self.limits.list_items,
+        );
+        match self.pretty {
+            true => serde_json::to_string_pretty(&package).unwrap(),
+            false => serde_json::to_string(&package).unwrap(),
+        }
+    }
+}
+
+// JSON Lines, for log pipelines that take one record at a time: a line per package as
+// it's rendered, then one with everything else the JSON report has. Each line says which
+// it is in "type"
+pub struct NdjsonFormatter {
+    pub json: JsonFormatter,
+}
+
+fn ndjson_line(kind: &str, fields: serde_json::Map<String, Value>) -> Value {
+    let mut line = serde_json::Map::new();
+    line.insert("type".to_string(), json!(kind));
+    line.extend(fields);
+    Value::Object(line)
+}
+
+fn object(value: Value) -> serde_json::Map<String, Value> {
+    match value {
+        Value::Object(fields) => fields,
+        _ => serde_json::Map::new(),
+    }
+}
+
+impl Formatter for NdjsonFormatter {
+    fn format_to(&self, results: &AnalysisResults, out: &mut dyn Write) -> io::Result<()> {
+        let json = &self.json;
+        let (listed, _) = capped(&results.packages, json.limits.packages);
+        for package_details in listed {
+            let package = package_json(
+                package_details,
+                &json.dependency_classes,
+                json.limits.list_items,
+            );
+            serde_json::to_writer(&mut *out, &ndjson_line("package", object(package)))?;
+            writeln!(out)?;
+        }
+
+        // the packages are already out, the truncated counts say how many were left off
+        let summary = object(json.report(results));
+        serde_json::to_writer(&mut *out, &ndjson_line("summary", summary))?;
+        writeln!(out)
+    }
+
+    fn format_package(&self, package_details: &PackageDetails) -> String {
+        let package = package_json(
+            package_details,
+            &self.json.dependency_classes,
+            self.json.limits.list_items,
+        );
+        serde_json::to_string(&ndjson_line("package", object(package))).unwrap() + "\n"
+    }
+}
+
+// the NDJSON report written while the tree is walked: each package's line as soon as it's
+// analyzed, then, once the whole tree is, an "update" line for every package the
+// tree-wide analyses changed, like dependency cycles or workspace membership, holding
+// only the fields that changed, and the summary line last
+pub struct NdjsonStream {
+    pub json: JsonFormatter,
+    // the fields of each line streamed so far, hashed, by the package's path
+    streamed: HashMap<String, Vec<(String, u64)>>,
+    // the first write that failed, nothing more is streamed after it
+    error: Option<io::Error>,
+}
+
+fn field_hash(value: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.to_string().hash(&mut hasher);
+    hasher.finish()
+}
+
+impl NdjsonStream {
+    pub fn new(json: JsonFormatter) -> NdjsonStream {
+        NdjsonStream {
+            json,
+            streamed: HashMap::new(),
+            error: None,
+        }
+    }
+
+    // the package's line, flushed so it's out before the walk moves on. Past
+    // --report-max-packages packages are left for the summary to count
+    pub fn package(
+        &mut self,
+        package_details: &PackageDetails,
+        out: &mut dyn Write,
+        redact: &dyn Fn(&str) -> String,
+    ) {
+        let listed = self.json.limits.packages.unwrap_or(usize::MAX);
+        if self.error.is_some() || self.streamed.len() >= listed {
+            return;
+        }
+        let fields = object(package_json(
+            package_details,
+            &self.json.dependency_classes,
+            self.json.limits.list_items,
+        ));
+        let hashes = fields
+            .iter()
+            .map(|(key, value)| (key.clone(), field_hash(value)))
+            .collect();
+        self.streamed
+            .insert(package_details.path.display().to_string(), hashes);
+        let line = serde_json::to_string(&ndjson_line("package", fields)).unwrap();
+        if let Err(err) = writeln!(out, "{}", redact(&line)).and_then(|_| out.flush()) {
+            self.error = Some(err);
+        }
+    }
+
+    pub fn finish(&mut self, results: &AnalysisResults, out: &mut dyn Write) -> io::Result<()> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        let json = &self.json;
+        let (listed, _) = capped(&results.packages, json.limits.packages);
+        for package_details in listed {
+            let path = package_details.path.display().to_string();
+            let fields = object(package_json(
+                package_details,
+                &json.dependency_classes,
+                json.limits.list_items,
+            ));
+            let line = match self.streamed.get(&path) {
+                Some(hashes) => {
+                    let changed: Vec<(String, Value)> = fields
+                        .into_iter()
+                        .filter(|(key, value)| !hashes.contains(&(key.clone(), field_hash(value))))
+                        .collect();
+                    if changed.is_empty() {
+                        continue;
+                    }
+                    let mut update = serde_json::Map::new();
+                    update.insert("path".to_string(), json!(path));
+                    update.extend(changed);
+                    ndjson_line("update", update)
+                }
+                // a package the walk didn't stream
+                None => ndjson_line("package", fields),
+            };
+            serde_json::to_writer(&mut *out, &line)?;
+            writeln!(out)?;
+        }
+
+        let summary = object(json.report(results));
+        serde_json::to_writer(&mut *out, &ndjson_line("summary", summary))?;
+        writeln!(out)
+    }
+}
+
+pub struct TableFormatter {
+    pub color: bool,
+    // the table is narrowed to fit, by shortening the name column
+    pub max_width: usize,
+    pub field_census: bool,
+    pub dependency_classes: Vec<DependencyClass>,
+    pub limits: ReportLimits,
+}
+
+const TABLE_HEADERS: [&str; 7] = ["Name", "Version", "ESM", "CJS", "TS", "Size", "Deps"];
+const CENSUS_HEADERS: [&str; 3] = ["Field", "Packages", "Share"];
+
+fn yes_no(value: bool) -> &'static str {
+    match value {
+        true => "yes",
+        false => "no",
+    }
+}
+
+// the size and dependency columns are numbers, and right aligned
+fn align(column: usize, cell: &str, width: usize) -> String {
+    match column >= 5 {
+        true => format!("{:>width$}", cell, width = width),
+        false => pad(cell, width),
+    }
+}
+
+impl TableFormatter {
+    fn paint_cell(&self, column: usize, cell: &str, padded: String) -> String {
+        if !self.color {
+            return padded;
+        }
+        match (column, cell) {
+            (0, _) => Green.paint(padded).to_string(),
+            (2..=4, "yes") => Green.paint(padded).to_string(),
+            (2..=4, "no") => Red.paint(padded).to_string(),
+            _ => padded,
+        }
+    }
+}
+
+impl Formatter for TableFormatter {
+    fn format_to(&self, results: &AnalysisResults, out: &mut dyn Write) -> io::Result<()> {
+        let (listed, omitted_packages) = capped(&results.packages, self.limits.packages);
+        self.render(listed, out)?;
+        if omitted_packages > 0 {
+            writeln!(
+                out,
+                "{}",
+                omitted(omitted_packages, "packages", "--report-max-packages")
+            )?;
+        }
+        if self.field_census {
+            writeln!(out)?;
+            self.render_census(&field_census::field_census(&results.packages), out)?;
+        }
+        Ok(())
+    }
+
+    fn format_package(&self, package_details: &PackageDetails) -> String {
+        buffered(|out| self.render(std::slice::from_ref(package_details), out))
+    }
+}
+
+impl TableFormatter {
+    fn render(&self, packages: &[PackageDetails], out: &mut dyn Write) -> io::Result<()> {
+        let rows: Vec<[String; 7]> = packages
+            .iter()
+            .map(|p| {
+                [
+                    p.name.clone(),
+                    p.version.clone(),
+                    yes_no(supports_esm(&p.module_support)).to_string(),
+                    yes_no(supports_cjs(&p.module_support)).to_string(),
+                    yes_no(p.has_types).to_string(),
+                    p.size.to_string(),
+                    dependency_count(p, &self.dependency_classes).to_string(),
+                ]
+            })
+            .collect();
+
+        let mut widths: Vec<usize> = TABLE_HEADERS.iter().map(|h| display_width(h)).collect();
+        for row in &rows {
+            for (column, cell) in row.iter().enumerate() {
+                widths[column] = widths[column].max(display_width(cell));
+            }
+        }
+
+        // columns are separated by two spaces
+        let total_width: usize = widths.iter().sum::<usize>() + 2 * (widths.len() - 1);
+        if total_width > self.max_width {
+            let excess = total_width - self.max_width;
+            widths[0] = widths[0]
+                .saturating_sub(excess)
+                .max(display_width(TABLE_HEADERS[0]));
+        }
+
+        let header: Vec<String> = TABLE_HEADERS
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(column, (header, width))| align(column, header, *width))
+            .collect();
+        writeln!(out, "{}", header.join("  ").trim_end())?;
+        let rule: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+        writeln!(out, "{}", rule.join("  "))?;
+
+        for row in &rows {
+            let cells: Vec<String> = row
+                .iter()
+                .enumerate()
+                .map(|(column, cell)| {
+                    let cell = truncate(cell, widths[column]);
+                    let padded = align(column, &cell, widths[column]);
+                    self.paint_cell(column, &cell, padded)
+                })
+                .collect();
+            writeln!(out, "{}", cells.join("  ").trim_end())?;
+        }
+
+        Ok(())
+    }
+
+    // a second table, one row per package.json field, the counts right aligned
+    fn render_census(&self, census: &FieldCensus, out: &mut dyn Write) -> io::Result<()> {
+        let (listed, omitted_fields) = capped(&census.fields, self.limits.list_items);
+        let rows: Vec<[String; 3]> = listed
+            .iter()
+            .map(|usage| {
+                [
+                    usage.field.to_string(),
+                    usage.packages.to_string(),
+                    format!("{:.1}%", usage.percentage),
+                ]
+            })
+            .collect();
+
+        let mut widths: Vec<usize> = CENSUS_HEADERS.iter().map(|h| display_width(h)).collect();
+        for row in &rows {
+            for (column, cell) in row.iter().enumerate() {
+                widths[column] = widths[column].max(display_width(cell));
+            }
+        }
+        let line = |cells: &[String]| -> String {
+            let cells: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .enumerate()
+                .map(|(column, (cell, width))| match column {
+                    0 => pad(cell, *width),
+                    _ => format!("{:>width$}", cell, width = width),
+                })
+                .collect();
+            cells.join("  ").trim_end().to_string()
+        };
+
+        let header = CENSUS_HEADERS.map(String::from);
+        writeln!(out, "{}", line(&header))?;
+        let rule: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+        writeln!(out, "{}", rule.join("  "))?;
+        for row in &rows {
+            let mut cells = row.clone();
+            if self.color {
+                cells[0] = Green.paint(pad(&cells[0], widths[0])).to_string();
+            }
+            writeln!(out, "{}", line(&cells))?;
+        }
+        if omitted_fields > 0 {
+            writeln!(
+                out,
+                "{}",
+                omitted(omitted_fields, "fields", "--report-max-items")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// a single page with everything inline, no scripts or styles fetched from anywhere, so
+// it can be opened straight from a CI artifact
+pub struct HtmlFormatter {
+    pub dependency_classes: Vec<DependencyClass>,
+    pub limits: ReportLimits,
+}
+
+// bars in the charts, past this only the largest are drawn
+const CHART_BARS: usize = 20;
+
+const HTML_STYLE: &str = "body { font-family: system-ui, sans-serif; margin: 2em; color: #222; }
+h1 { font-size: 1.6em; } h2 { font-size: 1.2em; margin-top: 2em; }
+table { border-collapse: collapse; margin: 0.5em 0; }
+th, td { padding: 0.25em 0.75em; border-bottom: 1px solid #ddd; text-align: left; }
+th { background: #f4f4f4; }
+th.sortable { cursor: pointer; }
+td.number { text-align: right; font-variant-numeric: tabular-nums; }
+.yes { color: #1a7f37; } .no { color: #b42318; } .partial { color: #b42318; }
+.chart td { border: none; padding: 0.1em 0.5em; }
+.bar { background: #eee; width: 24em; height: 1em; }
+.bar span { display: block; height: 100%; background: #3b82f6; }
+.stack { display: flex; width: 40em; height: 1.5em; }
+.stack span { height: 100%; }
+.dual { background: #1a7f37; } .esm { background: #3b82f6; }
+.cjs { background: #f59e0b; } .unknown { background: #9ca3af; }
+.legend span { display: inline-block; width: 0.8em; height: 0.8em; margin: 0 0.3em 0 1em; }
+#filter { margin: 0.5em 0; padding: 0.3em; width: 20em; }";
+
+// filters the package table as you type and sorts it by the header clicked, numbers by
+// the value they were rendered from
+const HTML_SCRIPT: &str = "const table = document.getElementById('packages');
+document.getElementById('filter').addEventListener('input', (event) => {
+  const text = event.target.value.toLowerCase();
+  for (const row of table.tBodies[0].rows) {
+    row.hidden = !row.cells[0].textContent.toLowerCase().includes(text);
+  }
+});
+table.querySelectorAll('th.sortable').forEach((header, column) => {
+  let ascending = true;
+  header.addEventListener('click', () => {
+    const value = (row) => row.cells[column].dataset.value ?? row.cells[column].textContent;
+    const rows = [...table.tBodies[0].rows].sort((a, b) => {
+      const [x, y] = [value(a), value(b)];
+      const order = isNaN(x) || isNaN(y) ? x.localeCompare(y) : x - y;
+      return ascending ? order : -order;
+    });
+    ascending = !ascending;
+    table.tBodies[0].append(...rows);
+  });
+});";
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn html_yes_no(value: bool) -> &'static str {
+    match value {
+        true => "<td class=\"yes\">yes</td>",
+        false => "<td class=\"no\">no</td>",
+    }
+}
+
+fn module_support_class(package_details: &PackageDetails) -> &'static str {
+    match (
+        supports_esm(&package_details.module_support),
+        supports_cjs(&package_details.module_support),
+    ) {
+        (true, true) => "dual",
+        (true, false) => "esm",
+        (false, true) => "cjs",
+        (false, false) => "unknown",
+    }
+}
+
+fn html_start(out: &mut dyn Write, title: &str) -> io::Result<()> {
+    writeln!(
+        out,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>\n<h1>{}</h1>",
+        escape_html(title),
+        HTML_STYLE,
+        escape_html(title)
+    )
+}
+
+fn html_summary_row(out: &mut dyn Write, label: &str, value: &str) -> io::Result<()> {
+    writeln!(
+        out,
+        "<tr><th>{}</th><td>{}</td></tr>",
+        escape_html(label),
+        escape_html(value)
+    )
+}
+
+// one bar per entry, scaled to the largest
+fn html_bar_chart(
+    out: &mut dyn Write,
+    title: &str,
+    bars: &[(String, u64, String)],
+) -> io::Result<()> {
+    let largest = bars.iter().map(|(_, value, _)| *value).max().unwrap_or(0);
+    if largest == 0 {
+        return Ok(());
+    }
+    writeln!(
+        out,
+        "<h2>{}</h2>\n<table class=\"chart\">",
+        escape_html(title)
+    )?;
+    for (label, value, shown) in bars {
+        writeln!(
+            out,
+            "<tr><td>{}</td><td><div class=\"bar\"><span style=\"width: {:.1}%\"></span></div></td><td class=\"number\">{}</td></tr>",
+            escape_html(label),
+            *value as f64 * 100.0 / largest as f64,
+            escape_html(shown)
+        )?;
+    }
+    writeln!(out, "</table>")
+}
+
+fn html_findings(out: &mut dyn Write, findings: &[(&PackageDetails, &Finding)]) -> io::Result<()> {
+    writeln!(
+        out,
+        "<h2>Findings</h2>\n<table>\n<tr><th>Package</th><th>Rule</th><th>Message</th></tr>"
+    )?;
+    for (package_details, finding) in findings {
+        writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape_html(&package_details.name),
+            escape_html(&finding.rule),
+            escape_html(&finding.message)
+        )?;
+    }
+    writeln!(out, "</table>")
+}
+
+fn html_split_table(
+    out: &mut dyn Write,
+    heading: &str,
+    splits: Vec<(&str, ScopeStatistics)>,
+) -> io::Result<()> {
+    if splits.len() < 2 {
+        return Ok(());
+    }
+    writeln!(out, "<h2>{}</h2>\n<table>\n<tr>", heading)?;
+    for header in [
+        "Scope",
+        "Packages",
+        "ESM",
+        "CommonJS only",
+        "Total size",
+        "Average size",
+        "Dependencies",
+        "Median",
+        "p90",
+        "Findings",
+        "Errors",
+    ] {
+        writeln!(out, "<th>{}</th>", header)?;
+    }
+    writeln!(out, "</tr>")?;
+    for (scope, statistics) in splits {
+        let (total, median, p90) = match &statistics.dependencies {
+            Some(dependencies) => (dependencies.total, dependencies.median, dependencies.p90),
+            None => (0, 0, 0),
+        };
+        writeln!(
+            out,
+            "<tr><th>{}</th><td class=\"number\">{}</td><td class=\"number\">{:.1}%</td><td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td></tr>",
+            scope,
+            statistics.packages,
+            statistics.esm_percentage,
+            statistics.cjs_only,
+            format_size(statistics.total_size),
+            format_size(statistics.average_size),
+            total,
+            median,
+            p90,
+            statistics.findings,
+            statistics.errors
+        )?;
+    }
+    writeln!(out, "</table>")
+}
+
+impl Formatter for HtmlFormatter {
+    fn format_to(&self, results: &AnalysisResults, out: &mut dyn Write) -> io::Result<()> {
+        let packages = &results.packages;
+        html_start(out, "walker report")?;
+        if results.partial() {
+            writeln!(
+                out,
+                "<p class=\"partial\">The scan stopped early, results are partial.</p>"
+            )?;
+        }
+
+        let esm = packages
+            .iter()
+            .filter(|p| supports_esm(&p.module_support))
+            .count();
+        let cjs_only = packages
+            .iter()
+            .filter(|p| supports_cjs(&p.module_support) && !supports_esm(&p.module_support))
+            .count();
+        let with_types = packages.iter().filter(|p| p.has_types).count();
+        let share = |count: usize| match packages.len() {
+            0 => String::new(),
+            total => format!(" ({:.1}%)", count as f64 * 100.0 / total as f64),
+        };
+        let (findings, suppressed_findings) = results.finding_counts();
+        let walk = &results.walk;
+
+        writeln!(out, "<h2>Summary</h2>\n<table>")?;
+        html_summary_row(out, "Packages", &packages.len().to_string())?;
+        html_summary_row(out, "ESM", &format!("{}{}", esm, share(esm)))?;
+        html_summary_row(
+            out,
+            "CommonJS only",
+            &format!("{}{}", cjs_only, share(cjs_only)),
+        )?;
+        html_summary_row(
+            out,
+            "With types",
+            &format!("{}{}", with_types, share(with_types)),
+        )?;
+        html_summary_row(
+            out,
+            "Total size",
+            &format_size(packages.iter().map(|p| p.size).sum()),
+        )?;
+        if let Some(statistics) = dependency_statistics(packages, &self.dependency_classes) {
+            html_summary_row(
+                out,
+                &format!(
+                    "Dependencies ({})",
+                    class_labels(&self.dependency_classes).join(", ")
+                ),
+                &format!(
+                    "{} total, median {}, p90 {} per package",
+                    statistics.total, statistics.median, statistics.p90
+                ),
+            )?;
+        }
+        html_summary_row(
+            out,
+            "Findings",
+            &format!(
+                "{}, suppressed: {}, acknowledged: {}",
+                findings,
+                suppressed_findings,
+                results.acknowledged_count()
+            ),
+        )?;
+        for (resolution, (compliant, non_compliant)) in typescript::compliance_counts(packages) {
+            html_summary_row(
+                out,
+                &format!("TypeScript {}", resolution),
+                &format!(
+                    "{} packages with types resolve cleanly, {} don't",
+                    compliant, non_compliant
+                ),
+            )?;
+        }
+        html_summary_row(out, "Errors", &results.errors.len().to_string())?;
+        html_summary_row(
+            out,
+            "Scanned",
+            &format!(
+                "{} directories, {} files, read {}",
+                walk.directories_scanned,
+                walk.files_processed,
+                format_size(walk.bytes_read)
+            ),
+        )?;
+        writeln!(out, "</table>")?;
+
+        html_split_table(
+            out,
+            "Published and private packages",
+            scope_statistics(packages, &self.dependency_classes),
+        )?;
+        html_split_table(
+            out,
+            "Workspace and vendored packages",
+            workspace_statistics(packages, &self.dependency_classes),
+        )?;
+
+        if !packages.is_empty() {
+            writeln!(out, "<h2>Module support</h2>\n<div class=\"stack\">")?;
+            let kinds = [
+                ("dual", "ESM and CommonJS"),
+                ("esm", "ESM only"),
+                ("cjs", "CommonJS only"),
+                ("unknown", "Unknown"),
+            ];
+            let counts: Vec<usize> = kinds
+                .iter()
+                .map(|(class, _)| {
+                    packages
+                        .iter()
+                        .filter(|p| module_support_class(p) == *class)
+                        .count()
+                })
+                .collect();
+            for ((class, label), count) in kinds.iter().zip(&counts) {
+                writeln!(
+                    out,
+                    "<span class=\"{}\" style=\"width: {:.1}%\" title=\"{}: {}\"></span>",
+                    class,
+                    *count as f64 * 100.0 / packages.len() as f64,
+                    label,
+                    count
+                )?;
+            }
+            writeln!(out, "</div>\n<p class=\"legend\">")?;
+            for ((class, label), count) in kinds.iter().zip(&counts) {
+                writeln!(out, "<span class=\"{}\"></span>{}: {}", class, label, count)?;
+            }
+            writeln!(out, "</p>")?;
+        }
+
+        let mut by_size: Vec<&PackageDetails> = packages.iter().collect();
+        by_size.sort_by(|a, b| b.size.cmp(&a.size).then(a.name.cmp(&b.name)));
+        let bars: Vec<(String, u64, String)> = by_size
+            .iter()
+            .take(CHART_BARS)
+            .map(|p| (p.name.clone(), p.size, format_size(p.size)))
+            .collect();
+        html_bar_chart(out, "Largest packages", &bars)?;
+
+        let mut by_dependencies: Vec<(&PackageDetails, usize)> = packages
+            .iter()
+            .map(|p| (p, dependency_count(p, &self.dependency_classes)))
+            .collect();
+        by_dependencies.sort_by(|(p, a), (q, b)| b.cmp(a).then(p.name.cmp(&q.name)));
+        let bars: Vec<(String, u64, String)> = by_dependencies
+            .iter()
+            .take(CHART_BARS)
+            .map(|(p, count)| (p.name.clone(), *count as u64, count.to_string()))
+            .collect();
+        html_bar_chart(out, "Most dependencies", &bars)?;
+
+        let (listed, omitted_packages) = capped(packages, self.limits.packages);
+        writeln!(
+            out,
+            "<h2>Packages</h2>\n<input id=\"filter\" type=\"search\" placeholder=\"Filter by name\">\n<table id=\"packages\">\n<thead><tr>"
+        )?;
+        for header in [
+            "Name", "Version", "ESM", "CJS", "Types", "Size", "Deps", "Findings",
+        ] {
+            writeln!(out, "<th class=\"sortable\">{}</th>", header)?;
+        }
+        writeln!(out, "</tr></thead>\n<tbody>")?;
+        for p in listed {
+            writeln!(
+                out,
+                "<tr><td title=\"{}\">{}</td><td>{}</td>{}{}{}<td class=\"number\" data-value=\"{}\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td></tr>",
+                escape_html(&p.path.display().to_string()),
+                escape_html(&p.name),
+                escape_html(&p.version),
+                html_yes_no(supports_esm(&p.module_support)),
+                html_yes_no(supports_cjs(&p.module_support)),
+                html_yes_no(p.has_types),
+                p.size,
+                format_size(p.size),
+                dependency_count(p, &self.dependency_classes),
+                p.findings.len()
+            )?;
+        }
+        writeln!(out, "</tbody>\n</table>")?;
+        if omitted_packages > 0 {
+            writeln!(
+                out,
+                "<p>{}</p>",
+                escape_html(&omitted(
+                    omitted_packages,
+                    "packages",
+                    "--report-max-packages"
+                ))
+            )?;
+        }
+
+        if findings > 0 {
+            let findings: Vec<(&PackageDetails, &Finding)> = packages
+                .iter()
+                .flat_map(|p| p.findings.iter().map(move |finding| (p, finding)))
+                .collect();
+            let (listed, omitted_findings) = capped(&findings, self.limits.list_items);
+            html_findings(out, listed)?;
+            if omitted_findings > 0 {
+                writeln!(
+                    out,
+                    "<p>{}</p>",
+                    escape_html(&omitted(omitted_findings, "findings", "--report-max-items"))
+                )?;
+            }
+        }
+
+        if !results.errors.is_empty() {
+            writeln!(out, "<h2>Errors</h2>\n<ul>")?;
+            let (errors, omitted_errors) = capped(&results.errors, self.limits.errors);
+            for error in errors {
+                writeln!(out, "<li>{}</li>", escape_html(&error.to_string()))?;
+            }
+            writeln!(out, "</ul>")?;
+            if omitted_errors > 0 {
+                writeln!(
+                    out,
+                    "<p>{}</p>",
+                    escape_html(&omitted(omitted_errors, "errors", "--report-max-errors"))
+                )?;
+            }
+        }
+
+        writeln!(
+            out,
+            "<script>\n{}\n</script>\n</body>\n</html>",
+            HTML_SCRIPT
+        )
+    }
+
+    fn format_package(&self, package_details: &PackageDetails) -> String {
+        buffered(|out| {
+            let p = package_details;
+            html_start(out, &format!("{}@{}", p.name, p.version))?;
+            writeln!(out, "<table>")?;
+            html_summary_row(out, "Path", &p.path.display().to_string())?;
+            writeln!(
+                out,
+                "<tr><th>ESM</th>{}</tr>\n<tr><th>CommonJS</th>{}</tr>\n<tr><th>Types</th>{}</tr>",
+                html_yes_no(supports_esm(&p.module_support)),
+                html_yes_no(supports_cjs(&p.module_support)),
+                html_yes_no(p.has_types)
+            )?;
+            html_summary_row(out, "Size", &format_size(p.size))?;
+            html_summary_row(out, "Dependencies", &format_counts(dependency_counts(p)))?;
+            if !p.tags.is_empty() {
+                html_summary_row(out, "Tags", &p.tags.join(", "))?;
+            }
+            writeln!(out, "</table>")?;
+            if !p.findings.is_empty() {
+                let findings: Vec<(&PackageDetails, &Finding)> =
+                    p.findings.iter().map(|finding| (p, finding)).collect();
+                html_findings(out, &findings)?;
+            }
+            if !p.errors.is_empty() {
+                writeln!(out, "<h2>Errors</h2>\n<ul>")?;
+                for error in &p.errors {
+                    writeln!(out, "<li>{}</li>", escape_html(&error.to_string()))?;
+                }
+                writeln!(out, "</ul>")?;
+            }
+            writeln!(out, "</body>\n</html>")
+        })
+    }
+}
+
+// one row per package, for spreadsheets. Every package is listed, a CSV file has no
+// room for a note saying what a report cap left out
+pub struct CsvFormatter {
+    pub dependency_classes: Vec<DependencyClass>,
+}
+
+const CSV_HEADERS: [&str; 10] = [
+    "name",
+    "version",
+    "path",
+    "esm",
+    "cjs",
+    "types",
+    "size",
+    "dependencies",
+    "findings",
+    "errors",
+];
+
+// quoted only when it has to be, doubling any quotes inside
+fn csv_field(value: &str) -> String {
+    match value.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
+
+impl Formatter for CsvFormatter {
+    fn format_to(&self, results: &AnalysisResults, out: &mut dyn Write) -> io::Result<()> {
+        self.render(&results.packages, out)
+    }
+
+    fn format_package(&self, package_details: &PackageDetails) -> String {
+        buffered(|out| self.render(std::slice::from_ref(package_details), out))
+    }
+}
+
+impl CsvFormatter {
+    fn render(&self, packages: &[PackageDetails], out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "{}", CSV_HEADERS.join(","))?;
+        for p in packages {
+            let row = [
+                p.name.clone(),
+                p.version.clone(),
+                p.path.display().to_string(),
+                yes_no(supports_esm(&p.module_support)).to_string(),
+                yes_no(supports_cjs(&p.module_support)).to_string(),
+                yes_no(p.has_types).to_string(),
+                p.size.to_string(),
+                dependency_count(p, &self.dependency_classes).to_string(),
+                p.findings.len().to_string(),
+                p.errors.len().to_string(),
+            ];
+            let fields: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+            writeln!(out, "{}", fields.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+// GitHub flavored markdown, short enough to post as a pull request comment
+pub struct MarkdownFormatter {
+    pub dependency_classes: Vec<DependencyClass>,
+    pub limits: ReportLimits,
+}
+
+// a pipe would end the cell and a newline the row
+fn escape_markdown(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace('\n', " ")
+}
+
+fn markdown_row(out: &mut dyn Write, cells: &[String]) -> io::Result<()> {
+    writeln!(out, "| {} |", cells.join(" | "))
+}
+
+// numeric columns are right aligned
+fn markdown_header(out: &mut dyn Write, headers: &[&str], numeric_from: usize) -> io::Result<()> {
+    writeln!(out, "| {} |", headers.join(" | "))?;
+    let rule: Vec<&str> = (0..headers.len())
+        .map(|column| match column >= numeric_from {
+            true => "---:",
+            false => "---",
+        })
+        .collect();
+    writeln!(out, "| {} |", rule.join(" | "))
+}
+
+fn markdown_findings(
+    out: &mut dyn Write,
+    findings: &[(&PackageDetails, &Finding)],
+) -> io::Result<()> {
+    for (p, finding) in findings {
+        writeln!(
+            out,
+            "- **{}** `{}`: {}",
+            escape_markdown(&p.name),
+            finding.rule,
+            escape_markdown(&finding.message)
+        )?;
+    }
+    Ok(())
+}
+
+fn markdown_split_table(
+    out: &mut dyn Write,
+    heading: &str,
+    splits: Vec<(&str, ScopeStatistics)>,
+) -> io::Result<()> {
+    if splits.len() < 2 {
+        return Ok(());
+    }
+    writeln!(out, "\n### {}\n", heading)?;
+    markdown_header(
+        out,
+        &[
+            "Scope",
+            "Packages",
+            "ESM",
+            "CommonJS only",
+            "Total size",
+            "Findings",
+            "Errors",
+        ],
+        1,
+    )?;
+    for (scope, statistics) in splits {
+        markdown_row(
+            out,
+            &[
+                scope.to_string(),
+                statistics.packages.to_string(),
+                format!("{:.1}%", statistics.esm_percentage),
+                statistics.cjs_only.to_string(),
+                format_size(statistics.total_size),
+                statistics.findings.to_string(),
+                statistics.errors.to_string(),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+impl Formatter for MarkdownFormatter {
+    fn format_to(&self, results: &AnalysisResults, out: &mut dyn Write) -> io::Result<()> {
+        let packages = &results.packages;
+        writeln!(out, "## walker report\n")?;
+        if results.partial() {
+            writeln!(out, "> The scan stopped early, results are partial.\n")?;
+        }
+
+        let esm = packages
+            .iter()
+            .filter(|p| supports_esm(&p.module_support))
+            .count();
+        let cjs_only = packages
+            .iter()
+            .filter(|p| supports_cjs(&p.module_support) && !supports_esm(&p.module_support))
+            .count();
+        let with_types = packages.iter().filter(|p| p.has_types).count();
+        let share = |count: usize| match packages.len() {
+            0 => String::new(),
+            total => format!(" ({:.1}%)", count as f64 * 100.0 / total as f64),
+        };
+        let (findings, suppressed_findings) = results.finding_counts();
+
+        markdown_header(out, &["", ""], 2)?;
+        let mut summary = vec![
+            ("Packages".to_string(), packages.len().to_string()),
+            ("ESM".to_string(), format!("{}{}", esm, share(esm))),
+            (
+                "CommonJS only".to_string(),
+                format!("{}{}", cjs_only, share(cjs_only)),
+            ),
+            (
+                "With types".to_string(),
+                format!("{}{}", with_types, share(with_types)),
+            ),
+            (
+                "Total size".to_string(),
+                format_size(packages.iter().map(|p| p.size).sum()),
+            ),
+        ];
+        if let Some(statistics) = dependency_statistics(packages, &self.dependency_classes) {
+            summary.push((
+                format!(
+                    "Dependencies ({})",
+                    class_labels(&self.dependency_classes).join(", ")
+                ),
+                format!(
+                    "{} total, median {}, p90 {} per package",
+                    statistics.total, statistics.median, statistics.p90
+                ),
+            ));
+        }
+        summary.push((
+            "Findings".to_string(),
+            format!(
+                "{}, suppressed: {}, acknowledged: {}",
+                findings,
+                suppressed_findings,
+                results.acknowledged_count()
+            ),
+        ));
+        for (resolution, (compliant, non_compliant)) in typescript::compliance_counts(packages) {
+            summary.push((
+                format!("TypeScript {}", resolution),
+                format!(
+                    "{} packages with types resolve cleanly, {} don't",
+                    compliant, non_compliant
+                ),
+            ));
+        }
+        summary.push(("Errors".to_string(), results.errors.len().to_string()));
+        for (label, value) in summary {
+            markdown_row(out, &[format!("**{}**", label), value])?;
+        }
+
+        markdown_split_table(
+            out,
+            "Published and private packages",
+            scope_statistics(packages, &self.dependency_classes),
+        )?;
+        markdown_split_table(
+            out,
+            "Workspace and vendored packages",
+            workspace_statistics(packages, &self.dependency_classes),
+        )?;
+
+        if !packages.is_empty() {
+            let (listed, omitted_packages) = capped(packages, self.limits.packages);
+            writeln!(out, "\n### Packages\n")?;
+            markdown_header(
+                out,
+                &[
+                    "Name", "Version", "ESM", "CJS", "Types", "Size", "Deps", "Findings",
+                ],
+                5,
+            )?;
+            for p in listed {
+                markdown_row(
+                    out,
+                    &[
+                        escape_markdown(&p.name),
+                        escape_markdown(&p.version),
+                        yes_no(supports_esm(&p.module_support)).to_string(),
+                        yes_no(supports_cjs(&p.module_support)).to_string(),
+                        yes_no(p.has_types).to_string(),
+                        format_size(p.size),
+                        dependency_count(p, &self.dependency_classes).to_string(),
+                        p.findings.len().to_string(),
+                    ],
+                )?;
+            }
+            if omitted_packages > 0 {
+                writeln!(
+                    out,
+                    "\n{}",
+                    omitted(omitted_packages, "packages", "--report-max-packages")
+                )?;
+            }
+        }
+
+        if findings > 0 {
+            let findings: Vec<(&PackageDetails, &Finding)> = packages
+                .iter()
+                .flat_map(|p| p.findings.iter().map(move |finding| (p, finding)))
+                .collect();
+            let (listed, omitted_findings) = capped(&findings, self.limits.list_items);
+            writeln!(out, "\n### Findings\n")?;
+            markdown_findings(out, listed)?;
+            if omitted_findings > 0 {
+                writeln!(
+                    out,
+                    "\n{}",
+                    omitted(omitted_findings, "findings", "--report-max-items")
+                )?;
+            }
+        }
+
+        if !results.errors.is_empty() {
+            writeln!(out, "\n### Errors\n")?;
+            let (errors, omitted_errors) = capped(&results.errors, self.limits.errors);
+            for error in errors {
+                writeln!(out, "- {}", escape_markdown(&error.to_string()))?;
+            }
+            if omitted_errors > 0 {
+                writeln!(
+                    out,
+                    "\n{}",
+                    omitted(omitted_errors, "errors", "--report-max-errors")
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn format_package(&self, package_details: &PackageDetails) -> String {
+        buffered(|out| {
+            let p = package_details;
+            writeln!(
+                out,
+                "## {}@{}\n",
+                escape_markdown(&p.name),
+                escape_markdown(&p.version)
+            )?;
+            markdown_header(out, &["", ""], 2)?;
+            let mut rows = vec![
+                ("Path", escape_markdown(&p.path.display().to_string())),
+                ("ESM", yes_no(supports_esm(&p.module_support)).to_string()),
+                (
+                    "CommonJS",
+                    yes_no(supports_cjs(&p.module_support)).to_string(),
+                ),
+                ("Types", yes_no(p.has_types).to_string()),
+                ("Size", format_size(p.size)),
+                ("Dependencies", format_counts(dependency_counts(p))),
+            ];
+            if !p.tags.is_empty() {
+                rows.push(("Tags", escape_markdown(&p.tags.join(", "))));
+            }
+            for (label, value) in rows {
+                markdown_row(out, &[format!("**{}**", label), value])?;
+            }
+            if !p.findings.is_empty() {
+                writeln!(out, "\n### Findings\n")?;
+                let findings: Vec<(&PackageDetails, &Finding)> =
+                    p.findings.iter().map(|finding| (p, finding)).collect();
+                markdown_findings(out, &findings)?;
+            }
+            if !p.errors.is_empty() {
+                writeln!(out, "\n### Errors\n")?;
+                for error in &p.errors {
+                    writeln!(out, "- {}", escape_markdown(&error.to_string()))?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WalkStatistics;
+
+    fn results(packages: Vec<PackageDetails>) -> AnalysisResults {
+        AnalysisResults {
+            packages,
+            errors: Vec::new(),
+            timed_out: false,
+            error_limit_reached: false,
+            sample: None,
+            breakdown: None,
+            walk: WalkStatistics::default(),
+            graph: None,
+            comparison: None,
+        }
+    }
+
+    fn lines(out: Vec<u8>) -> Vec<Value> {
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn csv_quotes_only_where_needed() {
+        assert_eq!(csv_field("lodash"), "lodash");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+
+        let package = PackageDetails {
+            name: "@scope/a,b".to_string(),
+            version: "1.0.0".to_string(),
+            path: PathBuf::from("packages/a"),
+            ..Default::default()
+        };
+        let formatter = CsvFormatter {
+            dependency_classes: Vec::new(),
+        };
+        let csv = formatter.format_package(&package);
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows[0], CSV_HEADERS.join(","));
+        assert_eq!(rows[1], "\"@scope/a,b\",1.0.0,packages/a,no,yes,no,0,0,0,0");
+    }
+
+    #[test]
+    fn streamed_packages_are_updated_with_what_changed() {
+        let mut stream = NdjsonStream::new(JsonFormatter {
+            list_skipped: false,
+            dependency_usage: false,
+            field_census: false,
+            pretty: false,
+            dependency_classes: Vec::new(),
+            limits: ReportLimits::default(),
+        });
+        let package = |name: &str| PackageDetails {
+            name: name.to_string(),
+            path: PathBuf::from(name),
+            ..Default::default()
+        };
+        let mut streamed = Vec::new();
+        for name in ["a", "b"] {
+            stream.package(&package(name), &mut streamed, &|line| line.to_string());
+        }
+        let streamed = lines(streamed);
+        assert_eq!(streamed.len(), 2);
+        assert_eq!(streamed[0]["type"], "package");
+        assert_eq!(streamed[0]["path"], "a");
+
+        let member = PackageDetails {
+            workspace_member: true,
+            ..package("a")
+        };
+        let mut finished = Vec::new();
+        stream
+            .finish(&results(vec![member, package("b")]), &mut finished)
+            .unwrap();
+        let finished = lines(finished);
+        assert_eq!(finished.len(), 2);
+        assert_eq!(
+            finished[0],
+            json!({ "type": "update", "path": "a", "workspace_member": true })
+        );
+        assert_eq!(finished[1]["type"], "summary");
+    }
+}
@@ -0,0 +1,31 @@
+// caps on how much of a scan a report lists, so a huge tree can't produce a report
+// too large to read or even write. None lists everything
+#[derive(Clone, Copy, Default)]
+pub struct ReportLimits {
+    pub errors: Option<usize>,
+    pub packages: Option<usize>,
+    // entries of any one list inside the report: dependencies, violations, locations
+    pub list_items: Option<usize>,
+}
+
+impl ReportLimits {
+    // caps not given on the command line come from the config file
+    pub fn or(self, fallback: ReportLimits) -> ReportLimits {
+        ReportLimits {
+            errors: self.errors.or(fallback.errors),
+            packages: self.packages.or(fallback.packages),
+            list_items: self.list_items.or(fallback.list_items),
+        }
+    }
+}
+
+// the items a report lists, and how many it leaves out
+pub fn capped<T>(items: &[T], limit: Option<usize>) -> (&[T], usize) {
+    let shown = limit.unwrap_or(items.len()).min(items.len());
+    (&items[..shown], items.len() - shown)
+}
+
+// the line a text report prints in place of what it left out
+pub fn omitted(count: usize, what: &str, flag: &str) -> String {
+    format!("... {} more {} not shown, see {}", count, what, flag)
+}
@@ -0,0 +1,155 @@
+use crate::args::Args;
+use crate::{AnalysisResults, PackageDetails};
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+mod encrypt;
+mod formatters;
+mod limits;
+mod redact;
+mod width;
+mod writer;
+
+pub use encrypt::{check_recipients, encrypt, write_encrypted, Recipient};
+pub use formatters::{
+    describe_change, format_size, CsvFormatter, HtmlFormatter, JsonFormatter, MarkdownFormatter,
+    NdjsonFormatter, NdjsonStream, TableFormatter, TextFormatter,
+};
+pub use limits::ReportLimits;
+pub use redact::Redactor;
+pub use writer::{write_atomic, write_atomic_with, write_output, WriteMode};
+
+pub enum OutputFormat {
+    Text,
+    Json,
+    Ndjson,
+    Table,
+    Html,
+    Markdown,
+    Csv,
+}
+
+impl OutputFormat {
+    pub fn parse(value: &str) -> Result<OutputFormat, String> {
+        match value {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" | "jsonl" => Ok(OutputFormat::Ndjson),
+            "table" => Ok(OutputFormat::Table),
+            "html" => Ok(OutputFormat::Html),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(format!(
+                "Invalid output format '{}', expected text, json, ndjson, table, html, markdown or csv",
+                value
+            )),
+        }
+    }
+}
+
+// "json:report.json" -> (Json, "report.json"), for --also-output
+pub fn parse_output_target(value: &str) -> Result<(OutputFormat, PathBuf), String> {
+    match value.split_once(':') {
+        Some((format, path)) if !path.is_empty() => {
+            Ok((OutputFormat::parse(format)?, PathBuf::from(path)))
+        }
+        _ => Err(format!(
+            "Invalid --also-output '{}', expected format:path like json:report.json",
+            value
+        )),
+    }
+}
+
+// reports are streamed to a writer as they're rendered, so a large one never has to be
+// held in memory whole
+pub trait Formatter {
+    fn format_to(&self, results: &AnalysisResults, out: &mut dyn Write) -> io::Result<()>;
+    // everything known about a single package, for `walker show`
+    fn format_package(&self, package_details: &PackageDetails) -> String;
+
+    // the whole report as a string, for callers that need all of it at once
+    fn format(&self, results: &AnalysisResults) -> String {
+        buffered(|out| self.format_to(results, out))
+    }
+}
+
+// renders into memory, for reports that are needed whole or are never large
+fn buffered(render: impl FnOnce(&mut dyn Write) -> io::Result<()>) -> String {
+    let mut out = Vec::new();
+    render(&mut out).expect("writing to memory can't fail");
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// COLUMNS is kept up to date by most shells, fall back to the traditional 80 columns
+fn terminal_width() -> usize {
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .unwrap_or(80)
+}
+
+fn json_formatter(args: &Args, pretty: bool) -> JsonFormatter {
+    JsonFormatter {
+        list_skipped: args.list_skipped,
+        dependency_usage: args.dependency_usage,
+        field_census: args.field_census,
+        pretty,
+        dependency_classes: args.dependency_classes.clone(),
+        limits: args.report_limits,
+    }
+}
+
+fn formatter_for(format: &OutputFormat, args: &Args, color: bool) -> Box<dyn Formatter> {
+    match format {
+        OutputFormat::Text => Box::new(TextFormatter {
+            group_by: args.group_by,
+            max_name_len: args.max_name_len,
+            list_skipped: args.list_skipped,
+            dependency_usage: args.dependency_usage,
+            field_census: args.field_census,
+            dependency_classes: args.dependency_classes.clone(),
+            limits: args.report_limits,
+        }),
+        OutputFormat::Json => Box::new(json_formatter(args, !args.json_compact)),
+        OutputFormat::Ndjson => Box::new(NdjsonFormatter {
+            json: json_formatter(args, false),
+        }),
+        OutputFormat::Table => Box::new(TableFormatter {
+            color,
+            max_width: terminal_width(),
+            field_census: args.field_census,
+            dependency_classes: args.dependency_classes.clone(),
+            limits: args.report_limits,
+        }),
+        OutputFormat::Html => Box::new(HtmlFormatter {
+            dependency_classes: args.dependency_classes.clone(),
+            limits: args.report_limits,
+        }),
+        OutputFormat::Markdown => Box::new(MarkdownFormatter {
+            dependency_classes: args.dependency_classes.clone(),
+            limits: args.report_limits,
+        }),
+        OutputFormat::Csv => Box::new(CsvFormatter {
+            dependency_classes: args.dependency_classes.clone(),
+        }),
+    }
+}
+
+// the --output ndjson report, for streaming while the tree is walked
+pub fn ndjson_stream(args: &Args) -> NdjsonStream {
+    NdjsonStream::new(json_formatter(args, false))
+}
+
+pub fn create_formatter(args: &Args) -> Box<dyn Formatter> {
+    let color = args.output_file.is_none() && io::stdout().is_terminal();
+    formatter_for(&args.output, args, color)
+}
+
+// one formatter per --also-output, each paired with the file it writes to
+pub fn create_extra_formatters(args: &Args) -> Vec<(Box<dyn Formatter>, &Path)> {
+    args.also_output
+        .iter()
+        .map(|(format, path)| (formatter_for(format, args, false), path.as_path()))
+        .collect()
+}
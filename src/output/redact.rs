@@ -0,0 +1,144 @@
+use std::env;
+
+const HOME_PLACEHOLDER: &str = "~";
+const USER_PLACEHOLDER: &str = "<user>";
+
+fn is_separator(c: char) -> bool {
+    c == '/' || c == '\\'
+}
+
+// a character that can carry on a name or a path component
+fn continues_name(c: char) -> bool {
+    c.is_alphanumeric() || "-_.@".contains(c)
+}
+
+// what identifies whoever ran the scan: their home directory and their username
+pub struct Redactor {
+    home: Option<String>,
+    user: Option<String>,
+}
+
+impl Redactor {
+    pub fn from_env() -> Redactor {
+        let home = env::var("HOME")
+            .or_else(|_| env::var("USERPROFILE"))
+            .ok()
+            .map(|home| home.trim_end_matches(is_separator).to_string())
+            .filter(|home| !home.is_empty());
+        let user = env::var("USER")
+            .or_else(|_| env::var("USERNAME"))
+            .ok()
+            .filter(|user| !user.is_empty());
+        Redactor { home, user }
+    }
+
+    // the home directory becomes "~", then the username is replaced wherever it's a whole
+    // path component, so "/Users/alice/src" and "C:\Users\alice" both lose it but a
+    // package that happens to contain the name doesn't. Only whole components match, with
+    // a home of /home/al "/home/alice" is left alone
+    pub fn redact(&self, text: &str) -> String {
+        let text = match &self.home {
+            Some(home) => replace_where(text, home, HOME_PLACEHOLDER, |before, after| {
+                !before.ends_with(|c| continues_name(c) || is_separator(c))
+                    && !after.starts_with(continues_name)
+            }),
+            None => text.to_string(),
+        };
+        match &self.user {
+            Some(user) => replace_where(&text, user, USER_PLACEHOLDER, |before, after| {
+                before.ends_with(is_separator) && !after.starts_with(continues_name)
+            }),
+            None => text,
+        }
+    }
+
+    // puts back what `redact` took out, for the files walker reads again itself, like
+    // checkpoints and saved results
+    pub fn restore(&self, text: &str) -> String {
+        let text = match &self.user {
+            Some(user) => replace_where(text, USER_PLACEHOLDER, user, |before, _| {
+                before.ends_with(is_separator)
+            }),
+            None => text.to_string(),
+        };
+        match &self.home {
+            Some(home) => replace_where(&text, HOME_PLACEHOLDER, home, |before, after| {
+                !before.ends_with(|c| continues_name(c) || is_separator(c))
+                    && (after.is_empty()
+                        || after.starts_with(is_separator)
+                        || after.starts_with('"'))
+            }),
+            None => text,
+        }
+    }
+}
+
+// replaces `pattern` wherever `keep` agrees, given the text either side of it
+fn replace_where(
+    text: &str,
+    pattern: &str,
+    replacement: &str,
+    keep: impl Fn(&str, &str) -> bool,
+) -> String {
+    let mut replaced = String::with_capacity(text.len());
+    let mut start = 0;
+    while let Some(index) = text[start..].find(pattern) {
+        let index = start + index;
+        let end = index + pattern.len();
+        replaced.push_str(&text[start..index]);
+        match keep(&text[..index], &text[end..]) {
+            true => replaced.push_str(replacement),
+            false => replaced.push_str(pattern),
+        }
+        start = end;
+    }
+    replaced.push_str(&text[start..]);
+    replaced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redactor() -> Redactor {
+        Redactor {
+            home: Some("/home/al".to_string()),
+            user: Some("al".to_string()),
+        }
+    }
+
+    #[test]
+    fn home_is_only_replaced_as_whole_components() {
+        let redactor = redactor();
+        assert_eq!(redactor.redact("/home/al/src/app"), "~/src/app");
+        assert_eq!(redactor.redact("/home/al"), "~");
+        assert_eq!(redactor.redact("\"/home/al\""), "\"~\"");
+        assert_eq!(redactor.redact("/home/alice/x"), "/home/alice/x");
+        assert_eq!(redactor.redact("/mnt/home/al/x"), "/mnt/home/<user>/x");
+        assert_eq!(
+            redactor.redact("read /home/al/a and /home/al/b"),
+            "read ~/a and ~/b"
+        );
+    }
+
+    #[test]
+    fn username_is_only_replaced_as_a_component() {
+        let redactor = redactor();
+        assert_eq!(redactor.redact("/srv/al/x"), "/srv/<user>/x");
+        assert_eq!(redactor.redact("C:\\Users\\al"), "C:\\Users\\<user>");
+        assert_eq!(redactor.redact("/srv/al-utils/x"), "/srv/al-utils/x");
+        assert_eq!(redactor.redact("salt and al"), "salt and al");
+    }
+
+    #[test]
+    fn restore_undoes_redact() {
+        let redactor = redactor();
+        for text in [
+            "{\"path\":\"/home/al/src\",\"root\":\"/home/al\"}",
+            "Unable to read /srv/al/x: denied",
+            "~1.2.3 and /home/alice",
+        ] {
+            assert_eq!(redactor.restore(&redactor.redact(text)), text);
+        }
+    }
+}
@@ -0,0 +1,62 @@
+// terminal column widths without pulling in unicode-width: East Asian wide characters
+// and most emoji take two columns, combining marks and zero width joiners take none
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x0000..=0x001F | 0x007F..=0x009F => 0,
+        0x0300..=0x036F
+        | 0x1AB0..=0x1AFF
+        | 0x1DC0..=0x1DFF
+        | 0x200B..=0x200F
+        | 0x20D0..=0x20FF
+        | 0xFE00..=0xFE0F
+        | 0xFE20..=0xFE2F => 0,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x2FFFD
+        | 0x30000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+pub fn display_width(s: &str) -> usize {
+    s.chars().map(char_width).sum()
+}
+
+// cuts `s` down to at most `max_width` columns, marking the cut with '…'
+pub fn truncate(s: &str, max_width: usize) -> String {
+    if display_width(s) <= max_width {
+        return s.to_string();
+    }
+
+    let mut truncated = String::new();
+    let mut width = 0;
+    for c in s.chars() {
+        let c_width = char_width(c);
+        if width + c_width + 1 > max_width {
+            break;
+        }
+        truncated.push(c);
+        width += c_width;
+    }
+    if max_width > 0 {
+        truncated.push('…');
+    }
+    truncated
+}
+
+// pads `s` with spaces to `width` columns, counting wide characters as two
+pub fn pad(s: &str, width: usize) -> String {
+    let padding = width.saturating_sub(display_width(s));
+    format!("{}{}", s, " ".repeat(padding))
+}
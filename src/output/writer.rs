@@ -0,0 +1,227 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn temp_path(path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.{}.tmp", file_name, process::id()))
+}
+
+fn write_file(path: &Path, write: impl FnOnce(&mut dyn Write) -> io::Result<()>) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write(&mut writer)?;
+    let file = writer.into_inner().map_err(|err| err.into_error())?;
+    file.sync_all()
+}
+
+// writes next to the destination first and renames into place, so a run that's
+// interrupted part way through never leaves a truncated file behind. `write` gets a
+// buffered writer to stream the contents into
+pub fn write_atomic_with(
+    path: &Path,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> io::Result<()> {
+    let temp_path = temp_path(path);
+    let result = write_file(&temp_path, write).and_then(|_| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+pub fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    write_atomic_with(path, |out| out.write_all(contents.as_bytes()))
+}
+
+// how an output file is written when it already exists
+pub enum WriteMode {
+    Replace,
+    Append,
+    // keep a timestamped file per run, and only the given number of them
+    Rotate(usize),
+}
+
+// civil date from days since the epoch, http://howardhinnant.github.io/date_algorithms.html
+fn civil_date(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// formats a unix timestamp as a UTC "2024-01-31"
+pub fn date(secs: u64) -> String {
+    let (year, month, day) = civil_date((secs / 86400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// formats a unix timestamp as a UTC "20240131T235959Z", which sorts chronologically
+fn timestamp(secs: u64) -> String {
+    let (year, month, day) = civil_date((secs / 86400) as i64);
+    let secs_of_day = secs % 86400;
+
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    )
+}
+
+// "report.json" -> ("report-", ".json")
+fn rotation_affixes(path: &Path) -> (String, String) {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    (format!("{}-", stem), extension)
+}
+
+// only the files rotate wrote itself, "report-20240131T235959Z.json", never a
+// "report-summary.json" someone keeps next to them
+fn is_rotated(name: &str, prefix: &str, suffix: &str) -> bool {
+    let stamp = match name
+        .strip_prefix(prefix)
+        .and_then(|rest| rest.strip_suffix(suffix))
+    {
+        Some(stamp) => stamp.as_bytes(),
+        None => return false,
+    };
+    stamp.len() == 16
+        && stamp[8] == b'T'
+        && stamp[15] == b'Z'
+        && stamp[..8]
+            .iter()
+            .chain(&stamp[9..15])
+            .all(u8::is_ascii_digit)
+}
+
+fn rotate(
+    path: &Path,
+    keep: usize,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> io::Result<PathBuf> {
+    let (prefix, suffix) = rotation_affixes(path);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let report_path = path.with_file_name(format!("{}{}{}", prefix, timestamp(now), suffix));
+    write_atomic_with(&report_path, write)?;
+
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut reports: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|report| {
+            report
+                .file_name()
+                .is_some_and(|name| is_rotated(&name.to_string_lossy(), &prefix, &suffix))
+        })
+        .collect();
+    reports.sort();
+
+    let expired = reports.len().saturating_sub(keep);
+    for report in &reports[..expired] {
+        fs::remove_file(report)?;
+    }
+
+    Ok(report_path)
+}
+
+// `write` streams the report into the file, returns the path it ended up in
+pub fn write_output(
+    path: &Path,
+    mode: &WriteMode,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> io::Result<PathBuf> {
+    match mode {
+        WriteMode::Replace => write_atomic_with(path, write).map(|_| path.to_path_buf()),
+        WriteMode::Append => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            let mut writer = BufWriter::new(file);
+            write(&mut writer)?;
+            writer.flush()?;
+            Ok(path.to_path_buf())
+        }
+        WriteMode::Rotate(keep) => rotate(path, *keep, write),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotation_only_matches_its_own_timestamps() {
+        let (prefix, suffix) = rotation_affixes(Path::new("out/report.json"));
+        assert!(is_rotated("report-20240131T235959Z.json", &prefix, &suffix));
+        assert!(!is_rotated("report-summary.json", &prefix, &suffix));
+        assert!(!is_rotated(
+            "report-20240131T235959Z.json.bak",
+            &prefix,
+            &suffix
+        ));
+        assert!(!is_rotated(
+            "report-2024013XT235959Z.json",
+            &prefix,
+            &suffix
+        ));
+        assert!(!is_rotated(
+            "report-20240131T235959Z-old.json",
+            &prefix,
+            &suffix
+        ));
+        assert!(!is_rotated("report.json", &prefix, &suffix));
+    }
+
+    #[test]
+    fn rotation_keeps_unrelated_files() {
+        let dir = std::env::temp_dir().join(format!("walker-rotate-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let summary = dir.join("report-summary.json");
+        fs::write(&summary, "{}").unwrap();
+        for stamp in ["20240101T000000Z", "20240102T000000Z"] {
+            fs::write(dir.join(format!("report-{}.json", stamp)), "{}").unwrap();
+        }
+
+        let written = rotate(&dir.join("report.json"), 1, |out| out.write_all(b"{}")).unwrap();
+        let mut left: Vec<PathBuf> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        left.sort();
+        fs::remove_dir_all(&dir).unwrap();
+        let mut expected = vec![summary, written];
+        expected.sort();
+        assert_eq!(left, expected);
+    }
+
+    #[test]
+    fn timestamps_sort_chronologically() {
+        assert_eq!(timestamp(0), "19700101T000000Z");
+        assert_eq!(timestamp(1706745599), "20240131T235959Z");
+        assert_eq!(date(951782400), "2000-02-29");
+    }
+}
@@ -0,0 +1,122 @@
+use crate::glob::{gitignore_glob, glob_match};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub struct OwnerRule {
+    pattern: String,
+    owner: String,
+}
+
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote) {
+            return &value[1..value.len() - 1];
+        }
+    }
+    value
+}
+
+fn parse_yaml_line(line: &str) -> Option<OwnerRule> {
+    // the key may be quoted, and a quoted key can contain ':'
+    let (pattern, owner) = match line.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let end = line[1..].find(quote)? + 1;
+            let rest = line[end + 1..].trim_start().strip_prefix(':')?;
+            (&line[1..end], rest)
+        }
+        _ => line.split_once(':')?,
+    };
+
+    let owner = unquote(owner);
+    if pattern.trim().is_empty() || owner.is_empty() {
+        return None;
+    }
+
+    Some(OwnerRule {
+        pattern: pattern.trim().to_string(),
+        owner: owner.to_string(),
+    })
+}
+
+// CODEOWNERS uses gitignore style patterns, a trailing '/' owns the whole directory
+fn parse_codeowners_line(line: &str) -> Option<OwnerRule> {
+    let mut parts = line.split_whitespace();
+    let pattern = parts.next()?;
+    let owners: Vec<&str> = parts.collect();
+
+    let mut glob = gitignore_glob(pattern.trim_end_matches('/'));
+    if pattern.ends_with('/') {
+        glob.push_str("/**");
+    }
+
+    Some(OwnerRule {
+        pattern: glob,
+        owner: owners.join(" "),
+    })
+}
+
+// the owners file is either a flat YAML mapping of path glob to team name
+//
+//   "packages/ui/**": design-system
+//   apps/*: web-platform
+//
+// or a file named CODEOWNERS in the usual `pattern @owner...` format
+pub fn load_owners(path: &Path) -> io::Result<Vec<OwnerRule>> {
+    let contents = fs::read_to_string(path)?;
+    let codeowners = path.file_name().is_some_and(|name| name == "CODEOWNERS");
+
+    let mut rules = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let rule = match codeowners {
+            true => parse_codeowners_line(line),
+            false => parse_yaml_line(line),
+        };
+        match rule {
+            Some(rule) => rules.push(rule),
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "{}:{}: expected 'pattern: team', got '{}'",
+                        path.display(),
+                        index + 1,
+                        line
+                    ),
+                ))
+            }
+        }
+    }
+
+    Ok(rules)
+}
+
+fn rule_matches(rule: &OwnerRule, relative_path: &str) -> bool {
+    // a rule that matches a directory also owns everything below it
+    let mut path = relative_path;
+    loop {
+        if glob_match(&rule.pattern, path) || glob_match(&rule.pattern, &format!("{}/", path)) {
+            return true;
+        }
+        match path.rsplit_once('/') {
+            Some((parent, _)) => path = parent,
+            None => return false,
+        }
+    }
+}
+
+// later rules take precedence over earlier ones, as in CODEOWNERS
+pub fn find_owner<'a>(rules: &'a [OwnerRule], relative_path: &str) -> Option<&'a str> {
+    rules
+        .iter()
+        .rev()
+        .find(|rule| rule_matches(rule, relative_path))
+        .map(|rule| rule.owner.as_str())
+        .filter(|owner| !owner.is_empty())
+}
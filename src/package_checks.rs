@@ -0,0 +1,82 @@
+use crate::filesystem::FileSystem;
+use crate::Finding;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const LOCKFILES: [&str; 5] = [
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lockb",
+];
+
+// 'main', 'module', 'types' and every 'exports' target that points at a file that isn't
+// there. Subpath patterns ("./*") can't be checked without expanding them and are skipped
+pub fn check_entrypoints(
+    filesystem: &dyn FileSystem,
+    v: &Value,
+    package_dir: &Path,
+    entrypoints: &BTreeMap<String, BTreeMap<String, String>>,
+) -> Vec<Finding> {
+    let fields = ["main", "module", "types", "typings"]
+        .into_iter()
+        .filter_map(|field| Some((format!("'{}'", field), v[field].as_str()?)));
+    let exports = entrypoints.iter().flat_map(|(subpath, conditions)| {
+        conditions.iter().map(move |(condition, target)| {
+            (
+                format!("exports[\"{}\"].{}", subpath, condition),
+                target.as_str(),
+            )
+        })
+    });
+
+    fields
+        .chain(exports)
+        .filter(|(_, target)| !target.contains('*'))
+        .filter(|(_, target)| !filesystem.is_file(&package_dir.join(target)))
+        .map(|(field, target)| Finding {
+            rule: "broken-entrypoint".into(),
+            subject: field.to_string(),
+            message: format!("{} points at {}, which doesn't exist", field, target),
+        })
+        .collect()
+}
+
+// a package built from TypeScript that doesn't publish its declarations leaves its
+// consumers with `any`
+pub fn check_missing_types(
+    filesystem: &dyn FileSystem,
+    v: &Value,
+    package_dir: &Path,
+    has_types: bool,
+) -> Vec<Finding> {
+    let typescript = filesystem.is_file(&package_dir.join("tsconfig.json"))
+        || !v["devDependencies"]["typescript"].is_null()
+        || !v["dependencies"]["typescript"].is_null();
+    match typescript && !has_types {
+        true => vec![Finding {
+            rule: "missing-types".into(),
+            subject: String::new(),
+            message: "built with TypeScript but doesn't publish 'types'".to_string(),
+        }],
+        false => Vec::new(),
+    }
+}
+
+// lockfiles side by side drift apart, and which one wins depends on who ran the install
+pub fn check_lockfiles(filesystem: &dyn FileSystem, package_dir: &Path) -> Vec<Finding> {
+    let lockfiles: Vec<&str> = LOCKFILES
+        .into_iter()
+        .filter(|lockfile| filesystem.is_file(&package_dir.join(lockfile)))
+        .collect();
+    match lockfiles.len() > 1 {
+        true => vec![Finding {
+            rule: "mixed-lockfiles".into(),
+            subject: String::new(),
+            message: format!("has more than one lockfile: {}", lockfiles.join(", ")),
+        }],
+        false => Vec::new(),
+    }
+}
@@ -0,0 +1,93 @@
+use crate::{AnalysisResults, PackageDetails};
+use std::cmp::Ordering;
+
+#[derive(Clone, Copy)]
+pub enum SortField {
+    Name,
+    Path,
+    Size,
+    Findings,
+    Errors,
+}
+
+// what a page is ordered by, "-size" for largest first
+#[derive(Clone, Copy)]
+pub struct SortKey {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+impl SortKey {
+    pub fn parse(value: &str) -> Result<SortKey, String> {
+        let (descending, name) = match value.strip_prefix('-') {
+            Some(name) => (true, name),
+            None => (false, value),
+        };
+        let field = match name {
+            "name" => SortField::Name,
+            "path" => SortField::Path,
+            "size" => SortField::Size,
+            "findings" => SortField::Findings,
+            "errors" => SortField::Errors,
+            _ => {
+                return Err(format!(
+                    "Invalid sort key '{}', expected name, path, size, findings or errors, with - in front for descending",
+                    value
+                ))
+            }
+        };
+        Ok(SortKey { field, descending })
+    }
+
+    fn compare(&self, a: &PackageDetails, b: &PackageDetails) -> Ordering {
+        let order = match self.field {
+            SortField::Name => a.name.cmp(&b.name),
+            SortField::Path => a.path.cmp(&b.path),
+            SortField::Size => a.size.cmp(&b.size),
+            SortField::Findings => a.findings.len().cmp(&b.findings.len()),
+            SortField::Errors => a.errors.len().cmp(&b.errors.len()),
+        };
+        match self.descending {
+            true => order.reverse(),
+            false => order,
+        }
+    }
+}
+
+pub struct Page<'a> {
+    // the packages on the page with where they are in the results, borrowed not cloned
+    pub packages: Vec<(usize, &'a PackageDetails)>,
+    // how many packages the filter kept, across every page
+    pub matched: usize,
+}
+
+impl AnalysisResults {
+    // one page of the packages `filter` keeps, ordered by `sort` and otherwise in the order
+    // they were found, so a UI can page through a large scan without copying it
+    pub fn page(
+        &self,
+        filter: impl Fn(&PackageDetails) -> bool,
+        sort: Option<SortKey>,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Page<'_> {
+        let mut packages: Vec<(usize, &PackageDetails)> = self
+            .packages
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| filter(p))
+            .collect();
+        if let Some(sort) = sort {
+            packages.sort_by(|(_, a), (_, b)| sort.compare(a, b));
+        }
+        let matched = packages.len();
+        Page {
+            packages: packages
+                .into_iter()
+                .skip(offset)
+                .take(limit.unwrap_or(usize::MAX))
+                .collect(),
+            matched,
+        }
+    }
+}
@@ -0,0 +1,672 @@
+use crate::acks::{self, Ack};
+use crate::args::Args;
+use crate::baseline;
+use crate::checkpoint::{self, Checkpoint, Restored};
+use crate::dependency_export;
+use crate::error_context::ContextError;
+use crate::exit_summary;
+use crate::filters::Filters;
+use crate::graph;
+use crate::identity::{self, Comparison};
+use crate::interop;
+use crate::layers::{self, Layer};
+use crate::output::{NdjsonStream, OutputFormat, Redactor};
+use crate::owners::{self, OwnerRule};
+use crate::progress::{self, ProgressFormat};
+use crate::protocols;
+use crate::rules::{self, RuleSetting};
+use crate::sampling::{self, Sampler};
+use crate::tags::{self, PathTag};
+use crate::vcs;
+use crate::workspaces;
+use crate::{
+    dir_handler, location_statistics, output, run_manifest, walk_dirs, AnalysisOptions,
+    AnalysisResults, CacheStatistics, PackageDetails, PerformanceMetrics, Scan, SkipReason,
+    WalkStatistics,
+};
+use ansi_term::Colour::Red;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, IsTerminal, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// a run is made of these stages, in order:
+//
+//   discover -> analyze -> aggregate -> format -> write
+//
+// main() only sets up their inputs and calls them one after the other, so any of them
+// can be swapped out without touching the others
+
+fn phase_started(args: &Args, phase: &str, total: Option<usize>, message: &str) {
+    if args.progress_format == Some(ProgressFormat::Json) {
+        progress::emit_event(phase, 0, total, message);
+    }
+}
+
+// walks the tree from `root`, parsing every package found along the way and handing it
+// to `on_package` once it's analyzed. A checkpoint left by an earlier run brings back
+// what it found, and the walk skips the directories it finished
+pub fn discover<'a>(
+    root: &Path,
+    args: &Args,
+    filters: Filters,
+    options: &AnalysisOptions<'a>,
+    checkpoint: Option<(Checkpoint, Option<Restored>)>,
+    on_package: Option<&'a dyn Fn(&PackageDetails)>,
+) -> (Scan<'a>, io::Result<()>) {
+    let mut scan = Scan {
+        filesystem: options.filesystem,
+        deadline: args.timeout.map(|timeout| Instant::now() + timeout),
+        package_timeout: args.package_timeout,
+        package_deadline: None,
+        nested_package_time: Duration::ZERO,
+        packages: Vec::new(),
+        errors: Vec::new(),
+        metrics: PerformanceMetrics {
+            started: Instant::now(),
+            package_timings: Vec::new(),
+            manifest_cache: CacheStatistics::default(),
+        },
+        walk: WalkStatistics::default(),
+        visited: HashMap::new(),
+        aliases: HashMap::new(),
+        root: root.to_path_buf(),
+        filters,
+        skip_submodules: args.skip_submodules,
+        submodules: HashSet::new(),
+        nested_repo: None,
+        one_file_system: args.one_file_system,
+        max_dir_size: args.skip_dirs_larger_than,
+        dir_sizes: HashMap::new(),
+        modified_since: args
+            .modified_since
+            .and_then(|age| SystemTime::now().checked_sub(age)),
+        root_device: None,
+        max_errors: args.max_errors,
+        warning_count: 0,
+        error_count: 0,
+        // the running count is only drawn on a terminal unless asked for
+        progress: match args.progress_format {
+            Some(progress_format) => Some(progress_format),
+            None if io::stderr().is_terminal() => Some(ProgressFormat::Text),
+            None => None,
+        },
+        sampler: args.sample.map(|mode| Sampler::new(mode, args.seed)),
+        graph: None,
+        checkpoint: None,
+        on_package,
+    };
+    let mut resumed = false;
+    if let Some((checkpoint, restored)) = checkpoint {
+        if let Some(restored) = restored {
+            resumed = true;
+            scan.warning_count = restored.packages.iter().map(|p| p.findings.len()).sum();
+            scan.error_count = restored.errors.len()
+                + restored
+                    .packages
+                    .iter()
+                    .map(|p| p.errors.len())
+                    .sum::<usize>();
+            if let Some(on_package) = on_package {
+                restored.packages.iter().for_each(on_package);
+            }
+            scan.packages = restored.packages;
+            scan.errors = restored.errors;
+            scan.walk = restored.walk;
+        }
+        scan.checkpoint = Some(checkpoint);
+    }
+
+    phase_started(args, "discover", None, "walking the tree");
+    let walk_result = walk_dirs(
+        &root.to_path_buf(),
+        &|path, metadata| dir_handler(path, metadata, options),
+        &mut scan,
+    );
+    // --watch keeps the cache, its counts are the ones of this walk
+    scan.metrics.manifest_cache = mem::take(&mut options.manifests.borrow_mut().statistics);
+    if scan.progress == Some(ProgressFormat::Text) && scan.warning_count + scan.error_count > 0 {
+        eprintln!();
+    }
+    // errors from directories the earlier run was still in come up again
+    if resumed {
+        let mut seen = HashSet::new();
+        scan.errors.retain(|error| seen.insert(error.clone()));
+    }
+    match walk_result {
+        Ok(()) => checkpoint::finish(&mut scan),
+        Err(_) => checkpoint::save(&mut scan, true),
+    }
+    (scan, walk_result)
+}
+
+fn relative_to(path: &Path, root: &Path) -> PathBuf {
+    match path.strip_prefix(root) {
+        Ok(relative) if relative.as_os_str().is_empty() => PathBuf::from("."),
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+// rewrites every reported path relative to the scan root, so output from different
+// checkouts of the same repository can be compared
+fn localize_paths(
+    packages: &mut [PackageDetails],
+    errors: &mut [ContextError],
+    skipped: &mut [(PathBuf, SkipReason)],
+    root: &Path,
+) {
+    for package_details in packages {
+        localize_package(package_details, root);
+    }
+    let root_prefix = format!("{}/", root.display());
+    for error in errors {
+        error.replace(&root_prefix, "");
+    }
+    for (path, _) in skipped {
+        *path = relative_to(path, root);
+    }
+}
+
+fn localize_package(package_details: &mut PackageDetails, root: &Path) {
+    let relative_path = relative_to(&package_details.path, root);
+    // packages without a name are reported by their directory
+    if package_details.name == package_details.path.display().to_string() {
+        package_details.name = relative_path.display().to_string();
+    }
+    package_details.path = relative_path;
+    for alias in &mut package_details.aliases {
+        *alias = relative_to(alias, root);
+    }
+    if let Some(duplicate_of) = &mut package_details.duplicate_of {
+        *duplicate_of = relative_to(duplicate_of, root);
+    }
+    for (dependency, _) in &mut package_details.scan_dependencies {
+        *dependency = relative_to(dependency, root);
+    }
+    if let Some(workspace_root) = &mut package_details.workspace_root {
+        *workspace_root = relative_to(workspace_root, root);
+    }
+    let root_prefix = format!("{}/", root.display());
+    for error in &mut package_details.errors {
+        error.replace(&root_prefix, "");
+    }
+}
+
+// attaches what's only known once the whole tree has been walked: aliases, owners,
+// dependencies between packages, and the paths packages are reported under
+pub fn analyze(
+    scan: &mut Scan,
+    root: &Path,
+    owner_rules: &[OwnerRule],
+    layers: &[Layer],
+    path_tags: &[PathTag],
+    rules: &[RuleSetting],
+    args: &Args,
+) {
+    phase_started(
+        args,
+        "analyze",
+        Some(scan.packages.len()),
+        "resolving aliases, owners and paths",
+    );
+    for package_details in &mut scan.packages {
+        if let Some(aliases) = scan.aliases.remove(&package_details.path) {
+            package_details.aliases = aliases;
+        }
+
+        let relative_path = package_details
+            .path
+            .strip_prefix(root)
+            .unwrap_or(&package_details.path)
+            .to_string_lossy()
+            .into_owned();
+        package_details.owner = owners::find_owner(owner_rules, &relative_path).map(String::from);
+        // before layers, which can be picked by tag
+        for tag in tags::path_tags(path_tags, &relative_path) {
+            if !package_details.tags.iter().any(|existing| existing == tag) {
+                package_details.tags.push(tag.to_string());
+            }
+        }
+        package_details.layer =
+            layers::find_layer(layers, &relative_path, &package_details.tags).map(String::from);
+    }
+
+    workspaces::assign_members(&mut scan.packages);
+    interop::find_duplicate_names(&mut scan.packages, root);
+    interop::find_cjs_only_dependencies(&mut scan.packages, root);
+    let analyses = graph::analyses(layers);
+    let names: Vec<&str> = analyses.iter().map(|analysis| analysis.name()).collect();
+    phase_started(
+        args,
+        "graph",
+        Some(analyses.len()),
+        &format!("running graph analyses: {}", names.join(", ")),
+    );
+    scan.graph = Some(graph::run_analyses(&analyses, &mut scan.packages, root));
+    let repository = vcs::find_repository(root);
+    let repo_root = repository
+        .as_ref()
+        .map_or_else(|| root.to_path_buf(), |vcs| vcs.root().to_path_buf());
+    protocols::find_links_outside(&mut scan.packages, &repo_root);
+    rules::apply(&mut scan.packages, rules);
+
+    if let Some(vcs) = repository.as_deref().filter(|_| args.git_history) {
+        phase_started(
+            args,
+            "history",
+            Some(scan.packages.len()),
+            &format!("reading package history from {}", vcs.name()),
+        );
+        let since = SystemTime::now()
+            .checked_sub(args.churn_window)
+            .and_then(|since| since.duration_since(UNIX_EPOCH).ok())
+            .map(|since| since.as_secs())
+            .unwrap_or(0);
+        let dirs: Vec<&Path> = scan.packages.iter().map(|p| p.path.as_path()).collect();
+        match vcs::package_histories(vcs, &dirs, since) {
+            Ok(histories) => {
+                for (package_details, history) in scan.packages.iter_mut().zip(histories) {
+                    package_details.history = history;
+                }
+            }
+            Err(err) => scan
+                .errors
+                .push(format!("Unable to read {} history: {}", vcs.name(), err).into()),
+        }
+    } else if args.git_history {
+        scan.errors.push(
+            "Unable to read package history: not inside a git, Mercurial or Sapling repository"
+                .into(),
+        );
+    }
+
+    let relative_paths = args.relative_paths.unwrap_or(repository.is_some());
+    if relative_paths {
+        localize_paths(
+            &mut scan.packages,
+            &mut scan.errors,
+            &mut scan.walk.skipped,
+            root,
+        );
+    }
+}
+
+// moves the packages and errors out of the scan, what's left of it is only needed for
+// metrics and warnings
+pub fn aggregate(scan: &mut Scan, walk_result: &io::Result<()>, args: &Args) -> AnalysisResults {
+    let mut results = AnalysisResults {
+        packages: mem::take(&mut scan.packages),
+        errors: mem::take(&mut scan.errors),
+        timed_out: matches!(walk_result, Err(err) if err.kind() == io::ErrorKind::TimedOut),
+        error_limit_reached: matches!(
+            walk_result,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted
+        ),
+        sample: None,
+        breakdown: None,
+        walk: mem::take(&mut scan.walk),
+        graph: scan.graph.take(),
+        comparison: None,
+    };
+    if let Some(sampler) = &scan.sampler {
+        results.sample = sampling::summarize(sampler, &results.packages);
+    }
+    if let Some(breakdown) = args.breakdown {
+        let statistics = location_statistics(&results.packages, &scan.root, breakdown);
+        results.breakdown = Some((breakdown, statistics));
+    }
+    results
+}
+
+// packages are matched to the earlier run's by identity rather than path, so the ones
+// that moved aren't counted as removed and added again
+pub fn compare(
+    results: &mut AnalysisResults,
+    previous: &AnalysisResults,
+    previous_path: &Path,
+    args: &Args,
+) {
+    results.comparison = Some(Comparison {
+        compared_with: previous_path.to_path_buf(),
+        changes: identity::compare(&previous.packages, &results.packages),
+        regressions: baseline::regressions(
+            &previous.packages,
+            &results.packages,
+            &args.dependency_classes,
+            args.size_growth_threshold,
+        ),
+    });
+}
+
+// findings accepted in the acknowledgements file are listed apart and don't count
+pub fn acknowledge(results: &mut AnalysisResults, acks: &mut [Ack]) {
+    acks::apply_acks(acks, &mut results.packages);
+}
+
+// narrows the report down to the packages asked for
+pub fn select(results: &mut AnalysisResults, args: &Args) {
+    if let Some(portability) = args.portability {
+        results
+            .packages
+            .retain(|p| p.edge_portability == portability);
+    }
+    if !args.tags.is_empty() {
+        results
+            .packages
+            .retain(|p| p.tags.iter().any(|tag| args.tags.contains(tag)));
+    }
+}
+
+// with --output ndjson to stdout the package lines go out during the walk, rather than
+// once the run is over. Not when the report is encrypted, which takes all of it at once,
+// or narrowed by --tag, as path tags are only known once the whole tree is. Nor when
+// sampling, as a package already written may be evicted from the sample later
+pub fn ndjson_stream(args: &Args) -> Option<RefCell<NdjsonStream>> {
+    let streams = matches!(args.output, OutputFormat::Ndjson)
+        && args.output_file.is_none()
+        && args.encrypt_to.is_empty()
+        && args.tags.is_empty()
+        && args.sample.is_none()
+        && args.load_results.is_none()
+        && args.query.is_none()
+        && args.diff.is_none()
+        && args.show.is_none()
+        && args.graph.is_none()
+        && !args.doctor
+        && !args.watch;
+    streams.then(|| RefCell::new(output::ndjson_stream(args)))
+}
+
+// writes each package's line to stdout as the walk analyzes it, with its paths as the
+// report will have them. What only the whole tree decides comes in the update lines
+// `write` adds at the end
+pub fn package_streamer<'a>(
+    stream: &'a RefCell<NdjsonStream>,
+    root: &'a Path,
+    args: &'a Args,
+) -> impl Fn(&PackageDetails) + 'a {
+    let relative_paths = args
+        .relative_paths
+        .unwrap_or_else(|| vcs::find_repository(root).is_some());
+    let redactor = args.redact.then(Redactor::from_env);
+    move |package_details| {
+        if args
+            .portability
+            .is_some_and(|portability| package_details.edge_portability != portability)
+        {
+            return;
+        }
+        let mut package_details = package_details.clone();
+        if relative_paths {
+            localize_package(&mut package_details, root);
+        }
+        let redact = |line: &str| match &redactor {
+            Some(redactor) => redactor.redact(line),
+            None => line.to_string(),
+        };
+        stream
+            .borrow_mut()
+            .package(&package_details, &mut io::stdout().lock(), &redact);
+    }
+}
+
+pub fn format(results: &AnalysisResults, args: &Args) -> String {
+    phase_started(
+        args,
+        "format",
+        Some(results.packages.len()),
+        "formatting the report",
+    );
+    let formatter = output::create_formatter(args);
+    formatter.format(results)
+}
+
+// finds a package by name, or by its path or one of its aliases relative to `root`
+fn find_package<'a>(
+    results: &'a AnalysisResults,
+    root: &Path,
+    target: &str,
+) -> Result<&'a PackageDetails, String> {
+    let target_path = Path::new(target.trim_end_matches('/'));
+    let target_path = target_path.strip_prefix(root).unwrap_or(target_path);
+    let target_path = target_path.strip_prefix(".").unwrap_or(target_path);
+    let is_target = |path: &Path| {
+        let path = path.strip_prefix(root).unwrap_or(path);
+        path == target_path || (path == Path::new(".") && target_path.as_os_str().is_empty())
+    };
+
+    let by_path = results
+        .packages
+        .iter()
+        .find(|p| is_target(&p.path) || p.aliases.iter().any(|alias| is_target(alias)));
+    if let Some(package_details) = by_path {
+        return Ok(package_details);
+    }
+
+    let by_name: Vec<&PackageDetails> = results
+        .packages
+        .iter()
+        .filter(|p| p.name == target)
+        .collect();
+    match by_name.as_slice() {
+        [package_details] => Ok(package_details),
+        [] => Err(format!("No package named or found at '{}'", target)),
+        _ => {
+            let paths: Vec<String> = by_name
+                .iter()
+                .map(|p| p.path.display().to_string())
+                .collect();
+            Err(format!(
+                "'{}' matches several packages, pass one of their paths instead: {}",
+                target,
+                paths.join(", ")
+            ))
+        }
+    }
+}
+
+pub fn show(
+    results: &AnalysisResults,
+    root: &Path,
+    target: &str,
+    args: &Args,
+) -> Result<String, String> {
+    let package_details = find_package(results, root, target)?;
+    Ok(output::create_formatter(args).format_package(package_details))
+}
+
+// what `write` puts out: text rendered already, the report in the --output format,
+// rendered as it's written, or the rest of an NDJSON report streamed during the walk
+pub enum Output<'a> {
+    Text(String),
+    Report,
+    Streamed(&'a RefCell<NdjsonStream>),
+}
+
+// prints or saves the report and any extra formats, then writes the run manifest and
+// results file if they were asked for
+pub fn write(results: &AnalysisResults, output: Output, root: &Path, args: &Args) {
+    if let Output::Report | Output::Streamed(_) = output {
+        phase_started(
+            args,
+            "format",
+            Some(results.packages.len()),
+            "formatting the report",
+        );
+    }
+    phase_started(
+        args,
+        "write",
+        Some(results.packages.len()),
+        "writing the report",
+    );
+    let redactor = args.redact.then(Redactor::from_env);
+    let redact = |text: &str| match &redactor {
+        Some(redactor) => redactor.redact(text),
+        None => text.to_string(),
+    };
+
+    // everything leaving walker is redacted first, then encrypted
+    let seal = |text: &str| output::encrypt(&redact(text), &args.encrypt_to);
+    let buffer = redactor.is_some() || !args.encrypt_to.is_empty();
+
+    let render = |out: &mut dyn Write| match &output {
+        Output::Text(text) => out.write_all(text.as_bytes()),
+        Output::Report => output::create_formatter(args).format_to(results, out),
+        Output::Streamed(stream) => stream.borrow_mut().finish(results, out),
+    };
+    // redacting and encrypting need the whole text, otherwise the report streams straight
+    // to stdout or the output file
+    let sealed = match buffer {
+        true => {
+            let mut rendered = Vec::new();
+            match render(&mut rendered).and_then(|_| seal(&String::from_utf8_lossy(&rendered))) {
+                Ok(sealed) => Some(sealed),
+                Err(err) => {
+                    let action = match args.encrypt_to.is_empty() {
+                        true => "write",
+                        false => "encrypt",
+                    };
+                    eprintln!(
+                        "{}",
+                        Red.paint(format!("Unable to {} the report: {}", action, err))
+                    );
+                    exit_summary::exit(args, 1, "write-failed", Some(results));
+                }
+            }
+        }
+        false => None,
+    };
+    let write_report = |out: &mut dyn Write| match &sealed {
+        Some(sealed) => out.write_all(sealed.as_bytes()),
+        None => render(out),
+    };
+    let written = match &args.output_file {
+        Some(output_file) => output::write_output(output_file, &args.output_mode, write_report)
+            .map(|_| ())
+            .map_err(|err| {
+                format!(
+                    "Unable to write output file {}: {}",
+                    output_file.display(),
+                    err
+                )
+            }),
+        None => {
+            let mut out = io::BufWriter::new(io::stdout().lock());
+            // stdout closing early, like `walker | head`, isn't a failure
+            match write_report(&mut out).and_then(|_| out.flush()) {
+                Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                written => written.map_err(|err| format!("Unable to write the report: {}", err)),
+            }
+        }
+    };
+    if let Err(message) = written {
+        eprintln!("{}", Red.paint(message));
+        exit_summary::exit(args, 1, "write-failed", Some(results));
+    }
+
+    // every extra format is rendered from the same results, nothing is analyzed twice
+    for (formatter, path) in output::create_extra_formatters(args) {
+        let written = match buffer {
+            true => seal(&formatter.format(results))
+                .and_then(|sealed| output::write_atomic(path, &sealed)),
+            false => output::write_atomic_with(path, |out| formatter.format_to(results, out)),
+        };
+        if let Err(err) = written {
+            eprintln!(
+                "{}",
+                Red.paint(format!(
+                    "Unable to write output file {}: {}",
+                    path.display(),
+                    err
+                ))
+            );
+            exit_summary::exit(args, 1, "write-failed", Some(results));
+        }
+    }
+
+    if let Some((format, path)) = &args.emit_dependencies {
+        let render = |out: &mut dyn Write| {
+            dependency_export::render_dependencies(&results.packages, *format, out)
+        };
+        let written = match buffer {
+            true => {
+                let mut rendered = Vec::new();
+                render(&mut rendered)
+                    .and_then(|_| seal(&String::from_utf8_lossy(&rendered)))
+                    .and_then(|sealed| output::write_atomic(path, &sealed))
+            }
+            false => output::write_atomic_with(path, render),
+        };
+        if let Err(err) = written {
+            eprintln!(
+                "{}",
+                Red.paint(format!(
+                    "Unable to write dependencies {}: {}",
+                    path.display(),
+                    err
+                ))
+            );
+            exit_summary::exit(args, 1, "write-failed", Some(results));
+        }
+    }
+
+    if let Some(manifest_path) = &args.run_manifest {
+        if let Err(err) = run_manifest::write_run_manifest(
+            manifest_path,
+            root,
+            &results.packages,
+            results.partial(),
+            args,
+        ) {
+            eprintln!(
+                "{}",
+                Red.paint(format!(
+                    "Unable to write run manifest {}: {}",
+                    manifest_path.display(),
+                    err
+                ))
+            );
+        }
+    }
+
+    if let Some(results_path) = &args.save_results {
+        if let Err(err) = results.save(results_path, args) {
+            eprintln!(
+                "{}",
+                Red.paint(format!(
+                    "Unable to save results {}: {}",
+                    results_path.display(),
+                    err
+                ))
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args;
+
+    fn parse(arguments: &[&str]) -> Args {
+        args::parse_args_from(arguments.iter().map(|argument| argument.to_string())).unwrap()
+    }
+
+    #[test]
+    fn ndjson_streams_to_stdout() {
+        assert!(ndjson_stream(&parse(&["--output", "ndjson"])).is_some());
+        assert!(ndjson_stream(&parse(&["--output", "json"])).is_none());
+    }
+
+    #[test]
+    fn ndjson_waits_for_the_sample() {
+        let sampled = parse(&["--output", "ndjson", "--sample-count", "5"]);
+        assert!(ndjson_stream(&sampled).is_none());
+        let sampled = parse(&["--output", "ndjson", "--sample", "10%"]);
+        assert!(ndjson_stream(&sampled).is_none());
+    }
+}
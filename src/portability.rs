@@ -0,0 +1,121 @@
+use crate::PackageDetails;
+use std::collections::BTreeMap;
+
+// builtins edge runtimes (Cloudflare workers, Vercel edge, Deno deploy) offer through
+// their node compatibility layers, or that bundlers polyfill
+const SHIMMABLE_BUILTINS: [&str; 14] = [
+    "assert",
+    "async_hooks",
+    "buffer",
+    "crypto",
+    "diagnostics_channel",
+    "events",
+    "path",
+    "process",
+    "querystring",
+    "stream",
+    "string_decoder",
+    "timers",
+    "url",
+    "util",
+];
+
+// export conditions that give edge runtimes an entrypoint of their own
+const EDGE_CONDITIONS: [&str; 4] = ["worker", "workerd", "edge-light", "edge-routine"];
+
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum EdgePortability {
+    #[default]
+    Portable,
+    NeedsShims,
+    NotPortable,
+}
+
+impl EdgePortability {
+    pub const ALL: [EdgePortability; 3] = [
+        EdgePortability::Portable,
+        EdgePortability::NeedsShims,
+        EdgePortability::NotPortable,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            EdgePortability::Portable => "portable",
+            EdgePortability::NeedsShims => "needs-shims",
+            EdgePortability::NotPortable => "not-portable",
+        }
+    }
+
+    pub fn parse(label: &str) -> Option<EdgePortability> {
+        EdgePortability::ALL
+            .into_iter()
+            .find(|portability| portability.label() == label)
+    }
+}
+
+// whether the package can run on an edge runtime, and why not. Builtin imports are only
+// known when sources were scanned, without them a package can look more portable than
+// it is
+pub fn classify(
+    package_details: &PackageDetails,
+    sources_scanned: bool,
+) -> (EdgePortability, Vec<String>) {
+    let mut reasons = Vec::new();
+    if !package_details.native_addon.is_empty() {
+        reasons.push(format!(
+            "native addon ({})",
+            package_details.native_addon.join(", ")
+        ));
+        return (EdgePortability::NotPortable, reasons);
+    }
+
+    let edge_conditions: Vec<&str> = package_details
+        .entrypoints
+        .values()
+        .flat_map(|conditions| conditions.keys())
+        .flat_map(|condition| condition.split('+'))
+        .filter(|condition| EDGE_CONDITIONS.contains(condition))
+        .collect();
+    if let Some(condition) = edge_conditions.first() {
+        reasons.push(format!("exports a '{}' entrypoint", condition));
+        return (EdgePortability::Portable, reasons);
+    }
+
+    let (shimmable, missing): (Vec<&str>, Vec<&str>) = package_details
+        .node_builtins
+        .iter()
+        .map(String::as_str)
+        .partition(|builtin| SHIMMABLE_BUILTINS.contains(builtin));
+    if !missing.is_empty() {
+        reasons.push(format!(
+            "imports {}, which edge runtimes don't provide",
+            missing.join(", ")
+        ));
+    }
+    if !shimmable.is_empty() {
+        reasons.push(format!(
+            "imports {}, which need node compatibility shims",
+            shimmable.join(", ")
+        ));
+    }
+    if !sources_scanned {
+        reasons.push("sources not scanned, builtin imports unknown".to_string());
+    }
+
+    let portability = match (missing.is_empty(), shimmable.is_empty()) {
+        (false, _) => EdgePortability::NotPortable,
+        (true, false) => EdgePortability::NeedsShims,
+        (true, true) => EdgePortability::Portable,
+    };
+    (portability, reasons)
+}
+
+pub fn portability_counts(packages: &[PackageDetails]) -> BTreeMap<&'static str, usize> {
+    let mut counts = BTreeMap::new();
+    for package_details in packages {
+        *counts
+            .entry(package_details.edge_portability.label())
+            .or_insert(0) += 1;
+    }
+    counts
+}
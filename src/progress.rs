@@ -0,0 +1,39 @@
+use serde_json::json;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ProgressFormat {
+    Text,
+    Json,
+}
+
+impl ProgressFormat {
+    pub fn parse(value: &str) -> Result<ProgressFormat, String> {
+        match value {
+            "text" => Ok(ProgressFormat::Text),
+            "json" => Ok(ProgressFormat::Json),
+            _ => Err(format!(
+                "Invalid progress format '{}', expected text or json",
+                value
+            )),
+        }
+    }
+}
+
+// one JSON object per line on stderr, for tools that draw their own progress. `total`
+// is null while it isn't known yet, e.g. during discovery
+pub fn emit_event(phase: &str, current: usize, total: Option<usize>, message: &str) {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0);
+
+    let event = json!({
+        "phase": phase,
+        "current": current,
+        "total": total,
+        "message": message,
+        "timestamp": timestamp,
+    });
+    eprintln!("{}", serde_json::to_string(&event).unwrap());
+}
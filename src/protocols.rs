@@ -0,0 +1,102 @@
+use crate::{Finding, PackageDetails};
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+// version protocols that point at another package in the repository, or somewhere else
+// on disk, instead of the registry
+const LOCAL_PROTOCOLS: [&str; 4] = ["workspace", "file", "link", "portal"];
+
+pub fn local_protocol(spec: &str) -> Option<&'static str> {
+    LOCAL_PROTOCOLS.into_iter().find(|protocol| {
+        spec.strip_prefix(protocol)
+            .is_some_and(|rest| rest.starts_with(':'))
+    })
+}
+
+// `..` is resolved against the path itself, the target doesn't have to exist
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+// the directory (or tarball) a file:, link: or portal: dependency of the package in
+// `package_dir` points at. workspace: dependencies go by name and have none, unless
+// they're given as a relative path like workspace:../other
+pub fn link_target(package_dir: &Path, spec: &str) -> Option<PathBuf> {
+    match local_protocol(spec)? {
+        "workspace" => {
+            let target = &spec["workspace:".len()..];
+            (target.starts_with("./") || target.starts_with("../"))
+                .then(|| normalize(&package_dir.join(target)))
+        }
+        protocol => {
+            let target = &spec[protocol.len() + 1..];
+            Some(normalize(&package_dir.join(target)))
+        }
+    }
+}
+
+// the package a workspace: dependency names, when it's an alias like
+// workspace:@scope/other@^1.0.0 rather than a range for the dependency's own name
+pub fn workspace_alias(spec: &str) -> Option<&str> {
+    let target = spec.strip_prefix("workspace:")?;
+    match target.rfind('@') {
+        Some(at) if at > 0 => Some(&target[..at]),
+        _ => None,
+    }
+}
+
+// local dependencies across all packages by protocol
+pub fn protocol_counts(packages: &[PackageDetails]) -> BTreeMap<&'static str, usize> {
+    let mut counts = BTreeMap::new();
+    for package_details in packages {
+        for spec in package_details.local_dependencies.values() {
+            if let Some(protocol) = local_protocol(spec) {
+                *counts.entry(protocol).or_insert(0) += 1;
+            }
+        }
+    }
+    counts
+}
+
+// a file: or link: dependency that leaves the repository only builds on the machine that
+// happens to have the other checkout next to it
+pub fn find_links_outside(packages: &mut [PackageDetails], repo_root: &Path) {
+    for package_details in packages {
+        let outside: Vec<Finding> = package_details
+            .local_dependencies
+            .iter()
+            .filter(|(_, spec)| {
+                link_target(&package_details.path, spec)
+                    .is_some_and(|target| !target.starts_with(repo_root))
+            })
+            .map(|(name, spec)| Finding {
+                rule: "link-outside-repo".into(),
+                subject: name.clone(),
+                message: format!(
+                    "dependency {} on {} points outside the repository",
+                    name, spec
+                ),
+            })
+            .collect();
+        for finding in outside {
+            match package_details
+                .ignore_rules
+                .iter()
+                .any(|rule| *rule == finding.rule)
+            {
+                true => package_details.suppressed_findings.push(finding),
+                false => package_details.findings.push(finding),
+            }
+        }
+    }
+}
@@ -0,0 +1,451 @@
+use crate::args::{self, Args, DependencyClass};
+use crate::glob::glob_match;
+use crate::pipeline;
+use crate::{dependency_count, supports_cjs, supports_esm, AnalysisResults, PackageDetails};
+use ansi_term::Colour::Red;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::mem;
+
+// what a query can ask about a package. Lists match when any of their items do
+#[derive(Clone, Copy)]
+enum Field {
+    Name,
+    Version,
+    Path,
+    Owner,
+    Layer,
+    Portability,
+    Tag,
+    Rule,
+    Builtin,
+    Esm,
+    Cjs,
+    Types,
+    Private,
+    Size,
+    Deps,
+    Findings,
+    Errors,
+    Es,
+}
+
+const FIELDS: [(&str, Field); 18] = [
+    ("name", Field::Name),
+    ("version", Field::Version),
+    ("path", Field::Path),
+    ("owner", Field::Owner),
+    ("layer", Field::Layer),
+    ("portability", Field::Portability),
+    ("tag", Field::Tag),
+    ("rule", Field::Rule),
+    ("builtin", Field::Builtin),
+    ("esm", Field::Esm),
+    ("cjs", Field::Cjs),
+    ("types", Field::Types),
+    ("private", Field::Private),
+    ("size", Field::Size),
+    ("deps", Field::Deps),
+    ("findings", Field::Findings),
+    ("errors", Field::Errors),
+    ("es", Field::Es),
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    // text contains
+    Contains,
+}
+
+const OPS: [(&str, Op); 8] = [
+    ("=", Op::Eq),
+    ("==", Op::Eq),
+    ("!=", Op::Ne),
+    ("<", Op::Lt),
+    ("<=", Op::Le),
+    (">", Op::Gt),
+    (">=", Op::Ge),
+    ("~", Op::Contains),
+];
+
+enum Value {
+    Text(String),
+    Number(u64),
+    Bool(bool),
+}
+
+enum Query {
+    Or(Box<Query>, Box<Query>),
+    And(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+    Compare(Field, Op, Value),
+}
+
+#[derive(PartialEq)]
+enum Token {
+    Word(String),
+    Op(Op),
+    Open,
+    Close,
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '\'' | '"' => {
+                let mut word = String::new();
+                loop {
+                    match chars.next() {
+                        Some(next) if next == c => break,
+                        Some(next) => word.push(next),
+                        None => return Err(format!("unterminated {}quoted value", c)),
+                    }
+                }
+                tokens.push(Token::Word(word));
+            }
+            '=' | '!' | '<' | '>' | '~' => {
+                let mut op = c.to_string();
+                if let Some(next) = chars.next_if(|&next| next == '=') {
+                    op.push(next);
+                }
+                match OPS.iter().find(|(label, _)| *label == op) {
+                    Some((_, op)) => tokens.push(Token::Op(*op)),
+                    None => return Err(format!("unknown operator '{}'", op)),
+                }
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some(next) =
+                    chars.next_if(|&next| !next.is_whitespace() && !"()=!<>~'\"".contains(next))
+                {
+                    word.push(next);
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn is_keyword(token: Option<&Token>, keyword: &str) -> bool {
+    matches!(token, Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" | "yes" => Some(true),
+        "false" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+fn is_number(field: Field) -> bool {
+    matches!(
+        field,
+        Field::Size | Field::Deps | Field::Findings | Field::Errors | Field::Es
+    )
+}
+
+// the value a field is compared to, checked against what the field holds
+fn parse_value(field: Field, name: &str, op: Op, value: &str) -> Result<Value, String> {
+    match field {
+        Field::Esm | Field::Cjs | Field::Types | Field::Private => {
+            if op != Op::Eq && op != Op::Ne {
+                return Err(format!(
+                    "'{}' is true or false, compare it with = or !=",
+                    name
+                ));
+            }
+            parse_bool(value)
+                .map(Value::Bool)
+                .ok_or_else(|| format!("'{}' is true or false, not '{}'", name, value))
+        }
+        _ if op == Op::Contains && is_number(field) => Err(format!(
+            "'{}' is a number, compare it with =, !=, <, <=, > or >=",
+            name
+        )),
+        Field::Size => Ok(Value::Number(args::parse_size(value)?)),
+        Field::Deps | Field::Findings | Field::Errors | Field::Es => {
+            let number = match field {
+                Field::Es => value.trim_start_matches("ES").trim_start_matches("es"),
+                _ => value,
+            };
+            number
+                .parse()
+                .map(Value::Number)
+                .map_err(|_| format!("'{}' is a number, not '{}'", name, value))
+        }
+        _ => match op {
+            Op::Eq | Op::Ne | Op::Contains => Ok(Value::Text(value.to_string())),
+            _ => Err(format!("'{}' is text, compare it with =, != or ~", name)),
+        },
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        self.position += 1;
+        self.tokens.get(self.position - 1)
+    }
+
+    fn or(&mut self) -> Result<Query, String> {
+        let mut query = self.and()?;
+        while is_keyword(self.peek(), "or") {
+            self.position += 1;
+            query = Query::Or(Box::new(query), Box::new(self.and()?));
+        }
+        Ok(query)
+    }
+
+    fn and(&mut self) -> Result<Query, String> {
+        let mut query = self.unary()?;
+        while is_keyword(self.peek(), "and") {
+            self.position += 1;
+            query = Query::And(Box::new(query), Box::new(self.unary()?));
+        }
+        Ok(query)
+    }
+
+    fn unary(&mut self) -> Result<Query, String> {
+        if is_keyword(self.peek(), "not") {
+            self.position += 1;
+            return Ok(Query::Not(Box::new(self.unary()?)));
+        }
+        let name = match self.next() {
+            Some(Token::Open) => {
+                let query = self.or()?;
+                return match self.next() {
+                    Some(Token::Close) => Ok(query),
+                    _ => Err("missing ')'".to_string()),
+                };
+            }
+            Some(Token::Word(name)) => name.clone(),
+            Some(_) => return Err("expected a field name".to_string()),
+            None => return Err("expected a condition".to_string()),
+        };
+        let field = match FIELDS.iter().find(|(label, _)| *label == name) {
+            Some((_, field)) => *field,
+            None => return Err(format!("unknown field '{}', try 'help'", name)),
+        };
+        let op = match self.peek() {
+            Some(Token::Op(op)) => *op,
+            // a true or false field on its own, `esm and not types`
+            _ => {
+                let value = parse_value(field, &name, Op::Eq, "true")?;
+                return Ok(Query::Compare(field, Op::Eq, value));
+            }
+        };
+        self.position += 1;
+        match self.next() {
+            Some(Token::Word(value)) => {
+                let value = value.clone();
+                Ok(Query::Compare(
+                    field,
+                    op,
+                    parse_value(field, &name, op, &value)?,
+                ))
+            }
+            _ => Err(format!("expected a value to compare '{}' with", name)),
+        }
+    }
+}
+
+// `esm = false and size > 1MB`, `tag = legacy or owner ~ platform`, `not (types)`.
+// Text compared with = can use the globs of the config file, `name = @acme/*`
+fn parse_query(expression: &str) -> Result<Query, String> {
+    let mut parser = Parser {
+        tokens: tokenize(expression)?,
+        position: 0,
+    };
+    let query = parser.or()?;
+    match parser.peek() {
+        None => Ok(query),
+        Some(_) => Err("expected 'and', 'or' or the end of the query".to_string()),
+    }
+}
+
+fn matches_text(op: Op, pattern: &str, text: &str) -> bool {
+    match op {
+        Op::Contains => text.contains(pattern),
+        _ => glob_match(pattern, text),
+    }
+}
+
+impl Query {
+    fn matches(&self, p: &PackageDetails, classes: &[DependencyClass]) -> bool {
+        let (field, op, value) = match self {
+            Query::Or(a, b) => return a.matches(p, classes) || b.matches(p, classes),
+            Query::And(a, b) => return a.matches(p, classes) && b.matches(p, classes),
+            Query::Not(query) => return !query.matches(p, classes),
+            Query::Compare(field, op, value) => (*field, *op, value),
+        };
+        let texts: Vec<String> = match field {
+            Field::Name => vec![p.name.clone()],
+            Field::Version => vec![p.version.clone()],
+            Field::Path => vec![p.path.display().to_string()],
+            Field::Owner => p.owner.iter().cloned().collect(),
+            Field::Layer => p.layer.iter().cloned().collect(),
+            Field::Portability => vec![p.edge_portability.label().to_string()],
+            Field::Tag => p.tags.clone(),
+            Field::Rule => p
+                .findings
+                .iter()
+                .map(|finding| finding.rule.to_string())
+                .collect(),
+            Field::Builtin => p.node_builtins.clone(),
+            _ => Vec::new(),
+        };
+        let number = match field {
+            Field::Size => Some(p.size),
+            Field::Deps => Some(dependency_count(p, classes) as u64),
+            Field::Findings => Some(p.findings.len() as u64),
+            Field::Errors => Some(p.errors.len() as u64),
+            Field::Es => p.min_es_version.map(u64::from),
+            _ => None,
+        };
+        let flag = match field {
+            Field::Esm => supports_esm(&p.module_support),
+            Field::Cjs => supports_cjs(&p.module_support),
+            Field::Types => p.has_types,
+            Field::Private => p.private,
+            _ => false,
+        };
+
+        match value {
+            // != on a list means none of its items match
+            Value::Text(pattern) => match op {
+                Op::Ne => !texts.iter().any(|text| glob_match(pattern, text)),
+                _ => texts.iter().any(|text| matches_text(op, pattern, text)),
+            },
+            Value::Number(value) => number.is_some_and(|number| match op {
+                Op::Eq => number == *value,
+                Op::Ne => number != *value,
+                Op::Lt => number < *value,
+                Op::Le => number <= *value,
+                Op::Gt => number > *value,
+                Op::Ge => number >= *value,
+                Op::Contains => false,
+            }),
+            Value::Bool(value) => match op {
+                Op::Ne => flag != *value,
+                _ => flag == *value,
+            },
+        }
+    }
+}
+
+// the report for the page of packages matching the query that --sort, --offset and
+// --limit ask for, in the --output format. The others are set aside while it's rendered
+// and everything is put back in its place after
+fn run_query(results: &mut AnalysisResults, query: &Query, args: &Args) -> String {
+    let page = results.page(
+        |p| query.matches(p, &args.dependency_classes),
+        args.sort,
+        args.offset,
+        args.limit,
+    );
+    let indices: Vec<usize> = page.packages.iter().map(|(index, _)| *index).collect();
+    if indices.len() < page.matched {
+        eprintln!(
+            "Showing {} of {} matching packages from {}",
+            indices.len(),
+            page.matched,
+            args.offset + 1
+        );
+    }
+
+    let mut slots: Vec<Option<PackageDetails>> = mem::take(&mut results.packages)
+        .into_iter()
+        .map(Some)
+        .collect();
+    results.packages = indices
+        .iter()
+        .filter_map(|&index| slots[index].take())
+        .collect();
+    let formatted = pipeline::format(results, args);
+    for (index, package_details) in indices.into_iter().zip(mem::take(&mut results.packages)) {
+        slots[index] = Some(package_details);
+    }
+    results.packages = slots.into_iter().flatten().collect();
+    formatted
+}
+
+fn help() -> String {
+    let fields: Vec<&str> = FIELDS.iter().map(|(label, _)| *label).collect();
+    format!(
+        "Conditions compare a field with a value, `size > 1MB`, `rule = cjs-only`, `name = @acme/*`,
+and combine with and, or, not and parentheses. True or false fields can stand alone, `esm and not types`.
+Fields: {}
+Operators: = != < <= > >= and ~ for text that contains the value
+'quit' leaves, so does the end of the input",
+        fields.join(", ")
+    )
+}
+
+// `walker query results.json`, every --eval expression in turn, or a prompt reading them
+// from stdin until it ends. Returns false when an --eval expression doesn't parse
+pub fn query_results(results: &mut AnalysisResults, args: &Args) -> bool {
+    let report = |results: &mut AnalysisResults, expression: &str| match parse_query(expression) {
+        Ok(query) => {
+            print!("{}", run_query(results, &query, args));
+            true
+        }
+        Err(message) => {
+            eprintln!("{}", Red.paint(format!("Invalid query: {}", message)));
+            false
+        }
+    };
+
+    if !args.eval.is_empty() {
+        return args
+            .eval
+            .iter()
+            .all(|expression| report(results, expression));
+    }
+
+    let interactive = io::stdin().is_terminal();
+    if interactive {
+        println!(
+            "{} packages loaded, 'help' lists the fields",
+            results.packages.len()
+        );
+    }
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        if interactive {
+            print!("walker> ");
+            let _ = io::stdout().flush();
+        }
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => return true,
+        };
+        match line.trim() {
+            "" => {}
+            "quit" | "exit" => return true,
+            "help" => println!("{}", help()),
+            expression => {
+                report(results, expression);
+            }
+        }
+    }
+}
@@ -0,0 +1,676 @@
+use crate::args::{Args, Breakdown, DependencyClass};
+use crate::bundlers::EntrypointResolution;
+use crate::error_context::{errors_from_json, errors_to_json};
+use crate::graph::GraphSummary;
+use crate::lockfile::LockfileStats;
+use crate::module_format::FormatMismatch;
+use crate::output::{write_encrypted, Redactor};
+use crate::portability::EdgePortability;
+use crate::sampling::{Estimate, SampleSummary};
+use crate::sha256::sha256_hex;
+use crate::typescript::TsResolution;
+use crate::vcs::PackageHistory;
+use crate::{
+    AnalysisResults, DeclaredDependency, FilesystemStatistics, Finding, LocationStatistics,
+    ModuleSupport, PackageDetails, SkipReason, WalkStatistics,
+};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const FORMAT: &str = "walker-results";
+const FORMAT_VERSION: u64 = 2;
+
+// a results file is two lines: a header naming the format, its version and the sha256 of
+// the second line, then the results themselves as compact JSON
+//
+//   {"format":"walker-results","version":2,"walker_version":"0.1.0","sha256":"..."}
+//   {"packages":[...],"errors":[...],"timed_out":false,"error_limit_reached":false,
+//    "sample":{...},"breakdown":{...},"graph":{...},"walk":{...}}
+impl AnalysisResults {
+    pub fn save(&self, path: &Path, args: &Args) -> io::Result<()> {
+        let payload = serde_json::to_string(&json!({
+            "packages": self.packages.iter().map(package_to_json).collect::<Vec<Value>>(),
+            "errors": errors_to_json(&self.errors),
+            "timed_out": self.timed_out,
+            "error_limit_reached": self.error_limit_reached,
+            "sample": self.sample.as_ref().map(sample_to_json),
+            "breakdown": self.breakdown.as_ref().map(breakdown_to_json),
+            "graph": self.graph.as_ref().map(graph_to_json),
+            "walk": {
+                "directories_scanned": self.walk.directories_scanned,
+                "files_processed": self.walk.files_processed,
+                "bytes_read": self.walk.bytes_read,
+                "skipped": skipped_to_json(&self.walk.skipped),
+                "filesystems": self
+                    .walk
+                    .filesystems
+                    .iter()
+                    .map(|(device, filesystem)| {
+                        json!([
+                            device,
+                            filesystem.directories,
+                            filesystem.time.as_nanos() as u64
+                        ])
+                    })
+                    .collect::<Vec<Value>>(),
+            },
+        }))?;
+        let header = json!({ "format": FORMAT, "version": FORMAT_VERSION });
+        let redactor = args.redact.then(Redactor::from_env);
+        let contents = frame(header, &payload, redactor.as_ref())?;
+        write_encrypted(path, &contents, &args.encrypt_to)
+    }
+
+    pub fn load(path: &Path) -> io::Result<AnalysisResults> {
+        let contents = fs::read_to_string(path)?;
+        let (_, payload) = unframe(&contents, FORMAT, FORMAT_VERSION, "results file")?;
+
+        let v: Value = serde_json::from_str(&payload).map_err(|err| invalid(&err.to_string()))?;
+        Ok(AnalysisResults {
+            packages: v["packages"]
+                .as_array()
+                .map(|packages| packages.iter().map(package_from_json).collect())
+                .unwrap_or_default(),
+            errors: errors_from_json(&v["errors"]),
+            timed_out: v["timed_out"].as_bool().unwrap_or(false),
+            error_limit_reached: v["error_limit_reached"].as_bool().unwrap_or(false),
+            sample: sample_from_json(&v["sample"]),
+            breakdown: breakdown_from_json(&v["breakdown"]),
+            walk: walk_from_json(&v["walk"]),
+            graph: graph_from_json(&v["graph"]),
+            comparison: None,
+        })
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+// the header line, naming the format and version, and the payload after it. The walker
+// version and the payload's checksum are added to the header. With --redact both lines
+// are redacted and the header says so, `unframe` puts back what was taken out
+pub fn frame(mut header: Value, payload: &str, redactor: Option<&Redactor>) -> io::Result<String> {
+    let payload = match redactor {
+        Some(redactor) => {
+            header["redacted"] = json!(true);
+            redactor.redact(payload)
+        }
+        None => payload.to_string(),
+    };
+    header["walker_version"] = json!(env!("CARGO_PKG_VERSION"));
+    header["sha256"] = json!(sha256_hex(payload.as_bytes()));
+    let header = serde_json::to_string(&header)?;
+    let header = match redactor {
+        Some(redactor) => redactor.redact(&header),
+        None => header,
+    };
+    Ok(format!("{}\n{}\n", header, payload))
+}
+
+// the header and payload of a framed file, once the format, version and checksum match
+pub fn unframe(
+    contents: &str,
+    format: &str,
+    version: u64,
+    what: &str,
+) -> io::Result<(Value, String)> {
+    if contents.starts_with("-----BEGIN ") {
+        return Err(invalid(&format!(
+            "the {} is encrypted, decrypt it first",
+            what
+        )));
+    }
+    let not_walker = || invalid(&format!("not a walker {}", what));
+    let (header_line, payload) = contents.split_once('\n').ok_or_else(not_walker)?;
+    let payload = payload.trim_end_matches('\n');
+    let header: Value = serde_json::from_str(header_line).map_err(|_| not_walker())?;
+
+    if header["format"].as_str() != Some(format) {
+        return Err(not_walker());
+    }
+    if header["version"].as_u64() != Some(version) {
+        return Err(invalid(&format!(
+            "unsupported {} format version {}, expected {}",
+            what, header["version"], version
+        )));
+    }
+    if header["sha256"].as_str() != Some(sha256_hex(payload.as_bytes()).as_str()) {
+        return Err(invalid(&format!(
+            "checksum mismatch, the {} is corrupt",
+            what
+        )));
+    }
+    // redacted by the same user, so the paths are theirs again
+    match header["redacted"].as_bool() == Some(true) {
+        true => {
+            let redactor = Redactor::from_env();
+            let header =
+                serde_json::from_str(&redactor.restore(header_line)).map_err(|_| not_walker())?;
+            Ok((header, redactor.restore(payload)))
+        }
+        false => Ok((header, payload.to_string())),
+    }
+}
+
+pub fn skipped_to_json(skipped: &[(PathBuf, SkipReason)]) -> Vec<Value> {
+    skipped
+        .iter()
+        .map(|(path, reason)| json!([path.display().to_string(), reason.label()]))
+        .collect()
+}
+
+pub fn skipped_from_json(v: &Value) -> Vec<(PathBuf, SkipReason)> {
+    v.as_array()
+        .map(|skipped| {
+            skipped
+                .iter()
+                .filter_map(|entry| {
+                    let reason = SkipReason::parse(entry[1].as_str()?)?;
+                    Some((PathBuf::from(string(&entry[0])), reason))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn findings_to_json(findings: &[Finding]) -> Vec<Value> {
+    findings
+        .iter()
+        .map(|finding| json!({ "rule": finding.rule, "subject": finding.subject, "message": finding.message }))
+        .collect()
+}
+
+pub fn package_to_json(package_details: &PackageDetails) -> Value {
+    let module_support = &package_details.module_support;
+    json!({
+        "name": package_details.name,
+        "version": package_details.version,
+        "path": package_details.path.display().to_string(),
+        "manifest_hash": package_details.manifest_hash,
+        "manifest_fields": package_details.manifest_fields,
+        "private": package_details.private,
+        "node_engine": package_details.node_engine,
+        "module_support": {
+            "esm_main_mjs": module_support.esm_main_mjs,
+            "esm_type": module_support.esm_type,
+            "esm_exports": module_support.esm_exports,
+            "esm_partial": module_support.esm_partial,
+            "cjs_type": module_support.cjs_type,
+            "cjs_exports": module_support.cjs_exports,
+        },
+        "size": package_details.size,
+        "has_exports": package_details.has_exports,
+        "entrypoints": package_details.entrypoints,
+        "has_types": package_details.has_types,
+        "dependencies": package_details.dependencies,
+        "declared_dependencies": package_details
+            .declared_dependencies
+            .iter()
+            .map(|dependency| json!([dependency.name, dependency.class.label(), dependency.spec]))
+            .collect::<Vec<Value>>(),
+        "cjs_only_dependencies": package_details.cjs_only_dependencies,
+        "min_es_version": package_details.min_es_version,
+        "es_features": package_details.es_features,
+        "node_builtins": package_details.node_builtins,
+        "dynamic_imports": package_details.dynamic_imports,
+        "implicit_imports": package_details.implicit_imports,
+        "format_mismatches": package_details
+            .format_mismatches
+            .iter()
+            .map(|mismatch| json!([mismatch.entry, mismatch.file, mismatch.declared, mismatch.syntax]))
+            .collect::<Vec<Value>>(),
+        "typescript": package_details.typescript.map(|(resolution, compliant)| {
+            json!({ "resolution": resolution.label(), "compliant": compliant })
+        }),
+        "edge_portability": package_details.edge_portability.label(),
+        "edge_portability_reasons": package_details.edge_portability_reasons,
+        "local_dependencies": package_details.local_dependencies,
+        "scan_dependencies": package_details
+            .scan_dependencies
+            .iter()
+            .map(|(path, class)| json!([path.display().to_string(), class.label()]))
+            .collect::<Vec<Value>>(),
+        "resolutions": package_details
+            .resolutions
+            .iter()
+            .map(|resolution| json!({
+                "resolver": resolution.resolver,
+                "import": resolution.import,
+                "require": resolution.require,
+            }))
+            .collect::<Vec<Value>>(),
+        "asset_exports": package_details.asset_exports,
+        "native_addon": package_details.native_addon,
+        "lockfile": package_details.lockfile.as_ref().map(|lockfile| json!({
+            "locked": lockfile.locked,
+            "unique_versions": lockfile.unique_versions,
+            "duplicated": lockfile.duplicated,
+            "estimated_size": lockfile.estimated_size,
+            "resolved": lockfile.resolved,
+        })),
+        "platforms": package_details.platforms,
+        "aliases": package_details
+            .aliases
+            .iter()
+            .map(|alias| alias.display().to_string())
+            .collect::<Vec<String>>(),
+        "duplicate_of": package_details
+            .duplicate_of
+            .as_ref()
+            .map(|path| path.display().to_string()),
+        "workspace_patterns": package_details.workspace_patterns,
+        "workspace_member": package_details.workspace_member,
+        "unlinked_workspace_packages": package_details.unlinked_workspace_packages,
+        "workspace_root": package_details
+            .workspace_root
+            .as_ref()
+            .map(|path| path.display().to_string()),
+        "owner": package_details.owner,
+        "layer": package_details.layer,
+        "history": package_details.history.as_ref().map(|history| {
+            json!([history.created, history.last_changed, history.since, history.commits])
+        }),
+        "tags": package_details.tags,
+        "ignore_rules": package_details.ignore_rules,
+        "partial": package_details.partial,
+        "findings": findings_to_json(&package_details.findings),
+        "suppressed_findings": findings_to_json(&package_details.suppressed_findings),
+        "acknowledged_findings": findings_to_json(&package_details.acknowledged_findings),
+        "notes": findings_to_json(&package_details.notes),
+        "errors": errors_to_json(&package_details.errors),
+    })
+}
+
+pub fn strings(v: &Value) -> Vec<String> {
+    v.as_array()
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|value| value.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn string(v: &Value) -> String {
+    v.as_str().unwrap_or_default().to_string()
+}
+
+fn findings_from_json(v: &Value) -> Vec<Finding> {
+    v.as_array()
+        .map(|findings| {
+            findings
+                .iter()
+                .map(|finding| Finding {
+                    rule: string(&finding["rule"]).into(),
+                    subject: string(&finding["subject"]),
+                    message: string(&finding["message"]),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn counts(v: &Value) -> BTreeMap<String, usize> {
+    v.as_object()
+        .map(|counts| {
+            counts
+                .iter()
+                .map(|(key, count)| (key.clone(), count.as_u64().unwrap_or(0) as usize))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn string_map(v: &Value) -> BTreeMap<String, String> {
+    v.as_object()
+        .map(|map| {
+            map.iter()
+                .map(|(key, value)| (key.clone(), string(value)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn entrypoints(v: &Value) -> BTreeMap<String, BTreeMap<String, String>> {
+    let empty = Map::new();
+    v.as_object()
+        .unwrap_or(&empty)
+        .iter()
+        .map(|(subpath, conditions)| {
+            let conditions = conditions
+                .as_object()
+                .unwrap_or(&empty)
+                .iter()
+                .map(|(condition, target)| (condition.clone(), string(target)))
+                .collect();
+            (subpath.clone(), conditions)
+        })
+        .collect()
+}
+
+fn walk_from_json(v: &Value) -> WalkStatistics {
+    WalkStatistics {
+        directories_scanned: v["directories_scanned"].as_u64().unwrap_or(0) as usize,
+        files_processed: v["files_processed"].as_u64().unwrap_or(0) as usize,
+        bytes_read: v["bytes_read"].as_u64().unwrap_or(0),
+        skipped: skipped_from_json(&v["skipped"]),
+        filesystems: v["filesystems"]
+            .as_array()
+            .map(|filesystems| {
+                filesystems
+                    .iter()
+                    .filter_map(|entry| {
+                        let filesystem = FilesystemStatistics {
+                            directories: entry[1].as_u64()? as usize,
+                            time: Duration::from_nanos(entry[2].as_u64()?),
+                        };
+                        Some((entry[0].as_u64()?, filesystem))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+fn sample_to_json(sample: &SampleSummary) -> Value {
+    let estimate = |estimate: &Estimate| json!([estimate.value, estimate.margin]);
+    json!({
+        "seed": sample.seed,
+        "discovered": sample.discovered,
+        "analyzed": sample.analyzed,
+        "esm_share": estimate(&sample.esm_share),
+        "cjs_only_share": estimate(&sample.cjs_only_share),
+        "with_findings_share": estimate(&sample.with_findings_share),
+        "average_size": estimate(&sample.average_size),
+    })
+}
+
+fn sample_from_json(v: &Value) -> Option<SampleSummary> {
+    let estimate = |v: &Value| {
+        Some(Estimate {
+            value: v[0].as_f64()?,
+            margin: v[1].as_f64()?,
+        })
+    };
+    Some(SampleSummary {
+        seed: v["seed"].as_u64()?,
+        discovered: v["discovered"].as_u64()? as usize,
+        analyzed: v["analyzed"].as_u64()? as usize,
+        esm_share: estimate(&v["esm_share"])?,
+        cjs_only_share: estimate(&v["cjs_only_share"])?,
+        with_findings_share: estimate(&v["with_findings_share"])?,
+        average_size: estimate(&v["average_size"])?,
+    })
+}
+
+// the breakdown as it was when the results were saved, a --breakdown given when loading
+// them has no tree to group
+fn breakdown_to_json(
+    (breakdown, locations): &(Breakdown, Vec<(String, LocationStatistics)>),
+) -> Value {
+    let by = match breakdown {
+        Breakdown::Depth => "depth",
+        Breakdown::Folder => "folder",
+    };
+    let locations: Vec<Value> = locations
+        .iter()
+        .map(|(location, statistics)| {
+            json!([
+                location,
+                statistics.packages,
+                statistics.esm_percentage,
+                statistics.cjs_only
+            ])
+        })
+        .collect();
+    json!({ "by": by, "locations": locations })
+}
+
+fn breakdown_from_json(v: &Value) -> Option<(Breakdown, Vec<(String, LocationStatistics)>)> {
+    let breakdown = match v["by"].as_str()? {
+        "depth" => Breakdown::Depth,
+        "folder" => Breakdown::Folder,
+        _ => return None,
+    };
+    let locations = v["locations"]
+        .as_array()?
+        .iter()
+        .filter_map(|entry| {
+            let statistics = LocationStatistics {
+                packages: entry[1].as_u64()? as usize,
+                esm_percentage: entry[2].as_f64()?,
+                cjs_only: entry[3].as_u64()? as usize,
+            };
+            Some((string(&entry[0]), statistics))
+        })
+        .collect();
+    Some((breakdown, locations))
+}
+
+fn graph_to_json(graph: &GraphSummary) -> Value {
+    json!({
+        "edges": graph.edges,
+        "approximate_edges": graph.approximate_edges,
+        "findings": graph.findings,
+    })
+}
+
+fn graph_from_json(v: &Value) -> Option<GraphSummary> {
+    Some(GraphSummary {
+        edges: v["edges"].as_u64()? as usize,
+        approximate_edges: v["approximate_edges"].as_u64()? as usize,
+        findings: v["findings"]
+            .as_array()?
+            .iter()
+            .filter_map(|entry| Some((string(&entry[0]).into(), entry[1].as_u64()? as usize)))
+            .collect(),
+    })
+}
+
+pub fn package_from_json(v: &Value) -> PackageDetails {
+    let module_support = &v["module_support"];
+    let flag = |key: &str| module_support[key].as_bool().unwrap_or(false);
+
+    PackageDetails {
+        name: string(&v["name"]),
+        version: string(&v["version"]),
+        path: PathBuf::from(string(&v["path"])),
+        manifest_hash: string(&v["manifest_hash"]),
+        manifest_fields: strings(&v["manifest_fields"]),
+        private: v["private"].as_bool().unwrap_or(false),
+        node_engine: v["node_engine"].as_str().map(String::from),
+        module_support: ModuleSupport {
+            esm_main_mjs: flag("esm_main_mjs"),
+            esm_type: flag("esm_type"),
+            esm_exports: flag("esm_exports"),
+            esm_partial: flag("esm_partial"),
+            cjs_type: flag("cjs_type"),
+            cjs_exports: flag("cjs_exports"),
+        },
+        size: v["size"].as_u64().unwrap_or(0),
+        has_exports: v["has_exports"].as_bool().unwrap_or(false),
+        entrypoints: entrypoints(&v["entrypoints"]),
+        has_types: v["has_types"].as_bool().unwrap_or(false),
+        dependencies: strings(&v["dependencies"]),
+        declared_dependencies: v["declared_dependencies"]
+            .as_array()
+            .map(|dependencies| {
+                dependencies
+                    .iter()
+                    .filter_map(|entry| {
+                        Some(DeclaredDependency {
+                            name: string(&entry[0]),
+                            class: DependencyClass::parse(entry[1].as_str()?)?,
+                            spec: string(&entry[2]),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        local_dependencies: string_map(&v["local_dependencies"]),
+        scan_dependencies: v["scan_dependencies"]
+            .as_array()
+            .map(|dependencies| {
+                dependencies
+                    .iter()
+                    .filter_map(|entry| {
+                        Some((
+                            PathBuf::from(entry[0].as_str()?),
+                            DependencyClass::parse(entry[1].as_str()?)?,
+                        ))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        cjs_only_dependencies: strings(&v["cjs_only_dependencies"]),
+        min_es_version: v["min_es_version"].as_u64().map(|version| version as u16),
+        es_features: strings(&v["es_features"]),
+        node_builtins: strings(&v["node_builtins"]),
+        dynamic_imports: strings(&v["dynamic_imports"]),
+        implicit_imports: strings(&v["implicit_imports"]),
+        format_mismatches: v["format_mismatches"]
+            .as_array()
+            .map(|mismatches| {
+                mismatches
+                    .iter()
+                    .map(|mismatch| FormatMismatch {
+                        entry: mismatch[0].as_str().unwrap_or_default().to_string(),
+                        file: mismatch[1].as_str().unwrap_or_default().to_string(),
+                        declared: string(&mismatch[2]).into(),
+                        syntax: string(&mismatch[3]).into(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        typescript: TsResolution::parse(v["typescript"]["resolution"].as_str().unwrap_or_default())
+            .map(|resolution| {
+                (
+                    resolution,
+                    v["typescript"]["compliant"].as_bool().unwrap_or(false),
+                )
+            }),
+        edge_portability: v["edge_portability"]
+            .as_str()
+            .and_then(EdgePortability::parse)
+            .unwrap_or_default(),
+        edge_portability_reasons: strings(&v["edge_portability_reasons"]),
+        resolutions: v["resolutions"]
+            .as_array()
+            .map(|resolutions| {
+                resolutions
+                    .iter()
+                    .map(|resolution| EntrypointResolution {
+                        resolver: string(&resolution["resolver"]).into(),
+                        import: resolution["import"].as_str().map(String::from),
+                        require: resolution["require"].as_str().map(String::from),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        asset_exports: counts(&v["asset_exports"]),
+        native_addon: strings(&v["native_addon"]),
+        lockfile: match &v["lockfile"] {
+            lockfile @ Value::Object(_) => Some(LockfileStats {
+                locked: lockfile["locked"].as_u64().unwrap_or(0) as usize,
+                unique_versions: lockfile["unique_versions"].as_u64().unwrap_or(0) as usize,
+                duplicated: lockfile["duplicated"].as_u64().unwrap_or(0) as usize,
+                estimated_size: lockfile["estimated_size"].as_u64().unwrap_or(0),
+                resolved: string_map(&lockfile["resolved"]),
+            }),
+            _ => None,
+        },
+        platforms: v["platforms"]
+            .as_array()
+            .map(|platforms| {
+                platforms
+                    .iter()
+                    .map(|platform| string(platform).into())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        aliases: strings(&v["aliases"])
+            .into_iter()
+            .map(PathBuf::from)
+            .collect(),
+        duplicate_of: v["duplicate_of"].as_str().map(PathBuf::from),
+        workspace_patterns: strings(&v["workspace_patterns"]),
+        workspace_member: v["workspace_member"].as_bool().unwrap_or(false),
+        unlinked_workspace_packages: v["unlinked_workspace_packages"].as_bool().unwrap_or(false),
+        workspace_root: v["workspace_root"].as_str().map(PathBuf::from),
+        owner: v["owner"].as_str().map(String::from),
+        layer: v["layer"].as_str().map(String::from),
+        history: v["history"].as_array().and_then(|history| {
+            Some(PackageHistory {
+                created: history.first()?.as_u64()?,
+                last_changed: history.get(1)?.as_u64()?,
+                since: history.get(2)?.as_u64()?,
+                commits: history.get(3)?.as_u64()? as usize,
+            })
+        }),
+        tags: strings(&v["tags"]),
+        ignore_rules: strings(&v["ignore_rules"]),
+        partial: v["partial"].as_bool().unwrap_or(false),
+        findings: findings_from_json(&v["findings"]),
+        suppressed_findings: findings_from_json(&v["suppressed_findings"]),
+        acknowledged_findings: findings_from_json(&v["acknowledged_findings"]),
+        notes: findings_from_json(&v["notes"]),
+        errors: errors_from_json(&v["errors"]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn findings_round_trip() {
+        let mut package_details = PackageDetails::default();
+        package_details.findings.push(Finding {
+            rule: "broken-entrypoint".into(),
+            subject: "main".to_string(),
+            message: "main points at index.js, which doesn't exist".to_string(),
+        });
+        let loaded = package_from_json(&package_to_json(&package_details));
+        assert_eq!(loaded.findings.len(), 1);
+        assert_eq!(loaded.findings[0].rule, "broken-entrypoint");
+        assert_eq!(loaded.findings[0].subject, "main");
+    }
+
+    #[test]
+    fn breakdown_and_sample_round_trip() {
+        let statistics = LocationStatistics {
+            packages: 3,
+            esm_percentage: 33.3,
+            cjs_only: 2,
+        };
+        let breakdown = (
+            Breakdown::Folder,
+            vec![("packages".to_string(), statistics)],
+        );
+        let (loaded, locations) = breakdown_from_json(&breakdown_to_json(&breakdown)).unwrap();
+        assert!(matches!(loaded, Breakdown::Folder));
+        assert_eq!(locations[0].0, "packages");
+        assert_eq!(locations[0].1.cjs_only, 2);
+
+        let estimate = |value| Estimate { value, margin: 0.1 };
+        let sample = SampleSummary {
+            seed: 7,
+            discovered: 10,
+            analyzed: 4,
+            esm_share: estimate(0.5),
+            cjs_only_share: estimate(0.5),
+            with_findings_share: estimate(0.25),
+            average_size: estimate(120.0),
+        };
+        let loaded = sample_from_json(&sample_to_json(&sample)).unwrap();
+        assert_eq!((loaded.seed, loaded.analyzed), (7, 4));
+        assert_eq!(loaded.esm_share.margin, 0.1);
+        assert!(sample_from_json(&Value::Null).is_none());
+    }
+}
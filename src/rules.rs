@@ -0,0 +1,95 @@
+use crate::{Finding, PackageDetails};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Severity {
+    Off,
+    Warn,
+    Error,
+}
+
+impl Severity {
+    pub fn parse(value: &str) -> Option<Severity> {
+        match value {
+            "off" => Some(Severity::Off),
+            "warn" => Some(Severity::Warn),
+            "error" => Some(Severity::Error),
+            _ => None,
+        }
+    }
+}
+
+// a rule named in the config file's "rules", with what its findings should do
+pub struct RuleSetting {
+    pub rule: String,
+    pub severity: Severity,
+}
+
+// every other rule warns unless the config says otherwise
+pub fn severity(settings: &[RuleSetting], rule: &str) -> Severity {
+    settings
+        .iter()
+        .find(|setting| setting.rule == rule)
+        .map_or(Severity::Warn, |setting| setting.severity)
+}
+
+// packaging policy that isn't a problem in itself, missing-engines and no-types, only
+// checked when the config file turns it on
+fn policy_findings(package_details: &PackageDetails, settings: &[RuleSetting]) -> Vec<Finding> {
+    let enabled = |rule: &str| {
+        settings
+            .iter()
+            .any(|setting| setting.rule == rule && setting.severity != Severity::Off)
+    };
+    let mut findings = Vec::new();
+    if enabled("missing-engines") && package_details.node_engine.is_none() {
+        findings.push(Finding {
+            rule: "missing-engines".into(),
+            subject: String::new(),
+            message: "doesn't declare the node versions it supports in 'engines.node'".to_string(),
+        });
+    }
+    if enabled("no-types") && !package_details.has_types {
+        findings.push(Finding {
+            rule: "no-types".into(),
+            subject: String::new(),
+            message: "doesn't ship TypeScript declarations".to_string(),
+        });
+    }
+    findings
+}
+
+// adds the policy rules the config turns on, and sets aside the findings of rules it
+// turns off the way a package's own "ignoreRules" does
+pub fn apply(packages: &mut [PackageDetails], settings: &[RuleSetting]) {
+    if settings.is_empty() {
+        return;
+    }
+    for package_details in packages {
+        let policy = policy_findings(package_details, settings);
+        let (suppressed, findings): (Vec<Finding>, Vec<Finding>) = package_details
+            .findings
+            .drain(..)
+            .chain(policy)
+            .partition(|finding| {
+                severity(settings, &finding.rule) == Severity::Off
+                    || package_details
+                        .ignore_rules
+                        .iter()
+                        .any(|rule| *rule == finding.rule)
+            });
+        package_details.findings = findings;
+        package_details.suppressed_findings.extend(suppressed);
+    }
+}
+
+// the reported findings of rules set to "error", which fail the run
+pub fn rule_errors<'a>(
+    packages: &'a [PackageDetails],
+    settings: &[RuleSetting],
+) -> Vec<(&'a PackageDetails, &'a Finding)> {
+    packages
+        .iter()
+        .flat_map(|p| p.findings.iter().map(move |finding| (p, finding)))
+        .filter(|(_, finding)| severity(settings, &finding.rule) == Severity::Error)
+        .collect()
+}
@@ -0,0 +1,64 @@
+use crate::args::Args;
+use crate::output::{write_encrypted, Redactor};
+use crate::PackageDetails;
+use serde_json::json;
+use std::env;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// records what a run looked at so an audit can reproduce exactly what was scanned: the
+// config it read, the patterns that decided what was walked and every package found
+pub fn write_run_manifest(
+    path: &Path,
+    scan_root: &Path,
+    packages: &[PackageDetails],
+    partial: bool,
+    args: &Args,
+) -> io::Result<()> {
+    let generated_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+
+    let arguments: Vec<String> = env::args().skip(1).collect();
+
+    let analyzed: Vec<_> = packages
+        .iter()
+        .map(|package_details| {
+            json!({
+                "path": package_details.path.display().to_string(),
+                "name": package_details.name,
+                "version": package_details.version,
+                "package_json_sha256": package_details.manifest_hash,
+            })
+        })
+        .collect();
+
+    let config = args.config.as_ref().map(|config_path| {
+        let source = match config_path == Path::new("-") {
+            true => "stdin".to_string(),
+            false => config_path.display().to_string(),
+        };
+        json!({ "source": source, "sha256": args.config_sha256 })
+    });
+
+    let manifest = json!({
+        "walker_version": env!("CARGO_PKG_VERSION"),
+        "generated_at": generated_at,
+        "arguments": arguments,
+        "scan_root": scan_root.display().to_string(),
+        "partial": partial,
+        "config": config,
+        "include_patterns": args.includes,
+        "exclude_patterns": args.excludes,
+        "packages": analyzed,
+    });
+
+    let manifest = serde_json::to_string_pretty(&manifest)?;
+    let manifest = match args.redact {
+        true => Redactor::from_env().redact(&manifest),
+        false => manifest,
+    };
+    write_encrypted(path, &manifest, &args.encrypt_to)
+}
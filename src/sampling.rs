@@ -0,0 +1,166 @@
+use crate::{supports_cjs, supports_esm, PackageDetails};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy)]
+pub enum SampleMode {
+    // analyze each package with this probability
+    Rate(f64),
+    // analyze this many packages, chosen uniformly
+    Count(usize),
+}
+
+// "10%" -> a rate of 0.1
+pub fn parse_percentage(value: &str) -> Result<f64, String> {
+    value
+        .trim_end_matches('%')
+        .parse::<f64>()
+        .ok()
+        .filter(|percentage| *percentage > 0.0 && *percentage <= 100.0)
+        .map(|percentage| percentage / 100.0)
+        .ok_or_else(|| {
+            format!(
+                "Invalid --sample '{}', expected a percentage like 10%",
+                value
+            )
+        })
+}
+
+// the decision to analyze a package is made when the walk reaches its directory, before
+// package.json is parsed, so packages left out of the sample cost almost nothing
+pub struct Sampler {
+    mode: SampleMode,
+    pub seed: u64,
+    state: u64,
+    pub discovered: usize,
+    // reservoir of the directories currently in the sample, for `SampleMode::Count`
+    selected: Vec<PathBuf>,
+}
+
+impl Sampler {
+    pub fn new(mode: SampleMode, seed: u64) -> Sampler {
+        Sampler {
+            mode,
+            seed,
+            state: seed,
+            discovered: 0,
+            selected: Vec::new(),
+        }
+    }
+
+    // splitmix64, good enough for picking a sample and reproducible from the seed
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // returns whether the package in `dir` should be analyzed, and a previously selected
+    // package it replaces in the sample, if any
+    pub fn select(&mut self, dir: &Path) -> (bool, Option<PathBuf>) {
+        self.discovered += 1;
+        match self.mode {
+            SampleMode::Rate(rate) => (self.next_f64() < rate, None),
+            SampleMode::Count(count) if self.selected.len() < count => {
+                self.selected.push(dir.to_path_buf());
+                (true, None)
+            }
+            // reservoir sampling, every package seen so far is equally likely to be kept
+            SampleMode::Count(_) => {
+                let slot = (self.next_u64() % self.discovered as u64) as usize;
+                match self.selected.get_mut(slot) {
+                    Some(selected) => {
+                        let evicted = std::mem::replace(selected, dir.to_path_buf());
+                        (true, Some(evicted))
+                    }
+                    None => (false, None),
+                }
+            }
+        }
+    }
+
+    // false once a package has been pushed out of the sample by a later one
+    pub fn keeps(&self, dir: &Path) -> bool {
+        match self.mode {
+            SampleMode::Rate(_) => true,
+            SampleMode::Count(_) => self.selected.iter().any(|selected| selected == dir),
+        }
+    }
+}
+
+pub struct Estimate {
+    pub value: f64,
+    // half width of the 95% confidence interval
+    pub margin: f64,
+}
+
+pub struct SampleSummary {
+    pub seed: u64,
+    pub discovered: usize,
+    pub analyzed: usize,
+    pub esm_share: Estimate,
+    pub cjs_only_share: Estimate,
+    pub with_findings_share: Estimate,
+    pub average_size: Estimate,
+}
+
+// the sample is drawn without replacement from a known number of packages, so the
+// finite population correction shrinks the interval as the sample approaches the total
+fn finite_population_correction(analyzed: usize, discovered: usize) -> f64 {
+    match discovered > 1 {
+        true => ((discovered - analyzed) as f64 / (discovered - 1) as f64).sqrt(),
+        false => 0.0,
+    }
+}
+
+fn estimate_share(
+    packages: &[PackageDetails],
+    fpc: f64,
+    f: impl Fn(&PackageDetails) -> bool,
+) -> Estimate {
+    let n = packages.len() as f64;
+    let share = packages.iter().filter(|p| f(p)).count() as f64 / n;
+    Estimate {
+        value: share,
+        margin: 1.96 * (share * (1.0 - share) / n).sqrt() * fpc,
+    }
+}
+
+fn estimate_mean(values: &[f64], fpc: f64) -> Estimate {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = match values.len() > 1 {
+        true => values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0),
+        false => 0.0,
+    };
+    Estimate {
+        value: mean,
+        margin: 1.96 * (variance / n).sqrt() * fpc,
+    }
+}
+
+pub fn summarize(sampler: &Sampler, packages: &[PackageDetails]) -> Option<SampleSummary> {
+    if packages.is_empty() {
+        return None;
+    }
+
+    let fpc = finite_population_correction(packages.len(), sampler.discovered);
+    let sizes: Vec<f64> = packages.iter().map(|p| p.size as f64).collect();
+
+    Some(SampleSummary {
+        seed: sampler.seed,
+        discovered: sampler.discovered,
+        analyzed: packages.len(),
+        esm_share: estimate_share(packages, fpc, |p| supports_esm(&p.module_support)),
+        cjs_only_share: estimate_share(packages, fpc, |p| {
+            supports_cjs(&p.module_support) && !supports_esm(&p.module_support)
+        }),
+        with_findings_share: estimate_share(packages, fpc, |p| !p.findings.is_empty()),
+        average_size: estimate_mean(&sizes, fpc),
+    })
+}
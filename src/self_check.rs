@@ -0,0 +1,145 @@
+use crate::args::DependencyClass;
+use crate::{scope_statistics, tag_statistics, workspace_statistics, AnalysisResults};
+use std::collections::HashMap;
+use std::path::Path;
+
+fn check_percentage(violations: &mut Vec<String>, what: &str, percentage: f64) {
+    if !(0.0..=100.0).contains(&percentage) {
+        violations.push(format!("{} is {}%, outside 0-100", what, percentage));
+    }
+}
+
+fn check_share(violations: &mut Vec<String>, what: &str, share: f64) {
+    if !(0.0..=1.0).contains(&share) {
+        violations.push(format!("{} is {}, outside 0-1", what, share));
+    }
+}
+
+impl AnalysisResults {
+    // what the summaries say agrees with the packages they were worked out from. Meant to
+    // run before the report narrows the packages down, the walk's own numbers cover them all
+    pub fn assert_invariants(&self, classes: &[DependencyClass]) -> Result<(), Vec<String>> {
+        let mut violations = Vec::new();
+        let total = self.packages.len();
+
+        let mut paths: HashMap<&Path, usize> = HashMap::new();
+        for package_details in &self.packages {
+            *paths.entry(package_details.path.as_path()).or_insert(0) += 1;
+        }
+        let mut duplicated: Vec<(&Path, usize)> =
+            paths.into_iter().filter(|(_, count)| *count > 1).collect();
+        duplicated.sort();
+        for (path, count) in duplicated {
+            violations.push(format!("{} is listed {} times", path.display(), count));
+        }
+
+        let splits = [
+            ("scope", scope_statistics(&self.packages, classes)),
+            ("workspace", workspace_statistics(&self.packages, classes)),
+        ];
+        for (split, sides) in splits {
+            let counted: usize = sides
+                .iter()
+                .map(|(_, statistics)| statistics.packages)
+                .sum();
+            if !sides.is_empty() && counted != total {
+                violations.push(format!(
+                    "the {} split counts {} packages, not {}",
+                    split, counted, total
+                ));
+            }
+            for (side, statistics) in &sides {
+                check_percentage(
+                    &mut violations,
+                    &format!("ESM share of {} packages", side),
+                    statistics.esm_percentage,
+                );
+                if statistics.cjs_only > statistics.packages {
+                    violations.push(format!(
+                        "{} of {} {} packages are CommonJS only",
+                        statistics.cjs_only, statistics.packages, side
+                    ));
+                }
+            }
+        }
+
+        for (tag, statistics) in tag_statistics(&self.packages) {
+            check_percentage(
+                &mut violations,
+                &format!("ESM share of tag {}", tag),
+                statistics.esm_percentage,
+            );
+            if statistics.packages > total {
+                violations.push(format!(
+                    "tag {} counts {} packages, more than the {} there are",
+                    tag, statistics.packages, total
+                ));
+            }
+        }
+
+        if let Some((_, locations)) = &self.breakdown {
+            let counted: usize = locations
+                .iter()
+                .map(|(_, statistics)| statistics.packages)
+                .sum();
+            if counted != total {
+                violations.push(format!(
+                    "the breakdown counts {} packages, not {}",
+                    counted, total
+                ));
+            }
+            for (location, statistics) in locations {
+                check_percentage(
+                    &mut violations,
+                    &format!("ESM share of {}", location),
+                    statistics.esm_percentage,
+                );
+            }
+        }
+
+        if let Some(sample) = &self.sample {
+            if sample.analyzed != total || sample.analyzed > sample.discovered {
+                violations.push(format!(
+                    "the sample analyzed {} of {} packages, {} are listed",
+                    sample.analyzed, sample.discovered, total
+                ));
+            }
+            check_share(&mut violations, "sampled ESM share", sample.esm_share.value);
+            check_share(
+                &mut violations,
+                "sampled CommonJS only share",
+                sample.cjs_only_share.value,
+            );
+            check_share(
+                &mut violations,
+                "sampled share with findings",
+                sample.with_findings_share.value,
+            );
+        }
+
+        if let Some(graph) = &self.graph {
+            let edges: usize = self
+                .packages
+                .iter()
+                .map(|p| p.scan_dependencies.len())
+                .sum();
+            if graph.edges != edges {
+                violations.push(format!(
+                    "the dependency graph has {} edges, the packages list {}",
+                    graph.edges, edges
+                ));
+            }
+            if graph.approximate_edges > graph.edges {
+                violations.push(format!(
+                    "{} of {} dependency graph edges are approximate",
+                    graph.approximate_edges, graph.edges
+                ));
+            }
+        }
+
+        match violations.is_empty() {
+            true => Ok(()),
+            false => Err(violations),
+        }
+    }
+}
@@ -0,0 +1,572 @@
+use crate::bundlers::EntrypointResolution;
+use crate::filesystem::{FileSystem, FileType};
+use crate::syntax;
+use std::cell::Cell;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+// files bigger than this are bundles or generated, and say little about what the
+// package itself uses
+const MAX_SOURCE_SIZE: u64 = 1024 * 1024;
+// a package with more sources than this is only partly looked at
+const MAX_SOURCE_FILES: usize = 2000;
+const SOURCE_EXTENSIONS: [&str; 8] = ["js", "mjs", "cjs", "jsx", "ts", "mts", "cts", "tsx"];
+
+// the modules node ships with, importable with or without the 'node:' prefix
+const NODE_BUILTINS: [&str; 42] = [
+    "assert",
+    "async_hooks",
+    "buffer",
+    "child_process",
+    "cluster",
+    "console",
+    "constants",
+    "crypto",
+    "dgram",
+    "diagnostics_channel",
+    "dns",
+    "domain",
+    "events",
+    "fs",
+    "http",
+    "http2",
+    "https",
+    "inspector",
+    "module",
+    "net",
+    "os",
+    "path",
+    "perf_hooks",
+    "process",
+    "punycode",
+    "querystring",
+    "readline",
+    "repl",
+    "stream",
+    "string_decoder",
+    "sys",
+    "timers",
+    "tls",
+    "trace_events",
+    "tty",
+    "url",
+    "util",
+    "v8",
+    "vm",
+    "wasi",
+    "worker_threads",
+    "zlib",
+];
+// child_process functions that start a process
+const CHILD_PROCESS_CALLS: [&str; 7] = [
+    "spawn",
+    "spawnSync",
+    "exec",
+    "execSync",
+    "execFile",
+    "execFileSync",
+    "fork",
+];
+// newer ones only exist with it, without it they're ordinary npm packages
+const NODE_PREFIXED_BUILTINS: [&str; 3] = ["sea", "sqlite", "test"];
+
+#[derive(Debug, PartialEq)]
+pub enum Token {
+    Ident(String),
+    Str(String),
+    Template,
+    Punct(char),
+}
+
+// splits JavaScript (or TypeScript) into just enough tokens to find module references.
+// Not a parser: regular expression literals aren't recognized, so one with a quote in it
+// can throw the rest of the file off. Comes with the line each token starts on
+pub fn tokenize(source: &str) -> (Vec<Token>, Vec<usize>) {
+    let mut tokens = Vec::new();
+    let mut lines = Vec::new();
+    let line = Cell::new(1);
+    let mut chars = source
+        .chars()
+        .inspect(|&c| {
+            if c == '\n' {
+                line.set(line.get() + 1);
+            }
+        })
+        .peekable();
+    while let Some(c) = chars.next() {
+        // a peeked character is counted when peeked, so this is the line `c` is on
+        let start = line.get();
+        match c {
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            '\'' | '"' => {
+                let mut value = String::new();
+                while let Some(next) = chars.next() {
+                    match next {
+                        '\\' => {
+                            if let Some(escaped) = chars.next() {
+                                value.push(escaped);
+                            }
+                        }
+                        next if next == c || next == '\n' => break,
+                        next => value.push(next),
+                    }
+                }
+                tokens.push(Token::Str(value));
+            }
+            '`' => {
+                while let Some(next) = chars.next() {
+                    match next {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '`' => break,
+                        _ => {}
+                    }
+                }
+                tokens.push(Token::Template);
+            }
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                let mut ident = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if !(next.is_alphanumeric() || next == '_' || next == '$') {
+                        break;
+                    }
+                    ident.push(next);
+                    chars.next();
+                }
+                tokens.push(Token::Ident(ident));
+            }
+            c if c.is_whitespace() || c.is_ascii_digit() => {}
+            c => tokens.push(Token::Punct(c)),
+        }
+        lines.resize(tokens.len(), start);
+    }
+    (tokens, lines)
+}
+
+fn is_ident(token: Option<&Token>, name: &str) -> bool {
+    matches!(token, Some(Token::Ident(ident)) if ident == name)
+}
+
+// the modules a source file refers to by a literal specifier: static imports and
+// re-exports, require() calls and import() expressions
+pub fn module_specifiers(tokens: &[Token]) -> Vec<&str> {
+    let mut specifiers = Vec::new();
+    for (index, token) in tokens.iter().enumerate() {
+        let previous = index.checked_sub(1).and_then(|index| tokens.get(index));
+        let call = |name: &str| {
+            is_ident(previous, name)
+                && token == &Token::Punct('(')
+                && matches!(tokens.get(index + 2), Some(Token::Punct(')')))
+        };
+        match tokens.get(index + 1) {
+            // import 'side-effect' and ... from 'module'
+            Some(Token::Str(specifier)) if is_ident(Some(token), "import") => {
+                specifiers.push(specifier.as_str())
+            }
+            Some(Token::Str(specifier)) if is_ident(Some(token), "from") => {
+                specifiers.push(specifier.as_str())
+            }
+            Some(Token::Str(specifier)) if call("require") || call("import") => {
+                specifiers.push(specifier.as_str())
+            }
+            _ => {}
+        }
+    }
+    specifiers
+}
+
+// the specifiers ESM resolves, of static imports, re-exports and import() expressions,
+// with the index of their token. require() keeps resolving the CommonJS way
+pub fn import_specifiers(tokens: &[Token]) -> Vec<(usize, &str)> {
+    let mut specifiers = Vec::new();
+    for (index, token) in tokens.iter().enumerate() {
+        let previous = index.checked_sub(1).and_then(|index| tokens.get(index));
+        let is_import_call = is_ident(previous, "import")
+            && token == &Token::Punct('(')
+            && matches!(tokens.get(index + 2), Some(Token::Punct(')')));
+        let is_import =
+            is_ident(Some(token), "import") || is_ident(Some(token), "from") || is_import_call;
+        if let (true, Some(Token::Str(specifier))) = (is_import, tokens.get(index + 1)) {
+            specifiers.push((index + 1, specifier.as_str()));
+        }
+    }
+    specifiers
+}
+
+// what a relative specifier leaves for the resolver to guess, which ESM won't: the
+// directory's index file, or the file's extension. None for specifiers ESM resolves as
+// written, bare package names included
+fn implicit_resolution(
+    filesystem: &dyn FileSystem,
+    dir: &Path,
+    specifier: &str,
+) -> Option<&'static str> {
+    if !(specifier.starts_with("./")
+        || specifier.starts_with("../")
+        || specifier == "."
+        || specifier == "..")
+    {
+        return None;
+    }
+    let path = dir.join(specifier);
+    if specifier.ends_with('/') || specifier.ends_with('.') || filesystem.is_dir(&path) {
+        return Some("directory index");
+    }
+    // a file that's there as written is fine, as is a missing one with an extension,
+    // TypeScript sources import their .ts files as the .js they compile to
+    let file_name = specifier.rsplit('/').next().unwrap_or(specifier);
+    match filesystem.is_file(&path) || file_name.contains('.') {
+        true => None,
+        false => Some("extension"),
+    }
+}
+
+// `import()` and `require()` calls whose specifier is computed rather than a string
+// literal, as token indices. Bundlers can't follow them, so what they load is unknown
+pub fn dynamic_loads(tokens: &[Token]) -> Vec<usize> {
+    let mut loads = Vec::new();
+    for (index, token) in tokens.iter().enumerate() {
+        let previous = index.checked_sub(1).and_then(|index| tokens.get(index));
+        let is_call = (is_ident(Some(token), "require") || is_ident(Some(token), "import"))
+            && tokens.get(index + 1) == Some(&Token::Punct('('))
+            // not a method, x.require(...), or the definition of one
+            && previous != Some(&Token::Punct('.'))
+            && !is_ident(previous, "function");
+        if !is_call {
+            continue;
+        }
+        // import('module', { with: ... }) still names its module
+        let literal = matches!(tokens.get(index + 2), Some(Token::Str(_)))
+            && matches!(tokens.get(index + 3), Some(Token::Punct(')' | ',')));
+        let empty = tokens.get(index + 2) == Some(&Token::Punct(')'));
+        if !literal && !empty {
+            loads.push(index);
+        }
+    }
+    loads
+}
+
+// `new Worker(...)` (worker_threads or a web worker), as token indices
+pub fn worker_starts(tokens: &[Token]) -> Vec<usize> {
+    let at = |index: Option<usize>| index.and_then(|index| tokens.get(index));
+    (0..tokens.len())
+        .filter(|&index| {
+            let constructed = is_ident(at(index.checked_sub(1)), "new")
+                // new worker_threads.Worker(...)
+                || (at(index.checked_sub(1)) == Some(&Token::Punct('.'))
+                    && is_ident(at(index.checked_sub(3)), "new"));
+            is_ident(tokens.get(index), "Worker")
+                && tokens.get(index + 1) == Some(&Token::Punct('('))
+                && constructed
+        })
+        .collect()
+}
+
+// calls that start a child process, as token indices and the function called. Names
+// like exec are common, so only files importing child_process are worth looking at
+pub fn child_process_calls(tokens: &[Token]) -> Vec<(usize, &'static str)> {
+    let imports_child_process = module_specifiers(tokens)
+        .into_iter()
+        .any(|specifier| node_builtin(specifier) == Some("child_process"));
+    if !imports_child_process {
+        return Vec::new();
+    }
+    let mut calls = Vec::new();
+    for (index, token) in tokens.iter().enumerate() {
+        let previous = index.checked_sub(1).and_then(|index| tokens.get(index));
+        let call = CHILD_PROCESS_CALLS
+            .into_iter()
+            .find(|name| is_ident(Some(token), name));
+        // a method of something named, cp.spawn(...), not of a literal like /re/.exec(...)
+        let receiver_named = previous != Some(&Token::Punct('.'))
+            || matches!(
+                index.checked_sub(2).and_then(|index| tokens.get(index)),
+                Some(Token::Ident(_))
+            );
+        if let Some(call) = call {
+            if tokens.get(index + 1) == Some(&Token::Punct('('))
+                && !is_ident(previous, "function")
+                && receiver_named
+            {
+                calls.push((index, call));
+            }
+        }
+    }
+    calls
+}
+
+// the builtin a specifier names, without the 'node:' prefix or a subpath
+pub fn node_builtin(specifier: &str) -> Option<&str> {
+    let (name, prefixed) = match specifier.strip_prefix("node:") {
+        Some(name) => (name, true),
+        None => (specifier, false),
+    };
+    let module = name.split('/').next().unwrap_or(name);
+    match NODE_BUILTINS.contains(&module) || (prefixed && NODE_PREFIXED_BUILTINS.contains(&module))
+    {
+        true => Some(module),
+        false => None,
+    }
+}
+
+// the package's own source files, leaving out node_modules and packages nested in it
+fn list_sources(
+    filesystem: &dyn FileSystem,
+    dir: &Path,
+    is_root: bool,
+    sources: &mut Vec<PathBuf>,
+) {
+    if !is_root && filesystem.is_file(&dir.join("package.json")) {
+        return;
+    }
+    let entries = match filesystem.read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        if sources.len() >= MAX_SOURCE_FILES {
+            return;
+        }
+        let name = entry.file_name();
+        if name == "node_modules" || name == ".git" {
+            continue;
+        }
+        let path = entry.path;
+        match entry.file_type {
+            FileType::Dir => list_sources(filesystem, &path, false, sources),
+            FileType::File => {
+                let is_source = path
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .is_some_and(|extension| SOURCE_EXTENSIONS.contains(&extension));
+                if is_source {
+                    sources.push(path);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// a source file small enough to be worth tokenizing, adding its size to `bytes_read`
+pub fn read_source(
+    filesystem: &dyn FileSystem,
+    path: &Path,
+    bytes_read: &mut u64,
+) -> Option<String> {
+    let source = match filesystem.metadata(path) {
+        Ok(metadata) if metadata.len <= MAX_SOURCE_SIZE => filesystem.read_to_string(path).ok()?,
+        _ => return None,
+    };
+    *bytes_read += source.len() as u64;
+    Some(source)
+}
+
+#[derive(Default)]
+pub struct SourceAnalysis {
+    pub files: usize,
+    pub builtins: BTreeSet<String>,
+    // file:line of every computed import() or require()
+    pub dynamic_loads: Vec<String>,
+    // file:line of workers started, and of child processes with the function used
+    pub workers: Vec<String>,
+    pub child_processes: Vec<String>,
+    // file:line of relative imports that leave out the extension or point at a directory,
+    // with the specifier
+    pub implicit_imports: Vec<String>,
+}
+
+pub struct EntrypointSyntax {
+    pub version: u16,
+    pub features: Vec<&'static str>,
+    // ESM entrypoints using top-level await, with the line of the first one
+    pub top_level_await: Vec<(String, usize)>,
+}
+
+// the syntax level of the files a package's entrypoints resolve to, None when none of
+// them could be read
+pub fn entrypoint_syntax(
+    filesystem: &dyn FileSystem,
+    package_dir: &Path,
+    resolutions: &[EntrypointResolution],
+    bytes_read: &mut u64,
+) -> Option<EntrypointSyntax> {
+    let imported: BTreeSet<&String> = resolutions
+        .iter()
+        .filter_map(|resolution| resolution.import.as_ref())
+        .collect();
+    let entries: BTreeSet<&String> = resolutions
+        .iter()
+        .flat_map(|resolution| [&resolution.import, &resolution.require])
+        .flatten()
+        .collect();
+
+    let mut features = Vec::new();
+    let mut top_level_await = Vec::new();
+    let mut read_any = false;
+    for entry in entries {
+        let source = match read_source(filesystem, &package_dir.join(entry), bytes_read) {
+            Some(source) => source,
+            None => continue,
+        };
+        read_any = true;
+        let (tokens, lines) = tokenize(&source);
+        for feature in syntax::features(&tokens) {
+            if !features.contains(&feature) {
+                features.push(feature);
+            }
+        }
+        // only a module can await at the top level, so an imported file doing it is one
+        if imported.contains(entry) && !entry.ends_with(".cjs") {
+            if let Some(index) = syntax::top_level_await(&tokens) {
+                top_level_await.push((entry.clone(), lines[index]));
+            }
+        }
+    }
+    read_any.then(|| {
+        let (version, features) = syntax::min_es_version(&features);
+        EntrypointSyntax {
+            version,
+            features,
+            top_level_await,
+        }
+    })
+}
+
+// reads every source file of the package in `package_dir`, adding what it reads to
+// `bytes_read`
+pub fn analyze_sources(
+    filesystem: &dyn FileSystem,
+    package_dir: &Path,
+    bytes_read: &mut u64,
+) -> SourceAnalysis {
+    let mut sources = Vec::new();
+    list_sources(filesystem, package_dir, true, &mut sources);
+    sources.sort();
+
+    let mut analysis = SourceAnalysis::default();
+    for path in sources {
+        let source = match read_source(filesystem, &path, bytes_read) {
+            Some(source) => source,
+            None => continue,
+        };
+        analysis.files += 1;
+
+        let (tokens, lines) = tokenize(&source);
+        for specifier in module_specifiers(&tokens) {
+            if let Some(builtin) = node_builtin(specifier) {
+                analysis.builtins.insert(builtin.to_string());
+            }
+        }
+        let file = path.strip_prefix(package_dir).unwrap_or(&path).display();
+        let dir = path.parent().unwrap_or(package_dir);
+        for (index, specifier) in import_specifiers(&tokens) {
+            if let Some(left_out) = implicit_resolution(filesystem, dir, specifier) {
+                analysis.implicit_imports.push(format!(
+                    "{}:{} ({}, no {})",
+                    file, lines[index], specifier, left_out
+                ));
+            }
+        }
+        for index in dynamic_loads(&tokens) {
+            analysis
+                .dynamic_loads
+                .push(format!("{}:{}", file, lines[index]));
+        }
+        for index in worker_starts(&tokens) {
+            analysis.workers.push(format!("{}:{}", file, lines[index]));
+        }
+        for (index, call) in child_process_calls(&tokens) {
+            analysis
+                .child_processes
+                .push(format!("{}:{} ({})", file, lines[index], call));
+        }
+    }
+    analysis
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(source: &str) -> Vec<Token> {
+        tokenize(source).0
+    }
+
+    #[test]
+    fn tokens_come_with_their_lines() {
+        let (tokens, lines) = tokenize("a\n'b\\'c'\n// d\n/* e\n */ `f\ng` h");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Ident("a".to_string()),
+                Token::Str("b'c".to_string()),
+                Token::Template,
+                Token::Ident("h".to_string()),
+            ]
+        );
+        assert_eq!(lines, vec![1, 2, 5, 6]);
+    }
+
+    #[test]
+    fn finds_literal_specifiers() {
+        let source = "import a from 'a'; import 'b'; export * from './c';\
+            const d = require('d'); import('e'); require(name);";
+        let tokens = tokens(source);
+        assert_eq!(module_specifiers(&tokens), vec!["a", "b", "./c", "d", "e"]);
+        let specifiers: Vec<&str> = import_specifiers(&tokens)
+            .into_iter()
+            .map(|(_, specifier)| specifier)
+            .collect();
+        assert_eq!(specifiers, vec!["a", "b", "./c", "e"]);
+    }
+
+    #[test]
+    fn finds_computed_loads() {
+        let tokens =
+            tokens("require(name); require('x'); x.require(y); import(`./${a}`); require()");
+        assert_eq!(dynamic_loads(&tokens), vec![0, 17]);
+    }
+
+    #[test]
+    fn finds_workers_and_child_processes() {
+        assert_eq!(worker_starts(&tokens("new Worker('./w.js')")), vec![1]);
+        assert_eq!(worker_starts(&tokens("new threads.Worker(x)")), vec![3]);
+        assert!(worker_starts(&tokens("Worker(x)")).is_empty());
+
+        let source =
+            "import { exec } from 'node:child_process'; exec('ls'); cp.spawn(x); /re/.exec(s)";
+        let calls: Vec<&str> = child_process_calls(&tokens(source))
+            .into_iter()
+            .map(|(_, call)| call)
+            .collect();
+        assert_eq!(calls, vec!["exec", "spawn"]);
+        assert!(child_process_calls(&tokens("exec('ls')")).is_empty());
+    }
+
+    #[test]
+    fn names_builtins_with_or_without_the_prefix() {
+        assert_eq!(node_builtin("fs/promises"), Some("fs"));
+        assert_eq!(node_builtin("node:path"), Some("path"));
+        assert_eq!(node_builtin("node:test"), Some("test"));
+        assert_eq!(node_builtin("test"), None);
+        assert_eq!(node_builtin("lodash"), None);
+    }
+}
@@ -0,0 +1,230 @@
+use crate::sources::Token;
+use std::collections::BTreeMap;
+
+// statements whose parenthesized head is followed by a block that isn't a function body
+const CONTROL_KEYWORDS: [&str; 6] = ["if", "for", "while", "switch", "catch", "with"];
+
+fn punct(token: Option<&Token>, c: char) -> bool {
+    token == Some(&Token::Punct(c))
+}
+
+fn ident(token: Option<&Token>) -> Option<&str> {
+    match token {
+        Some(Token::Ident(ident)) => Some(ident),
+        _ => None,
+    }
+}
+
+// the index of the first `await` outside of any function. Blocks are told apart by what
+// opens them: a `{` after `=>`, or after the parameter list of anything but a control
+// statement, is a function body
+pub fn top_level_await(tokens: &[Token]) -> Option<usize> {
+    // what's open: true for function bodies, for the rest of braces and parentheses false
+    let mut scopes: Vec<bool> = Vec::new();
+    // the identifier in front of each open '(' and of the last one closed
+    let mut paren_owners: Vec<Option<&str>> = Vec::new();
+    let mut closed_owner: Option<&str> = None;
+
+    for (index, token) in tokens.iter().enumerate() {
+        let previous = index.checked_sub(1).and_then(|index| tokens.get(index));
+        match token {
+            Token::Punct('(') => paren_owners.push(ident(previous)),
+            Token::Punct(')') => closed_owner = paren_owners.pop().flatten(),
+            Token::Punct('{') => {
+                let arrow = punct(previous, '>')
+                    && punct(index.checked_sub(2).and_then(|i| tokens.get(i)), '=');
+                let after_parameters = punct(previous, ')')
+                    && !closed_owner.is_some_and(|owner| CONTROL_KEYWORDS.contains(&owner));
+                scopes.push(arrow || after_parameters);
+            }
+            Token::Punct('}') => {
+                scopes.pop();
+            }
+            Token::Ident(name) if name == "await" && !scopes.contains(&true) => {
+                // unless it's in an arrow function body without braces, `async () => await x`
+                let statement: Vec<&Token> = tokens[..index]
+                    .iter()
+                    .rev()
+                    .take_while(|token| !matches!(token, Token::Punct(';' | '{' | '}')))
+                    .collect();
+                let in_arrow = statement
+                    .windows(2)
+                    .any(|pair| punct(Some(pair[0]), '>') && punct(Some(pair[1]), '='));
+                if !in_arrow {
+                    return Some(index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// syntax features by the ECMAScript edition that introduced them
+const FEATURES: [(&str, u16); 15] = [
+    ("arrow functions", 2015),
+    ("classes", 2015),
+    ("template literals", 2015),
+    ("let and const", 2015),
+    ("exponentiation operator", 2016),
+    ("async functions", 2017),
+    ("async iteration", 2018),
+    ("optional catch binding", 2019),
+    ("optional chaining", 2020),
+    ("nullish coalescing", 2020),
+    ("import.meta", 2020),
+    ("logical assignment", 2021),
+    ("private class members", 2022),
+    ("class static blocks", 2022),
+    ("top-level await", 2022),
+];
+
+fn year(feature: &str) -> u16 {
+    FEATURES
+        .iter()
+        .find(|(name, _)| *name == feature)
+        .map(|(_, year)| *year)
+        .unwrap_or(2015)
+}
+
+// the features a source file uses, found from its tokens alone. Operators come out of
+// the tokenizer a character at a time, so `a ? .5 : b` reads as optional chaining, a
+// rare enough shape to live with
+pub fn features(tokens: &[Token]) -> Vec<&'static str> {
+    let mut found = Vec::new();
+    let at = |index: usize| tokens.get(index);
+    for (index, token) in tokens.iter().enumerate() {
+        let next = at(index + 1);
+        let feature = match token {
+            Token::Template => Some("template literals"),
+            Token::Ident(name) => match (name.as_str(), next) {
+                ("class", _) => Some("classes"),
+                ("let" | "const", Some(Token::Ident(_))) => Some("let and const"),
+                ("async", Some(Token::Ident(function))) if function == "function" => {
+                    Some("async functions")
+                }
+                ("await", _) => Some("async functions"),
+                ("for", Some(Token::Ident(r#await))) if r#await == "await" => {
+                    Some("async iteration")
+                }
+                ("catch", Some(Token::Punct('{'))) => Some("optional catch binding"),
+                ("import", Some(Token::Punct('.'))) if ident(at(index + 2)) == Some("meta") => {
+                    Some("import.meta")
+                }
+                ("static", Some(Token::Punct('{'))) => Some("class static blocks"),
+                _ => None,
+            },
+            Token::Punct(c) => match (c, next) {
+                ('=', Some(Token::Punct('>'))) => Some("arrow functions"),
+                ('*', Some(Token::Punct('*'))) => Some("exponentiation operator"),
+                ('?', Some(Token::Punct('?'))) if punct(at(index + 2), '=') => {
+                    Some("logical assignment")
+                }
+                ('|', Some(Token::Punct('|'))) | ('&', Some(Token::Punct('&')))
+                    if punct(at(index + 2), '=') && !punct(at(index + 3), '=') =>
+                {
+                    Some("logical assignment")
+                }
+                ('?', Some(Token::Punct('?'))) => Some("nullish coalescing"),
+                ('?', Some(Token::Punct('.'))) => Some("optional chaining"),
+                ('#', Some(Token::Ident(_))) => Some("private class members"),
+                _ => None,
+            },
+            Token::Str(_) => None,
+        };
+        if let Some(feature) = feature {
+            if !found.contains(&feature) {
+                found.push(feature);
+            }
+        }
+    }
+    if top_level_await(tokens).is_some() {
+        found.push("top-level await");
+    }
+    found
+}
+
+// the lowest edition that has every feature, and the features that need it. Code using
+// none of them is counted as ES5
+pub fn min_es_version(features: &[&'static str]) -> (u16, Vec<&'static str>) {
+    let version = features
+        .iter()
+        .map(|feature| year(feature))
+        .max()
+        .unwrap_or(5);
+    let needing: Vec<&str> = features
+        .iter()
+        .copied()
+        .filter(|feature| year(feature) == version)
+        .collect();
+    (version, needing)
+}
+
+pub fn es_label(version: u16) -> String {
+    format!("ES{}", version)
+}
+
+// oldest first, ES5 before ES2015
+pub fn version_counts(versions: impl Iterator<Item = u16>) -> Vec<(String, usize)> {
+    let mut counts = BTreeMap::new();
+    for version in versions {
+        *counts.entry(version).or_insert(0) += 1;
+    }
+    counts
+        .into_iter()
+        .map(|(version, count)| (es_label(version), count))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::tokenize;
+
+    fn top_level(source: &str) -> bool {
+        top_level_await(&tokenize(source).0).is_some()
+    }
+
+    #[test]
+    fn await_counts_outside_functions_only() {
+        assert!(top_level("await load();"));
+        assert!(top_level("if (ready) { await load(); }"));
+        assert!(top_level("for (const x of xs) { await x; }"));
+        assert!(!top_level("async function f() { await load(); }"));
+        assert!(!top_level("const f = async () => { await load(); };"));
+        assert!(!top_level("const f = async () => await load();"));
+        assert!(!top_level("class A { async m() { await load(); } }"));
+    }
+
+    #[test]
+    fn the_newest_feature_sets_the_version() {
+        let features = features(&tokenize("const a = b?.c ?? (() => 1);").0);
+        assert_eq!(
+            features,
+            vec![
+                "let and const",
+                "optional chaining",
+                "nullish coalescing",
+                "arrow functions"
+            ]
+        );
+        assert_eq!(
+            min_es_version(&features),
+            (2020, vec!["optional chaining", "nullish coalescing"])
+        );
+        assert_eq!(min_es_version(&[]), (5, vec![]));
+        assert!(super::features(&tokenize("await x;").0).contains(&"top-level await"));
+    }
+
+    #[test]
+    fn versions_count_oldest_first() {
+        assert_eq!(
+            version_counts([2020, 5, 2015, 2020].into_iter()),
+            vec![
+                ("ES5".to_string(), 1),
+                ("ES2015".to_string(), 1),
+                ("ES2020".to_string(), 2)
+            ]
+        );
+    }
+}
@@ -0,0 +1,24 @@
+use crate::glob::glob_match;
+
+// a tag the config file gives every package under some paths, for groupings that don't
+// belong in each package.json's "walker": { "tags": [...] }
+pub struct PathTag {
+    pub tag: String,
+    pub paths: Vec<String>,
+}
+
+// the tags whose path globs match a package's path relative to the scan root
+pub fn path_tags<'a>(
+    path_tags: &'a [PathTag],
+    relative_path: &'a str,
+) -> impl Iterator<Item = &'a str> {
+    path_tags
+        .iter()
+        .filter(move |path_tag| {
+            path_tag
+                .paths
+                .iter()
+                .any(|pattern| glob_match(pattern, relative_path))
+        })
+        .map(|path_tag| path_tag.tag.as_str())
+}
@@ -0,0 +1,267 @@
+use crate::args::DependencyClass;
+use crate::graph_export::module_support;
+use crate::identity::{self, ChangeKind, PackageChange};
+use crate::output::{describe_change, format_size};
+use crate::{AnalysisResults, PackageDetails};
+use serde_json::{json, Value};
+
+// how one package differs between the two trees
+pub struct PackageDiff {
+    pub name: String,
+    pub path: String,
+    pub version: (String, String),
+    pub module_support: (&'static str, &'static str),
+    pub size: (u64, u64),
+    // dependencies of the counted classes only one side declares
+    pub added_dependencies: Vec<String>,
+    pub removed_dependencies: Vec<String>,
+}
+
+impl PackageDiff {
+    fn is_empty(&self) -> bool {
+        self.version.0 == self.version.1
+            && self.module_support.0 == self.module_support.1
+            && self.size.0 == self.size.1
+            && self.added_dependencies.is_empty()
+            && self.removed_dependencies.is_empty()
+    }
+}
+
+fn dependency_names<'a>(
+    package_details: &'a PackageDetails,
+    classes: &[DependencyClass],
+) -> Vec<&'a str> {
+    package_details
+        .declared_dependencies
+        .iter()
+        .filter(|dependency| classes.contains(&dependency.class))
+        .map(|dependency| dependency.name.as_str())
+        .collect()
+}
+
+// packages only one tree has, and the differences of the ones both have. Packages are
+// paired by identity, so one that moved between the trees is compared with itself
+pub fn diff_trees(
+    before: &[PackageDetails],
+    after: &[PackageDetails],
+    classes: &[DependencyClass],
+) -> (Vec<PackageChange>, Vec<PackageDiff>) {
+    let changes: Vec<PackageChange> = identity::compare(before, after)
+        .into_iter()
+        .filter(|change| matches!(change.kind, ChangeKind::Added | ChangeKind::Removed))
+        .collect();
+
+    let mut diffs: Vec<PackageDiff> = identity::pair_up(before, after)
+        .into_iter()
+        .map(|(earlier, index)| {
+            let (earlier, package_details) = (&before[earlier], &after[index]);
+            let was = dependency_names(earlier, classes);
+            let is = dependency_names(package_details, classes);
+            PackageDiff {
+                name: package_details.name.clone(),
+                path: package_details.path.display().to_string(),
+                version: (earlier.version.clone(), package_details.version.clone()),
+                module_support: (module_support(earlier), module_support(package_details)),
+                size: (earlier.size, package_details.size),
+                added_dependencies: is
+                    .iter()
+                    .filter(|name| !was.contains(name))
+                    .map(|name| name.to_string())
+                    .collect(),
+                removed_dependencies: was
+                    .iter()
+                    .filter(|name| !is.contains(name))
+                    .map(|name| name.to_string())
+                    .collect(),
+            }
+        })
+        .filter(|diff| !diff.is_empty())
+        .collect();
+    diffs.sort_by(|a, b| a.path.cmp(&b.path).then(a.name.cmp(&b.name)));
+    (changes, diffs)
+}
+
+// how two sets of results differ, for tools built on walker that follow a tree over
+// time: the packages only one side has, and field by field the ones both have that changed
+pub struct ResultsDiff {
+    // added and removed packages
+    pub changes: Vec<PackageChange>,
+    pub changed: Vec<PackageDiff>,
+    // how many packages each side has
+    pub totals: (usize, usize),
+}
+
+// dependencies are compared for the classes given, the ones the report counts
+pub fn diff(
+    before: &AnalysisResults,
+    after: &AnalysisResults,
+    classes: &[DependencyClass],
+) -> ResultsDiff {
+    let (changes, changed) = diff_trees(&before.packages, &after.packages, classes);
+    ResultsDiff {
+        changes,
+        changed,
+        totals: (before.packages.len(), after.packages.len()),
+    }
+}
+
+fn size_change(size: (u64, u64)) -> String {
+    match size.1 >= size.0 {
+        true => format!("+{}", format_size(size.1 - size.0)),
+        false => format!("-{}", format_size(size.0 - size.1)),
+    }
+}
+
+pub fn render_text(labels: (&str, &str), diff: &ResultsDiff) -> String {
+    let (changes, diffs, totals) = (&diff.changes, &diff.changed, diff.totals);
+    let mut out = format!(
+        "Comparing {} with {}: {} packages -> {}, {} added, {} removed, {} changed\n",
+        labels.0,
+        labels.1,
+        totals.0,
+        totals.1,
+        changes
+            .iter()
+            .filter(|change| change.kind == ChangeKind::Added)
+            .count(),
+        changes
+            .iter()
+            .filter(|change| change.kind == ChangeKind::Removed)
+            .count(),
+        diffs.len()
+    );
+    for change in changes {
+        out.push_str(&format!("  {}\n", describe_change(change, None)));
+    }
+    for diff in diffs {
+        out.push_str(&format!("  {} at {}\n", diff.name, diff.path));
+        if diff.version.0 != diff.version.1 {
+            out.push_str(&format!(
+                "    version {} -> {}\n",
+                diff.version.0, diff.version.1
+            ));
+        }
+        if diff.module_support.0 != diff.module_support.1 {
+            out.push_str(&format!(
+                "    module support {} -> {}\n",
+                diff.module_support.0, diff.module_support.1
+            ));
+        }
+        if diff.size.0 != diff.size.1 {
+            out.push_str(&format!(
+                "    size {} -> {} ({})\n",
+                format_size(diff.size.0),
+                format_size(diff.size.1),
+                size_change(diff.size)
+            ));
+        }
+        if !diff.added_dependencies.is_empty() {
+            out.push_str(&format!(
+                "    dependencies added: {}\n",
+                diff.added_dependencies.join(", ")
+            ));
+        }
+        if !diff.removed_dependencies.is_empty() {
+            out.push_str(&format!(
+                "    dependencies removed: {}\n",
+                diff.removed_dependencies.join(", ")
+            ));
+        }
+    }
+    out
+}
+
+pub fn render_json(labels: (&str, &str), diff: &ResultsDiff) -> String {
+    let (changes, diffs, totals) = (&diff.changes, &diff.changed, diff.totals);
+    let side = |change: &PackageChange| {
+        change
+            .before
+            .as_ref()
+            .or(change.after.as_ref())
+            .map(|(version, path)| {
+                json!({
+                    "change": change.kind.label(),
+                    "name": change.name,
+                    "version": version,
+                    "path": path.display().to_string(),
+                })
+            })
+    };
+    let diff = json!({
+        "before": { "tree": labels.0, "packages": totals.0 },
+        "after": { "tree": labels.1, "packages": totals.1 },
+        "packages": changes.iter().filter_map(side).collect::<Vec<Value>>(),
+        "changed": diffs
+            .iter()
+            .map(|diff| {
+                json!({
+                    "name": diff.name,
+                    "path": diff.path,
+                    "version": [diff.version.0, diff.version.1],
+                    "module_support": [diff.module_support.0, diff.module_support.1],
+                    "size": [diff.size.0, diff.size.1],
+                    "dependencies_added": diff.added_dependencies,
+                    "dependencies_removed": diff.removed_dependencies,
+                })
+            })
+            .collect::<Vec<Value>>(),
+    });
+    serde_json::to_string_pretty(&diff).unwrap() + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WalkStatistics;
+    use std::path::PathBuf;
+
+    fn package(name: &str, version: &str, path: &str, size: u64) -> PackageDetails {
+        PackageDetails {
+            name: name.to_string(),
+            version: version.to_string(),
+            path: PathBuf::from(path),
+            manifest_fields: vec!["name".to_string()],
+            size,
+            ..Default::default()
+        }
+    }
+
+    fn results(packages: Vec<PackageDetails>) -> AnalysisResults {
+        AnalysisResults {
+            packages,
+            errors: Vec::new(),
+            timed_out: false,
+            error_limit_reached: false,
+            sample: None,
+            breakdown: None,
+            walk: WalkStatistics::default(),
+            graph: None,
+            comparison: None,
+        }
+    }
+
+    #[test]
+    fn moved_packages_are_compared_with_themselves() {
+        let before = results(vec![
+            package("a", "1.0.0", "packages/a", 10),
+            package("old", "1.0.0", "packages/old", 10),
+        ]);
+        let after = results(vec![
+            package("a", "1.0.0", "libs/a", 20),
+            package("new", "1.0.0", "packages/new", 10),
+        ]);
+        let diff = diff(&before, &after, &[DependencyClass::Prod]);
+        assert_eq!(diff.totals, (2, 2));
+        let changes: Vec<(&str, ChangeKind)> = diff
+            .changes
+            .iter()
+            .map(|change| (change.name.as_str(), change.kind))
+            .collect();
+        assert_eq!(changes.len(), 2);
+        assert!(changes.contains(&("old", ChangeKind::Removed)));
+        assert!(changes.contains(&("new", ChangeKind::Added)));
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].path, "libs/a");
+        assert_eq!(diff.changed[0].size, (10, 20));
+    }
+}
@@ -0,0 +1,219 @@
+use crate::bundlers::resolve_export_target;
+use crate::filesystem::FileSystem;
+use crate::{Finding, PackageDetails};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+// TypeScript's moduleResolution settings that read 'exports'. Both pick the 'types'
+// condition only where it comes first among the ones that match, node16 also reads a
+// declaration file as ESM or CommonJS the way node would read the JavaScript next to it
+#[derive(Clone, Copy, PartialEq)]
+pub enum TsResolution {
+    Node16,
+    Bundler,
+}
+
+impl TsResolution {
+    pub const ALL: [TsResolution; 2] = [TsResolution::Node16, TsResolution::Bundler];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TsResolution::Node16 => "node16",
+            TsResolution::Bundler => "bundler",
+        }
+    }
+
+    pub fn parse(label: &str) -> Option<TsResolution> {
+        match label {
+            // nodenext resolves the same way today
+            "nodenext" => Some(TsResolution::Node16),
+            _ => TsResolution::ALL
+                .into_iter()
+                .find(|resolution| resolution.label() == label),
+        }
+    }
+
+    // the conditions TypeScript matches for `import` and `require`, 'types' first
+    fn conditions(self) -> [&'static [&'static str]; 2] {
+        match self {
+            TsResolution::Node16 => [&["types", "node", "import"], &["types", "node", "require"]],
+            TsResolution::Bundler => [&["types", "import"], &["types", "require"]],
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ModuleFormat {
+    Esm,
+    Cjs,
+}
+
+fn format_label(format: ModuleFormat) -> &'static str {
+    match format {
+        ModuleFormat::Esm => "ESM",
+        ModuleFormat::Cjs => "CommonJS",
+    }
+}
+
+const DECLARATION_EXTENSIONS: [(&str, &str); 3] =
+    [(".d.mts", ".mjs"), (".d.cts", ".cjs"), (".d.ts", ".js")];
+
+fn is_declaration(path: &str) -> bool {
+    DECLARATION_EXTENSIONS
+        .iter()
+        .any(|(declaration, _)| path.ends_with(declaration))
+}
+
+// .mjs and .d.mts are always ESM, .cjs and .d.cts always CommonJS, the rest follow the
+// package's 'type'
+fn module_format(path: &str, esm_type: bool) -> ModuleFormat {
+    if path.ends_with(".mjs") || path.ends_with(".d.mts") {
+        ModuleFormat::Esm
+    } else if path.ends_with(".cjs") || path.ends_with(".d.cts") {
+        ModuleFormat::Cjs
+    } else if esm_type {
+        ModuleFormat::Esm
+    } else {
+        ModuleFormat::Cjs
+    }
+}
+
+// the declaration file TypeScript looks for next to a JavaScript file, None for anything
+// that isn't JavaScript
+fn sibling_declaration(path: &str) -> Option<String> {
+    DECLARATION_EXTENSIONS
+        .iter()
+        .find_map(|(declaration, script)| {
+            Some(format!("{}{}", path.strip_suffix(script)?, declaration))
+        })
+}
+
+fn ships_types(v: &Value) -> bool {
+    fn has_types_condition(target: &Value) -> bool {
+        match target {
+            Value::Object(conditions) => conditions
+                .iter()
+                .any(|(key, target)| key == "types" || has_types_condition(target)),
+            Value::Array(fallbacks) => fallbacks.iter().any(has_types_condition),
+            _ => false,
+        }
+    }
+    v["types"].is_string()
+        || v["typings"].is_string()
+        || v["typesVersions"].is_object()
+        || has_types_condition(&v["exports"])
+}
+
+// checks what TypeScript finds for each 'exports' subpath under `resolution`: declarations
+// for every entrypoint, and under node16 declarations in the same module format as the
+// JavaScript they describe. None for a package that ships no types
+pub fn check_types(
+    filesystem: &dyn FileSystem,
+    v: &Value,
+    package_dir: &Path,
+    resolution: TsResolution,
+) -> Option<Vec<Finding>> {
+    if !ships_types(v) {
+        return None;
+    }
+    let mut findings = Vec::new();
+    let exports = &v["exports"];
+    if exports.is_null() {
+        return Some(findings);
+    }
+
+    if v["typesVersions"].is_object() {
+        findings.push(Finding {
+            rule: "ts-types-versions-ignored".into(),
+            subject: resolution.label().to_string(),
+            message: format!(
+                "'typesVersions' is ignored under {} resolution once there's an 'exports' field, declare the types there",
+                resolution.label()
+            ),
+        });
+    }
+
+    let subpaths: Vec<(&str, &Value)> = match exports.as_object() {
+        Some(subpaths) if subpaths.keys().any(|key| key.starts_with('.')) => subpaths
+            .iter()
+            .map(|(subpath, target)| (subpath.as_str(), target))
+            .collect(),
+        _ => vec![(".", exports)],
+    };
+    let esm_type = v["type"].as_str() == Some("module");
+    for (subpath, target) in subpaths {
+        // patterns can't be checked without expanding them against the files
+        if subpath.contains('*') {
+            continue;
+        }
+        for (kind, conditions) in ["import", "require"]
+            .into_iter()
+            .zip(resolution.conditions())
+        {
+            // only JavaScript entrypoints need types, not json or css ones
+            let (script, sibling) = match resolve_export_target(target, &conditions[1..]) {
+                Some(script) => match sibling_declaration(&script) {
+                    Some(sibling) => (script, sibling),
+                    None => continue,
+                },
+                None => continue,
+            };
+            let types = match resolve_export_target(target, conditions) {
+                Some(types) if is_declaration(&types) => types,
+                _ => match filesystem.is_file(&package_dir.join(&sibling)) {
+                    true => sibling,
+                    false => {
+                        findings.push(Finding {
+                            rule: "ts-types-missing".into(),
+                            subject: format!("{} {} {}", subpath, kind, resolution.label()),
+                            message: format!(
+                                "exports[\"{}\"] has no types for {} under {} resolution, {} has no declaration file, add a 'types' condition first",
+                                subpath,
+                                kind,
+                                resolution.label(),
+                                script
+                            ),
+                        });
+                        continue;
+                    }
+                },
+            };
+
+            let (types_format, script_format) = (
+                module_format(&types, esm_type),
+                module_format(&script, esm_type),
+            );
+            if resolution == TsResolution::Node16 && types_format != script_format {
+                findings.push(Finding {
+                    rule: "ts-types-format".into(),
+                    subject: format!("{} {}", subpath, kind),
+                    message: format!(
+                        "exports[\"{}\"] types {} for {} are read as {} but {} is {}, give each entrypoint its own .d.mts or .d.cts",
+                        subpath,
+                        types,
+                        kind,
+                        format_label(types_format),
+                        script,
+                        format_label(script_format)
+                    ),
+                });
+            }
+        }
+    }
+    findings.dedup_by(|a, b| a.message == b.message);
+    Some(findings)
+}
+
+// resolution mode -> packages whose types resolve cleanly under it, and ones that don't
+pub fn compliance_counts(packages: &[PackageDetails]) -> BTreeMap<&'static str, (usize, usize)> {
+    let mut counts: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    for (resolution, compliant) in packages.iter().filter_map(|p| p.typescript) {
+        let (compliant_count, non_compliant) = counts.entry(resolution.label()).or_default();
+        match compliant {
+            true => *compliant_count += 1,
+            false => *non_compliant += 1,
+        }
+    }
+    counts
+}
@@ -0,0 +1,30 @@
+use super::Vcs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub struct Git {
+    pub root: PathBuf,
+}
+
+impl Vcs for Git {
+    fn name(&self) -> &'static str {
+        "git"
+    }
+
+    fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn file_log(&self) -> Command {
+        let mut command = Command::new("git");
+        command.arg("-C").arg(&self.root).args([
+            "-c",
+            "core.quotePath=false",
+            "log",
+            "--no-renames",
+            "--name-only",
+            "--format=%x00%ct",
+        ]);
+        command
+    }
+}
@@ -0,0 +1,41 @@
+use super::Vcs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// {date|hgdate} is the unix timestamp followed by the timezone offset, the offset is
+// ignored when the log is read
+const FILE_LOG_TEMPLATE: &str = "\\0{date|hgdate}\\n{join(files, '\\n')}\\n";
+
+// Mercurial, and Sapling which kept its command line and templates
+pub struct Mercurial {
+    pub root: PathBuf,
+    pub sapling: bool,
+}
+
+impl Vcs for Mercurial {
+    fn name(&self) -> &'static str {
+        match self.sapling {
+            true => "sapling",
+            false => "mercurial",
+        }
+    }
+
+    fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn file_log(&self) -> Command {
+        let binary = match self.sapling {
+            true => "sl",
+            false => "hg",
+        };
+        let mut command = Command::new(binary);
+        command
+            .arg("--cwd")
+            .arg(&self.root)
+            .args(["log", "--template", FILE_LOG_TEMPLATE]);
+        // plain output whatever the user's config says
+        command.env("HGPLAIN", "1");
+        command
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::{BTreeSet, HashMap};
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+mod git;
+mod mercurial;
+mod remote;
+
+pub use git::Git;
+pub use mercurial::Mercurial;
+pub use remote::{checkout, checkout_ref, Checkout, RemoteRepo};
+
+pub const DEFAULT_CHURN_WINDOW: Duration = Duration::from_secs(90 * 24 * 60 * 60);
+
+// when a package was first and last committed to, unix timestamps, and how many commits
+// touched it since `since`
+#[derive(Clone)]
+pub struct PackageHistory {
+    pub created: u64,
+    pub last_changed: u64,
+    pub since: u64,
+    pub commits: usize,
+}
+
+// a version control system the repository is kept in
+pub trait Vcs {
+    fn name(&self) -> &'static str;
+    fn root(&self) -> &Path;
+    // a log of every commit, newest first, each a "\0<timestamp>" line followed by the
+    // files it changed relative to the root, one per line
+    fn file_log(&self) -> Command;
+}
+
+// the repository holding dir, from the marker directory of the innermost one
+pub fn find_repository(dir: &Path) -> Option<Box<dyn Vcs>> {
+    dir.ancestors().find_map(|ancestor| {
+        let root = ancestor.to_path_buf();
+        if ancestor.join(".git").exists() {
+            Some(Box::new(Git { root }) as Box<dyn Vcs>)
+        } else if ancestor.join(".hg").is_dir() {
+            Some(Box::new(Mercurial {
+                root,
+                sapling: false,
+            }))
+        } else if ancestor.join(".sl").is_dir() {
+            Some(Box::new(Mercurial {
+                root,
+                sapling: true,
+            }))
+        } else {
+            None
+        }
+    })
+}
+
+// the history of every package directory, from a single log over the whole repository
+// rather than one per package. A commit counts for the innermost package holding one of
+// the files it changed, so nested packages don't inflate their parents
+pub fn package_histories(
+    vcs: &dyn Vcs,
+    package_dirs: &[&Path],
+    since: u64,
+) -> io::Result<Vec<Option<PackageHistory>>> {
+    let by_dir: HashMap<&Path, usize> = package_dirs
+        .iter()
+        .enumerate()
+        .filter_map(|(index, dir)| Some((dir.strip_prefix(vcs.root()).ok()?, index)))
+        .collect();
+
+    let mut child = vcs
+        .file_log()
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+
+    let mut histories: Vec<Option<PackageHistory>> = package_dirs.iter().map(|_| None).collect();
+    let mut record = |time: u64, touched: &BTreeSet<usize>| {
+        for &package in touched {
+            let history = histories[package].get_or_insert(PackageHistory {
+                created: time,
+                last_changed: time,
+                since,
+                commits: 0,
+            });
+            history.created = history.created.min(time);
+            history.last_changed = history.last_changed.max(time);
+            if time >= since {
+                history.commits += 1;
+            }
+        }
+    };
+
+    let mut commit: Option<u64> = None;
+    let mut touched = BTreeSet::new();
+    for line in BufReader::new(stdout).lines() {
+        let line = line?;
+        if let Some(time) = line.strip_prefix('\0') {
+            if let Some(time) = commit {
+                record(time, &touched);
+            }
+            commit = time.split_whitespace().next().and_then(|t| t.parse().ok());
+            touched.clear();
+            continue;
+        }
+        if line.is_empty() {
+            continue;
+        }
+        let package = Path::new(&line)
+            .ancestors()
+            .skip(1)
+            .find_map(|dir| by_dir.get(dir));
+        if let Some(&package) = package {
+            touched.insert(package);
+        }
+    }
+    if let Some(time) = commit {
+        record(time, &touched);
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(io::Error::other(message));
+    }
+    Ok(histories)
+}
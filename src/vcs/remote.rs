@@ -0,0 +1,160 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// a repository to clone rather than scan in place, `url#branch-or-tag`
+pub struct RemoteRepo {
+    pub url: String,
+    pub reference: Option<String>,
+}
+
+impl RemoteRepo {
+    pub fn parse(value: &str) -> Result<RemoteRepo, String> {
+        let (url, reference) = match value.rsplit_once('#') {
+            Some((url, reference)) => (url, Some(reference.to_string())),
+            None => (value, None),
+        };
+        if url.is_empty() || reference.as_deref() == Some("") {
+            return Err(format!(
+                "Invalid --git '{}', expected a repository URL and an optional #branch",
+                value
+            ));
+        }
+        Ok(RemoteRepo {
+            url: url.to_string(),
+            reference,
+        })
+    }
+
+    // a directory name for the repository in a cache, one per URL and branch
+    fn cache_name(&self) -> String {
+        let name = match &self.reference {
+            Some(reference) => format!("{}#{}", self.url, reference),
+            None => self.url.clone(),
+        };
+        name.chars()
+            .map(
+                |c| match c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+                    true => c,
+                    false => '-',
+                },
+            )
+            .collect()
+    }
+}
+
+// a working copy of a remote repository. One in a temporary directory is removed when
+// it's dropped, one in a cache directory is kept for the next run to update. process::exit
+// doesn't run Drop, so a checkout has to be dropped before any exit while it's in use
+pub struct Checkout {
+    pub path: PathBuf,
+    temporary: bool,
+}
+
+impl Drop for Checkout {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+fn run_git(command: &mut Command) -> io::Result<()> {
+    let output = command.output()?;
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        )),
+    }
+}
+
+fn temporary_dir() -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or(0);
+    env::temp_dir().join(format!("walker-{}-{}", process::id(), nanos))
+}
+
+// a commit of the git repository in `repo_dir` checked out on its own, for scanning a
+// branch or tag next to the working copy. The clone shares the repository's objects, so
+// nothing is copied or fetched, and it's removed when dropped
+pub fn checkout_ref(repo_dir: &Path, reference: &str) -> io::Result<Checkout> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo_dir)
+        .args(["rev-parse", "--verify", "--quiet"])
+        .arg(format!("{}^{{commit}}", reference))
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("'{}' is neither a directory nor a git ref", reference),
+        ));
+    }
+    let commit = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+    let checkout = Checkout {
+        path: temporary_dir(),
+        temporary: true,
+    };
+    run_git(
+        Command::new("git")
+            .args(["clone", "--quiet", "--shared", "--no-checkout", "--"])
+            .arg(repo_dir)
+            .arg(&checkout.path),
+    )?;
+    run_git(
+        Command::new("git")
+            .arg("-C")
+            .arg(&checkout.path)
+            .args(["checkout", "--quiet", "--detach", &commit]),
+    )?;
+    Ok(checkout)
+}
+
+// a shallow clone of the repository's branch, or of its default branch without one. A
+// clone already in the cache directory is fetched and checked out again instead
+pub fn checkout(remote: &RemoteRepo, cache_dir: Option<&Path>) -> io::Result<Checkout> {
+    let checkout = match cache_dir {
+        Some(cache_dir) => Checkout {
+            path: cache_dir.join(remote.cache_name()),
+            temporary: false,
+        },
+        None => Checkout {
+            path: temporary_dir(),
+            temporary: true,
+        },
+    };
+
+    if checkout.path.join(".git").exists() {
+        let reference = remote.reference.as_deref().unwrap_or("HEAD");
+        run_git(Command::new("git").arg("-C").arg(&checkout.path).args([
+            "fetch", "--quiet", "--depth", "1", "origin", "--", reference,
+        ]))?;
+        run_git(Command::new("git").arg("-C").arg(&checkout.path).args([
+            "checkout",
+            "--quiet",
+            "--force",
+            "--detach",
+            "FETCH_HEAD",
+        ]))?;
+        return Ok(checkout);
+    }
+
+    if let Some(parent) = checkout.path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut clone = Command::new("git");
+    clone.args(["clone", "--quiet", "--depth", "1", "--single-branch"]);
+    if let Some(reference) = &remote.reference {
+        clone.arg("--branch").arg(reference);
+    }
+    clone.arg("--").arg(&remote.url).arg(&checkout.path);
+    run_git(&mut clone)?;
+    Ok(checkout)
+}
@@ -0,0 +1,166 @@
+use crate::filesystem::FileSystem;
+use crate::graph_export::module_support;
+use crate::identity;
+use crate::output::{describe_change, format_size};
+use crate::{AnalysisResults, PackageDetails, Scan};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+// how often the tree is looked at, polling works the same on every platform and every
+// filesystem, network mounts included
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(PartialEq)]
+struct DirState {
+    // changes when an entry is added, removed or renamed
+    modified: Option<SystemTime>,
+    // when its package.json last changed and its size
+    manifest: Option<(Option<SystemTime>, u64)>,
+}
+
+// the directories a scan walked through, as they were when it finished. Directories it
+// left out, excluded or skipped, aren't watched
+pub struct Snapshot {
+    dirs: BTreeMap<PathBuf, DirState>,
+}
+
+fn dir_state(filesystem: &dyn FileSystem, dir: &Path) -> Option<DirState> {
+    let metadata = filesystem.metadata(dir).ok()?;
+    let manifest = filesystem
+        .metadata(&dir.join("package.json"))
+        .ok()
+        .map(|manifest| (manifest.modified, manifest.len));
+    Some(DirState {
+        modified: metadata.modified,
+        manifest,
+    })
+}
+
+pub fn snapshot(scan: &Scan) -> Snapshot {
+    let dirs = scan
+        .visited
+        .values()
+        .filter_map(|dir| Some((dir.clone(), dir_state(scan.filesystem, dir)?)))
+        .collect();
+    Snapshot { dirs }
+}
+
+// the directories whose package.json or entries changed since the snapshot, or that are
+// gone
+pub fn changes(filesystem: &dyn FileSystem, snapshot: &Snapshot) -> Vec<PathBuf> {
+    snapshot
+        .dirs
+        .iter()
+        .filter(|(dir, state)| dir_state(filesystem, dir).as_ref() != Some(*state))
+        .map(|(dir, _)| dir.clone())
+        .collect()
+}
+
+// blocks until something changes. Installs and editors write in bursts, so it then waits
+// for a poll that finds nothing new before reporting what changed
+pub fn wait_for_changes(filesystem: &dyn FileSystem, snapshot: &Snapshot) -> Vec<PathBuf> {
+    let mut changed = Vec::new();
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        let now_changed = changes(filesystem, snapshot);
+        if !changed.is_empty() && now_changed == changed {
+            return changed;
+        }
+        changed = now_changed;
+    }
+}
+
+fn module_support_label(package_details: &PackageDetails) -> &'static str {
+    match module_support(package_details) {
+        "dual" => "ESM and CommonJS",
+        "esm" => "ESM only",
+        "cjs" => "CommonJS only",
+        _ => "unknown",
+    }
+}
+
+fn signed(before: u64, after: u64, format: fn(u64) -> String) -> String {
+    match after >= before {
+        true => format!("+{}", format(after - before)),
+        false => format!("-{}", format(before - after)),
+    }
+}
+
+// what one analysis of the tree changed from the one before it, a line each: packages
+// added, removed or moved, a different module support or size, errors that are new.
+// Packages are matched by path, the tree is the same one
+pub fn deltas(before: &AnalysisResults, after: &AnalysisResults) -> Vec<String> {
+    let mut lines: Vec<String> = identity::compare(&before.packages, &after.packages)
+        .iter()
+        .map(|change| describe_change(change, None))
+        .collect();
+
+    let earlier: HashMap<&Path, &PackageDetails> = before
+        .packages
+        .iter()
+        .map(|p| (p.path.as_path(), p))
+        .collect();
+    for p in &after.packages {
+        let earlier = match earlier.get(p.path.as_path()) {
+            Some(earlier) => earlier,
+            None => continue,
+        };
+        let (was, is) = (module_support_label(earlier), module_support_label(p));
+        if was != is {
+            lines.push(format!("{}: {} -> {}", p.name, was, is));
+        }
+        if earlier.size != p.size {
+            lines.push(format!(
+                "{}: size {} -> {} ({})",
+                p.name,
+                format_size(earlier.size),
+                format_size(p.size),
+                signed(earlier.size, p.size, format_size)
+            ));
+        }
+        for error in p
+            .errors
+            .iter()
+            .filter(|error| !earlier.errors.contains(error))
+        {
+            lines.push(format!("{}: new error: {}", p.name, error));
+        }
+    }
+    for error in after
+        .errors
+        .iter()
+        .filter(|error| !before.errors.contains(error))
+    {
+        lines.push(format!("new error: {}", error));
+    }
+    lines
+}
+
+// the totals after the change, with how much they moved
+pub fn totals(before: &AnalysisResults, after: &AnalysisResults) -> String {
+    let size = |results: &AnalysisResults| results.packages.iter().map(|p| p.size).sum::<u64>();
+    let errors = |results: &AnalysisResults| {
+        results.errors.len()
+            + results
+                .packages
+                .iter()
+                .map(|p| p.errors.len())
+                .sum::<usize>()
+    };
+    let count = |count: u64| count.to_string();
+    format!(
+        "{} packages ({}), {} ({}), {} errors ({})",
+        after.packages.len(),
+        signed(
+            before.packages.len() as u64,
+            after.packages.len() as u64,
+            count
+        ),
+        format_size(size(after)),
+        signed(size(before), size(after), format_size),
+        errors(after),
+        signed(errors(before) as u64, errors(after) as u64, count)
+    )
+}
@@ -0,0 +1,212 @@
+use crate::filesystem::FileSystem;
+use crate::glob::glob_match;
+use crate::PackageDetails;
+use serde_json::Value;
+use std::path::{Component, Path, PathBuf};
+
+// "./packages/*/" and "packages/*" are the same pattern
+fn normalize(pattern: &str) -> String {
+    let (negated, pattern) = match pattern.strip_prefix('!') {
+        Some(pattern) => ("!", pattern),
+        None => ("", pattern),
+    };
+    let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+    format!("{}{}", negated, pattern)
+}
+
+// npm and yarn's "workspaces": ["packages/*"], or yarn's { "packages": [...] }
+pub fn manifest_patterns(v: &Value) -> Vec<String> {
+    let patterns = match &v["workspaces"] {
+        Value::Array(patterns) => patterns,
+        Value::Object(_) => match v["workspaces"]["packages"].as_array() {
+            Some(patterns) => patterns,
+            None => return Vec::new(),
+        },
+        _ => return Vec::new(),
+    };
+    patterns
+        .iter()
+        .filter_map(Value::as_str)
+        .map(normalize)
+        .collect()
+}
+
+// the `packages:` list of a pnpm-workspace.yaml next to the package.json, adding its size
+// to `bytes_read`
+pub fn pnpm_patterns(filesystem: &dyn FileSystem, dir: &Path, bytes_read: &mut u64) -> Vec<String> {
+    let contents = match filesystem.read_to_string(&dir.join("pnpm-workspace.yaml")) {
+        Ok(contents) => contents,
+        Err(_) => return Vec::new(),
+    };
+    *bytes_read += contents.len() as u64;
+    packages_list(&contents)
+}
+
+// only the `packages:` list is read, as plain or quoted block items
+fn packages_list(contents: &str) -> Vec<String> {
+    let mut patterns = Vec::new();
+    let mut in_packages = false;
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        // a top-level key starts or ends the list
+        if !line.starts_with(char::is_whitespace) && !trimmed.starts_with('-') {
+            in_packages = trimmed.trim_end() == "packages:";
+            continue;
+        }
+        if let (true, Some(item)) = (in_packages, trimmed.strip_prefix('-')) {
+            let item = item.split(" #").next().unwrap_or_default().trim();
+            let item = item.trim_matches(|c| c == '\'' || c == '"');
+            if !item.is_empty() {
+                patterns.push(normalize(item));
+            }
+        }
+    }
+    patterns
+}
+
+// whether a workspace root's .npmrc turns off link-workspace-packages, adding its size to
+// `bytes_read`. Its members' dependencies by plain version range then come from the
+// registry, only workspace: ones link. Left unset it counts as on, as npm and yarn do
+pub fn unlinked_workspace_packages(
+    filesystem: &dyn FileSystem,
+    dir: &Path,
+    bytes_read: &mut u64,
+) -> bool {
+    let contents = match filesystem.read_to_string(&dir.join(".npmrc")) {
+        Ok(contents) => contents,
+        Err(_) => return false,
+    };
+    *bytes_read += contents.len() as u64;
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#') && !line.starts_with(';'))
+        .filter_map(|line| line.split_once('='))
+        .rfind(|(key, _)| key.trim() == "link-workspace-packages")
+        .is_some_and(|(_, value)| value.trim() == "false")
+}
+
+fn in_node_modules(relative_path: &Path) -> bool {
+    relative_path
+        .components()
+        .any(|component| component == Component::Normal("node_modules".as_ref()))
+}
+
+// marks the packages each workspace root's patterns take in, the nearest root winning
+// when workspaces nest. Packages installed under node_modules never are members, even
+// when a pattern like "**" reaches them
+pub fn assign_members(packages: &mut [PackageDetails]) {
+    let mut roots: Vec<(PathBuf, Vec<String>)> = packages
+        .iter()
+        .filter(|p| !p.workspace_patterns.is_empty())
+        .map(|p| (p.path.clone(), p.workspace_patterns.clone()))
+        .collect();
+    // deepest first, so the first root that matches is the nearest
+    roots.sort_by(|(a, _), (b, _)| {
+        b.components()
+            .count()
+            .cmp(&a.components().count())
+            .then(a.cmp(b))
+    });
+
+    for package_details in packages.iter_mut() {
+        for (root, patterns) in &roots {
+            if package_details.path == *root {
+                package_details.workspace_root = Some(root.clone());
+                break;
+            }
+            let relative_path = match package_details.path.strip_prefix(root) {
+                Ok(relative_path) if !in_node_modules(relative_path) => relative_path,
+                _ => continue,
+            };
+            let relative_path = relative_path.to_string_lossy();
+            let included = patterns
+                .iter()
+                .filter(|pattern| !pattern.starts_with('!'))
+                .any(|pattern| glob_match(pattern, &relative_path));
+            let excluded = patterns
+                .iter()
+                .filter_map(|pattern| pattern.strip_prefix('!'))
+                .any(|pattern| glob_match(pattern, &relative_path));
+            if included && !excluded {
+                package_details.workspace_member = true;
+                package_details.workspace_root = Some(root.clone());
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn manifest_patterns_are_normalized() {
+        assert_eq!(
+            manifest_patterns(&json!({ "workspaces": ["./packages/*/", "!packages/old"] })),
+            vec!["packages/*", "!packages/old"]
+        );
+        assert_eq!(
+            manifest_patterns(&json!({ "workspaces": { "packages": ["apps/*"] } })),
+            vec!["apps/*"]
+        );
+        assert!(manifest_patterns(&json!({ "workspaces": "packages/*" })).is_empty());
+    }
+
+    #[test]
+    fn reads_only_the_packages_list() {
+        let contents = "\
+# the workspace
+packages:
+  - 'packages/*'
+  - \"apps/*\"  # the apps
+  - ./tools/
+
+  - '!**/test/**'
+catalog:
+  - not-a-pattern
+";
+        assert_eq!(
+            packages_list(contents),
+            vec!["packages/*", "apps/*", "tools", "!**/test/**"]
+        );
+        assert!(packages_list("catalog:\n  react: ^18\n").is_empty());
+    }
+
+    #[test]
+    fn the_nearest_root_takes_members() {
+        let package = |path: &str, patterns: &[&str]| PackageDetails {
+            path: PathBuf::from(path),
+            workspace_patterns: patterns.iter().map(|p| p.to_string()).collect(),
+            ..Default::default()
+        };
+        let mut packages = vec![
+            package("/repo", &["packages/*"]),
+            package("/repo/packages/app", &[]),
+            package("/repo/packages/tools", &["*"]),
+            package("/repo/packages/tools/lint", &[]),
+            package("/repo/packages/app/node_modules/dep", &[]),
+        ];
+        assign_members(&mut packages);
+        let roots: Vec<Option<&str>> = packages
+            .iter()
+            .map(|p| p.workspace_root.as_deref().and_then(Path::to_str))
+            .collect();
+        assert_eq!(
+            roots,
+            vec![
+                Some("/repo"),
+                Some("/repo"),
+                Some("/repo/packages/tools"),
+                Some("/repo/packages/tools"),
+                None
+            ]
+        );
+        assert!(packages[1].workspace_member && !packages[4].workspace_member);
+    }
+}